@@ -0,0 +1,352 @@
+use crate::analysis::SymbolTable;
+use iced_x86::{
+  BlockEncoder, BlockEncoderOptions, Code, Instruction, InstructionBlock, MemoryOperand, Mnemonic,
+  OpCodeOperandKind, Register,
+};
+
+// Spellings iced folds into one mnemonic
+const MNEMONIC_ALIASES: [(&str, Mnemonic); 16] = [
+  ("jz", Mnemonic::Je),
+  ("jnz", Mnemonic::Jne),
+  ("jc", Mnemonic::Jb),
+  ("jnae", Mnemonic::Jb),
+  ("jnc", Mnemonic::Jae),
+  ("jnb", Mnemonic::Jae),
+  ("jna", Mnemonic::Jbe),
+  ("jnbe", Mnemonic::Ja),
+  ("jpe", Mnemonic::Jp),
+  ("jpo", Mnemonic::Jnp),
+  ("jnge", Mnemonic::Jl),
+  ("jnl", Mnemonic::Jge),
+  ("jng", Mnemonic::Jle),
+  ("jnle", Mnemonic::Jg),
+  ("retn", Mnemonic::Ret),
+  ("sal", Mnemonic::Shl),
+];
+
+#[derive(Debug, Clone, Copy)]
+enum Operand {
+  Register(Register),
+  Immediate(i64), // Numbers, and branch targets given as numbers or names
+  Memory(MemoryOperand, Option<usize>), // The size from byte/word/dword/qword ptr
+}
+
+// Assembles one instruction in Intel syntax at the address, trying every encoding of the mnemonic
+// and keeping the shortest one iced accepts, operands are registers, numbers, names and [base+index*scale+disp]
+pub fn assemble(
+  text: &str,
+  ip: u64,
+  bitness: u32,
+  symbols: &SymbolTable,
+) -> Result<Vec<u8>, String> {
+  if bitness != 64 && ip > u64::from(u32::MAX) {
+    return Err(format!(
+      "{:#x} is out of range for {}-bit code",
+      ip, bitness
+    ));
+  }
+  let text = text.trim().to_lowercase();
+  let (name, operands) = match text.split_once(char::is_whitespace) {
+    Some((name, operands)) => (name, operands.trim()),
+    None => (text.as_str(), ""),
+  };
+  let mnemonic = parse_mnemonic(name).ok_or(format!("Unknown mnemonic {}", name))?;
+  let operands = if operands.is_empty() {
+    vec![]
+  } else {
+    operands
+      .split(',')
+      .map(|x| parse_operand(x.trim(), bitness, symbols))
+      .collect::<Result<Vec<Operand>, String>>()?
+  };
+  // without a register to go by, the size of a memory operand has to be spelled out
+  let sized = operands.iter().any(|x| match x {
+    Operand::Memory(_, size) => size.is_some(),
+    Operand::Register(_) => true,
+    Operand::Immediate(_) => false,
+  });
+  if !sized && operands.iter().any(|x| matches!(x, Operand::Memory(..))) {
+    return Err("Give the memory operand a size, e.g. dword ptr [eax]".to_owned());
+  }
+
+  Code::values()
+    .filter(|code| code.mnemonic() == mnemonic)
+    .filter(|code| {
+      let op_code = code.op_code();
+      op_code.is_instruction()
+        && op_code.op_count() as usize == operands.len()
+        && match bitness {
+          64 => op_code.mode64(),
+          32 => op_code.mode32(),
+          _ => op_code.mode16(),
+        }
+    })
+    .filter_map(|code| build_instruction(code, &operands, bitness))
+    .filter_map(|instr| {
+      BlockEncoder::encode(
+        bitness,
+        InstructionBlock::new(&[instr], ip),
+        BlockEncoderOptions::NONE,
+      )
+      .ok()
+    })
+    .map(|x| x.code_buffer)
+    .min_by_key(|x| x.len())
+    .ok_or(format!("Can't encode {} in {}-bit code", text, bitness))
+}
+
+// 0x1000 and 1000h are hex, plain digits are decimal
+pub fn parse_number(text: &str) -> Option<u64> {
+  let text = text.replace('_', "");
+  if let Some(hex) = text
+    .strip_prefix("0x")
+    .or_else(|| text.strip_prefix("0X"))
+    .or_else(|| text.strip_suffix('h'))
+    .or_else(|| text.strip_suffix('H'))
+  {
+    return u64::from_str_radix(hex, 16).ok();
+  }
+  text.parse::<u64>().ok()
+}
+
+fn parse_mnemonic(name: &str) -> Option<Mnemonic> {
+  MNEMONIC_ALIASES
+    .iter()
+    .find(|(alias, _)| *alias == name)
+    .map(|(_, mnemonic)| *mnemonic)
+    .or_else(|| {
+      Mnemonic::values()
+        .filter(|x| *x != Mnemonic::INVALID)
+        .find(|x| format!("{:?}", x).eq_ignore_ascii_case(name))
+    })
+}
+
+fn parse_register(name: &str) -> Option<Register> {
+  Register::values()
+    .filter(|x| *x != Register::None)
+    .find(|x| format!("{:?}", x).eq_ignore_ascii_case(name))
+}
+
+fn parse_signed(text: &str) -> Option<i64> {
+  match text.strip_prefix('-') {
+    Some(rest) => parse_number(rest.trim()).map(|x| (x as i64).wrapping_neg()),
+    None => parse_number(text).map(|x| x as i64),
+  }
+}
+
+fn parse_operand(text: &str, bitness: u32, symbols: &SymbolTable) -> Result<Operand, String> {
+  if let Some(register) = parse_register(text) {
+    return Ok(Operand::Register(register));
+  }
+  if let Some(value) = parse_signed(text) {
+    return Ok(Operand::Immediate(value));
+  }
+
+  let (size, rest) = match text.split_once("ptr") {
+    Some((size, rest)) => {
+      let size = match size.trim() {
+        "byte" => 1,
+        "word" => 2,
+        "dword" => 4,
+        "qword" => 8,
+        other => return Err(format!("Unknown operand size {}", other)),
+      };
+      (Some(size), rest.trim())
+    }
+    None => (None, text),
+  };
+  if let Some(inner) = rest.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+    return Ok(Operand::Memory(
+      parse_memory(inner, bitness, symbols)?,
+      size,
+    ));
+  }
+
+  // the names the listing shows, for branch targets
+  symbols
+    .address(text)
+    .map(|x| Operand::Immediate(x as i64))
+    .ok_or(format!("Unknown operand {}", text))
+}
+
+// base+index*scale+displacement, in any order
+fn parse_memory(text: &str, bitness: u32, symbols: &SymbolTable) -> Result<MemoryOperand, String> {
+  let mut base = Register::None;
+  let mut index = Register::None;
+  let mut scale = 1;
+  let mut displacement: i64 = 0;
+  let terms = text
+    .replace('-', "+-")
+    .split('+')
+    .map(|x| x.trim().to_owned())
+    .filter(|x| !x.is_empty())
+    .collect::<Vec<String>>();
+  for term in &terms {
+    if let Some((left, right)) = term.split_once('*') {
+      let (register, factor) = match (parse_register(left.trim()), parse_register(right.trim())) {
+        (Some(register), None) => (register, right.trim()),
+        (None, Some(register)) => (register, left.trim()),
+        _ => return Err(format!("Can't scale {}", term)),
+      };
+      index = register;
+      scale = parse_number(factor)
+        .filter(|x| matches!(x, 1 | 2 | 4 | 8))
+        .ok_or(format!("The scale in {} has to be 1, 2, 4 or 8", term))? as u32;
+    } else if let Some(register) = parse_register(term) {
+      // the listing shows the target of rip relative operands, so that's what is typed
+      if register == Register::RIP || register == Register::EIP {
+        return Err("Write the target address instead of rip".to_owned());
+      }
+      if base == Register::None {
+        base = register;
+      } else {
+        index = register;
+      }
+    } else if let Some(value) = parse_signed(term) {
+      displacement = displacement.wrapping_add(value);
+    } else if let Some(address) = symbols.address(term) {
+      displacement = displacement.wrapping_add(address as i64);
+    } else {
+      return Err(format!("Unknown address term {}", term));
+    }
+  }
+
+  // 64-bit code reaches absolute addresses relative to the instruction, the displacement is the target
+  if bitness == 64 && base == Register::None && index == Register::None {
+    base = Register::RIP;
+  }
+  // iced wants 8 for a full displacement on a 64-bit base, it still encodes 4 bytes
+  let displacement_size = if base == Register::RIP {
+    8
+  } else if displacement == 0 {
+    0
+  } else if i8::try_from(displacement).is_ok() && base != Register::None {
+    1
+  } else if base.is_gpr64() {
+    8
+  } else {
+    4
+  };
+  Ok(MemoryOperand::new(
+    base,
+    index,
+    scale,
+    displacement,
+    displacement_size,
+    false,
+    Register::None,
+  ))
+}
+
+fn build_instruction(code: Code, operands: &[Operand], bitness: u32) -> Option<Instruction> {
+  let branch_size = match code.op_code().op_kind(0) {
+    OpCodeOperandKind::br16_1 | OpCodeOperandKind::br16_2 => Some(16),
+    OpCodeOperandKind::br32_1 | OpCodeOperandKind::br32_4 => Some(32),
+    OpCodeOperandKind::br64_1 | OpCodeOperandKind::br64_4 => Some(64),
+    _ => None,
+  };
+  // the block encoder can't fix up a branch with an operand size other than the code's
+  if branch_size.is_some_and(|x| x != bitness) {
+    return None;
+  }
+  let instr = match operands {
+    [] => Instruction::with(code),
+    [Operand::Immediate(target)] if branch_size.is_some() => {
+      Instruction::with_branch(code, *target as u64).ok()?
+    }
+    _ if branch_size.is_some() => return None,
+    [Operand::Register(a)] => Instruction::with1(code, *a).ok()?,
+    [Operand::Immediate(a)] => Instruction::with1(code, i32::try_from(*a).ok()?).ok()?,
+    [Operand::Memory(a, _)] => Instruction::with1(code, *a).ok()?,
+    [Operand::Register(a), Operand::Register(b)] => Instruction::with2(code, *a, *b).ok()?,
+    [Operand::Register(a), Operand::Immediate(b)] => Instruction::with2(code, *a, *b).ok()?,
+    [Operand::Register(a), Operand::Memory(b, _)] => Instruction::with2(code, *a, *b).ok()?,
+    [Operand::Memory(a, _), Operand::Register(b)] => Instruction::with2(code, *a, *b).ok()?,
+    [Operand::Memory(a, _), Operand::Immediate(b)] => {
+      Instruction::with2(code, *a, i32::try_from(*b).ok()?).ok()?
+    }
+    _ => return None,
+  };
+  // the same operands fit encodings of every size, keep the one that was asked for
+  let size = operands.iter().find_map(|x| match x {
+    Operand::Memory(_, size) => *size,
+    _ => None,
+  });
+  match size {
+    Some(size) if instr.memory_size().size() != size => None,
+    _ => Some(instr),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn asm(text: &str, ip: u64, bitness: u32) -> Result<Vec<u8>, String> {
+    assemble(text, ip, bitness, &SymbolTable::default())
+  }
+
+  #[test]
+  fn registers_and_immediates() {
+    assert_eq!(asm("nop", 0x1000, 64), Ok(vec![0x90]));
+    assert_eq!(asm("xor eax, eax", 0x1000, 64), Ok(vec![0x31, 0xc0]));
+    assert_eq!(
+      asm("sub rsp, 0x20", 0x1000, 64),
+      Ok(vec![0x48, 0x83, 0xec, 0x20])
+    );
+    assert_eq!(
+      asm("MOV ECX, 10h", 0x1000, 32),
+      Ok(vec![0xb9, 0x10, 0, 0, 0])
+    );
+  }
+
+  #[test]
+  fn branches_are_relative_to_the_address() {
+    assert_eq!(asm("jmp 0x1010", 0x1000, 64), Ok(vec![0xeb, 0x0e]));
+    assert_eq!(asm("jz 0x1000", 0x1000, 32), Ok(vec![0x74, 0xfe]));
+    assert_eq!(
+      asm("call 0x2000", 0x1000, 64),
+      Ok(vec![0xe8, 0xfb, 0x0f, 0x00, 0x00])
+    );
+  }
+
+  #[test]
+  fn memory_operands() {
+    assert_eq!(
+      asm("mov eax, dword ptr [rbx+rcx*4+8]", 0x1000, 64),
+      Ok(vec![0x8b, 0x44, 0x8b, 0x08])
+    );
+    assert_eq!(
+      asm("mov ecx, dword ptr [rdx+rax*4+0x1100]", 0x1000, 64),
+      Ok(vec![0x8b, 0x8c, 0x82, 0x00, 0x11, 0x00, 0x00])
+    );
+    assert_eq!(
+      asm("mov dword ptr [ebp-4], 0", 0x1000, 32),
+      Ok(vec![0xc7, 0x45, 0xfc, 0, 0, 0, 0])
+    );
+    // an absolute address in 64-bit code is reached relative to the next instruction
+    assert_eq!(
+      asm("mov rax, qword ptr [0x2000]", 0x1000, 64),
+      Ok(vec![0x48, 0x8b, 0x05, 0xf9, 0x0f, 0x00, 0x00])
+    );
+  }
+
+  #[test]
+  fn names_from_the_symbol_table() {
+    let labels = std::collections::BTreeMap::from([(0x1020, "done".to_owned())]);
+    let symbols = SymbolTable::default().with_labels(&labels);
+    assert_eq!(
+      assemble("jmp done", 0x1000, 64, &symbols),
+      Ok(vec![0xeb, 0x1e])
+    );
+  }
+
+  #[test]
+  fn rejected_input() {
+    assert!(asm("frobnicate eax", 0x1000, 64).is_err());
+    assert!(asm("mov [rax], 1", 0x1000, 64).is_err());
+    assert!(asm("lea rax, [rip+0x10]", 0x1000, 64).is_err());
+    assert!(asm("mov eax, [ebx*3]", 0x1000, 32).is_err());
+    assert!(asm("nop", 0x1_0000_0000, 32).is_err());
+  }
+}
@@ -0,0 +1,333 @@
+use crate::analysis::functions::instruction_index;
+use crate::analysis::{BasicBlock, ControlFlowGraph, Edge, EdgeKind, Function};
+use crate::parser::{InstructionData, JumpTable, SectionData};
+use iced_x86::{Code, FlowControl, Instruction, Mnemonic};
+use std::collections::BTreeSet;
+
+// Splits a function into basic blocks, a block ends at a terminator or right before a branch target.
+// Calls return to the next instruction, so they stay inside the block and only add a Call edge
+pub fn build_cfg(section: &SectionData, function: &Function) -> ControlFlowGraph {
+  let instructions = &section.data;
+  let first = match instruction_index(instructions, function.start) {
+    Some(index) => index,
+    None => {
+      return ControlFlowGraph {
+        function_start: function.start,
+        blocks: vec![],
+      }
+    }
+  };
+  let body = instructions[first..]
+    .iter()
+    .take_while(|x| x.instr.ip() < function.end)
+    .take_while(|x| x.instr.code() != Code::DeclareByte)
+    .collect::<Vec<&InstructionData>>();
+
+  let mut leaders = BTreeSet::new();
+  leaders.insert(function.start);
+  for data in &body {
+    let instr = &data.instr;
+    if ends_block(data) {
+      leaders.insert(instr.next_ip());
+    }
+    if matches!(
+      instr.flow_control(),
+      FlowControl::ConditionalBranch | FlowControl::UnconditionalBranch
+    ) || is_xbegin(instr)
+    {
+      leaders.insert(instr.near_branch_target());
+    }
+    if let Some(table) = section.jump_table(instr.ip()) {
+      leaders.extend(table.unique_targets());
+    }
+  }
+
+  let mut blocks: Vec<BasicBlock> = vec![];
+  for (i, data) in body.iter().enumerate() {
+    let ip = data.instr.ip();
+    if leaders.contains(&ip) || blocks.is_empty() {
+      blocks.push(BasicBlock {
+        start: ip,
+        end: ip,
+        first_instruction: first + i,
+        instruction_count: 0,
+        edges: vec![],
+      });
+    }
+    let block = blocks.last_mut().expect("a block was just pushed");
+    block.end = data.instr.next_ip();
+    block.instruction_count += 1;
+    block.edges.extend(call_edge(&data.instr));
+
+    let is_last = match body.get(i + 1) {
+      Some(next) => leaders.contains(&next.instr.ip()),
+      None => true,
+    };
+    if is_last {
+      block
+        .edges
+        .extend(edges(data, section.jump_table(data.instr.ip())));
+    }
+  }
+
+  ControlFlowGraph {
+    function_start: function.start,
+    blocks,
+  }
+}
+
+fn ends_block(data: &InstructionData) -> bool {
+  match data.instr.flow_control() {
+    FlowControl::Next | FlowControl::Call | FlowControl::IndirectCall => false,
+    FlowControl::Interrupt => data.instr.code() == Code::Int3,
+    FlowControl::XbeginXabortXend => is_xbegin(&data.instr),
+    _ => true,
+  }
+}
+
+// xbegin branches to its abort handler, xend and xabort have no target and carry on
+fn is_xbegin(instr: &Instruction) -> bool {
+  instr.mnemonic() == Mnemonic::Xbegin
+}
+
+fn call_edge(instr: &Instruction) -> Option<Edge> {
+  match instr.flow_control() {
+    FlowControl::Call => Some(Edge {
+      kind: EdgeKind::Call,
+      target: Some(instr.near_branch_target()),
+    }),
+    FlowControl::IndirectCall => Some(Edge {
+      kind: EdgeKind::Call,
+      target: None,
+    }),
+    _ => None,
+  }
+}
+
+// Classifies the edges leaving a block by its last instruction, its calls were added already
+fn edges(data: &InstructionData, jump_table: Option<&JumpTable>) -> Vec<Edge> {
+  let instr = &data.instr;
+  let fallthrough = Edge {
+    kind: EdgeKind::Fallthrough,
+    target: Some(instr.next_ip()),
+  };
+
+  match instr.flow_control() {
+    FlowControl::ConditionalBranch => vec![
+      Edge {
+        kind: EdgeKind::Conditional,
+        target: Some(instr.near_branch_target()),
+      },
+      fallthrough,
+    ],
+    FlowControl::XbeginXabortXend if is_xbegin(instr) => vec![
+      Edge {
+        kind: EdgeKind::Conditional,
+        target: Some(instr.near_branch_target()),
+      },
+      fallthrough,
+    ],
+    FlowControl::UnconditionalBranch => vec![Edge {
+      kind: EdgeKind::Unconditional,
+      target: Some(instr.near_branch_target()),
+    }],
+    FlowControl::IndirectBranch => match jump_table {
+      Some(table) => table
+        .unique_targets()
+        .into_iter()
+        .map(|x| Edge {
+          kind: EdgeKind::Case,
+          target: Some(x),
+        })
+        .collect(),
+      None => vec![Edge {
+        kind: EdgeKind::Indirect,
+        target: None,
+      }],
+    },
+    FlowControl::Return => vec![Edge {
+      kind: EdgeKind::Return,
+      target: None,
+    }],
+    FlowControl::Exception => vec![],
+    FlowControl::Interrupt if instr.code() == Code::Int3 => vec![],
+    FlowControl::Next
+    | FlowControl::Interrupt
+    | FlowControl::Call
+    | FlowControl::IndirectCall
+    | FlowControl::XbeginXabortXend => vec![fallthrough],
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use iced_x86::{Decoder, DecoderOptions};
+
+  const BASE: u64 = 0x1000;
+
+  fn section(bytes: &[u8], jump_tables: Vec<JumpTable>) -> SectionData {
+    let mut decoder = Decoder::with_ip(64, bytes, BASE, DecoderOptions::NONE);
+    let mut data = vec![];
+    while decoder.can_decode() {
+      let offset = decoder.position();
+      let instr = decoder.decode();
+      data.push(InstructionData {
+        instr,
+        offset,
+        size: instr.len(),
+        bytes: bytes[offset..offset + instr.len()].to_vec(),
+      });
+    }
+    SectionData {
+      data,
+      bytes: bytes.to_vec(),
+      jump_tables,
+      ..Default::default()
+    }
+  }
+
+  fn cfg(bytes: &[u8], jump_tables: Vec<JumpTable>) -> ControlFlowGraph {
+    let function = Function {
+      start: BASE,
+      end: BASE + bytes.len() as u64,
+      name: None,
+    };
+    build_cfg(&section(bytes, jump_tables), &function)
+  }
+
+  // block start and the kind and target of every edge leaving it
+  type BlockEdges = (u64, Vec<(EdgeKind, Option<u64>)>);
+
+  fn edges_of(cfg: &ControlFlowGraph) -> Vec<BlockEdges> {
+    cfg
+      .blocks
+      .iter()
+      .map(|x| {
+        (
+          x.start,
+          x.edges.iter().map(|x| (x.kind, x.target)).collect(),
+        )
+      })
+      .collect()
+  }
+
+  #[test]
+  fn diamond() {
+    let bytes = [
+      0x85, 0xc9, // test ecx, ecx
+      0x74, 0x07, // je 0x100b
+      0xb8, 0x01, 0x00, 0x00, 0x00, // mov eax, 1
+      0xeb, 0x05, // jmp 0x1010
+      0xb8, 0x02, 0x00, 0x00, 0x00, // mov eax, 2
+      0xc3, // ret
+    ];
+    assert_eq!(
+      edges_of(&cfg(&bytes, vec![])),
+      [
+        (
+          0x1000,
+          vec![
+            (EdgeKind::Conditional, Some(0x100b)),
+            (EdgeKind::Fallthrough, Some(0x1004))
+          ]
+        ),
+        (0x1004, vec![(EdgeKind::Unconditional, Some(0x1010))]),
+        (0x100b, vec![(EdgeKind::Fallthrough, Some(0x1010))]),
+        (0x1010, vec![(EdgeKind::Return, None)]),
+      ]
+    );
+  }
+
+  #[test]
+  fn loop_with_a_call_in_its_body() {
+    let bytes = [
+      0x31, 0xc0, // xor eax, eax
+      0xff, 0xc0, // inc eax
+      0xe8, 0xf7, 0x0f, 0x00, 0x00, // call 0x2000
+      0x39, 0xc8, // cmp eax, ecx
+      0x72, 0xf5, // jb 0x1002
+      0xc3, // ret
+    ];
+    let cfg = cfg(&bytes, vec![]);
+    assert_eq!(
+      edges_of(&cfg),
+      [
+        (0x1000, vec![(EdgeKind::Fallthrough, Some(0x1002))]),
+        (
+          0x1002,
+          vec![
+            (EdgeKind::Call, Some(0x2000)),
+            (EdgeKind::Conditional, Some(0x1002)),
+            (EdgeKind::Fallthrough, Some(0x100d))
+          ]
+        ),
+        (0x100d, vec![(EdgeKind::Return, None)]),
+      ]
+    );
+    assert_eq!(cfg.blocks[1].instruction_count, 4);
+  }
+
+  #[test]
+  fn jump_table() {
+    let bytes = [
+      0x83, 0xf9, 0x02, // cmp ecx, 2
+      0x77, 0x08, // ja 0x100d
+      0xff, 0xe0, // jmp rax
+      0xb0, 0x01, 0xc3, // mov al, 1 / ret
+      0xb0, 0x02, 0xc3, // mov al, 2 / ret
+      0x31, 0xc0, 0xc3, // xor eax, eax / ret
+    ];
+    let table = JumpTable {
+      dispatch: 0x1005,
+      table: 0x3000,
+      targets: vec![Some(0x1007), Some(0x100a), Some(0x1007)],
+    };
+    assert_eq!(
+      edges_of(&cfg(&bytes, vec![table])),
+      [
+        (
+          0x1000,
+          vec![
+            (EdgeKind::Conditional, Some(0x100d)),
+            (EdgeKind::Fallthrough, Some(0x1005))
+          ]
+        ),
+        (
+          0x1005,
+          vec![
+            (EdgeKind::Case, Some(0x1007)),
+            (EdgeKind::Case, Some(0x100a))
+          ]
+        ),
+        (0x1007, vec![(EdgeKind::Return, None)]),
+        (0x100a, vec![(EdgeKind::Return, None)]),
+        (0x100d, vec![(EdgeKind::Return, None)]),
+      ]
+    );
+  }
+
+  #[test]
+  fn xend_falls_through_and_xbegin_branches() {
+    let bytes = [
+      0xc7, 0xf8, 0x04, 0x00, 0x00, 0x00, // xbegin 0x100a
+      0x0f, 0x01, 0xd5, // xend
+      0xc3, // ret
+      0xc3, // ret
+    ];
+    assert_eq!(
+      edges_of(&cfg(&bytes, vec![])),
+      [
+        (
+          0x1000,
+          vec![
+            (EdgeKind::Conditional, Some(0x100a)),
+            (EdgeKind::Fallthrough, Some(0x1006))
+          ]
+        ),
+        (0x1006, vec![(EdgeKind::Return, None)]),
+        (0x100a, vec![(EdgeKind::Return, None)]),
+      ]
+    );
+  }
+}
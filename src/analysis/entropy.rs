@@ -0,0 +1,140 @@
+use crate::analysis::{EntropyReport, PackerSign, SectionEntropy};
+use crate::parser::{DataDirectoryTableField, PEFile, SectionCharacteristics, SectionEntry};
+
+// Bytes per point of the file curve, windows overlap by half so a boundary doesn't hide a jump
+pub const ENTROPY_WINDOW: usize = 1024;
+const ENTROPY_STEP: usize = ENTROPY_WINDOW / 2;
+// Compressed or encrypted data sits close to 8 bits per byte, compiled code rarely goes past 6.5
+pub const HIGH_ENTROPY: f64 = 7.2;
+// A packer stub only needs LoadLibrary and GetProcAddress to import the rest itself
+const TINY_IMPORT_COUNT: usize = 8;
+// Room a stub unpacks into, an empty .bss is smaller than this
+const LARGE_VIRTUAL_SIZE: u32 = 0x10000;
+
+// Bits per byte, 0 for a run of one value up to 8 for random data
+pub fn shannon_entropy(bytes: &[u8]) -> f64 {
+  if bytes.is_empty() {
+    return 0.0;
+  }
+  let mut counts = [0usize; 256];
+  for b in bytes {
+    counts[*b as usize] += 1;
+  }
+  let len = bytes.len() as f64;
+  counts
+    .iter()
+    .filter(|x| **x > 0)
+    .map(|x| {
+      let p = *x as f64 / len;
+      -p * p.log2()
+    })
+    .sum()
+}
+
+// Entropy of every section and across the file, and the signs of a packer that go with it
+pub fn build_entropy(pe_file: &PEFile) -> EntropyReport {
+  let bytes = &pe_file.bytes;
+  let sections = pe_file
+    .section_table
+    .iter()
+    .map(|x| {
+      let start = (x.pointer_to_raw_data as usize).min(bytes.len());
+      let end = start
+        .saturating_add(x.size_of_raw_data as usize)
+        .min(bytes.len());
+      SectionEntropy {
+        name: x.name.clone(),
+        entropy: shannon_entropy(&bytes[start..end]),
+        raw_size: x.size_of_raw_data,
+        virtual_size: x.virtual_size,
+        executable: is_code(x),
+      }
+    })
+    .collect::<Vec<SectionEntropy>>();
+
+  let windows = if bytes.len() <= ENTROPY_WINDOW {
+    vec![shannon_entropy(bytes)]
+  } else {
+    (0..=bytes.len() - ENTROPY_WINDOW)
+      .step_by(ENTROPY_STEP)
+      .map(|x| shannon_entropy(&bytes[x..x + ENTROPY_WINDOW]))
+      .collect()
+  };
+
+  EntropyReport {
+    file: shannon_entropy(bytes),
+    packer_signs: packer_signs(pe_file, &sections),
+    sections,
+    windows,
+    window_step: ENTROPY_STEP,
+  }
+}
+
+fn packer_signs(pe_file: &PEFile, sections: &[SectionEntropy]) -> Vec<PackerSign> {
+  let mut signs = vec![];
+  for section in sections {
+    if section.executable && section.raw_size > 0 && section.entropy >= HIGH_ENTROPY {
+      signs.push(PackerSign::HighEntropyCode {
+        section: section.name.clone(),
+        entropy: section.entropy,
+      });
+    }
+    if section.raw_size == 0 && section.virtual_size >= LARGE_VIRTUAL_SIZE {
+      signs.push(PackerSign::EmptyRawData {
+        section: section.name.clone(),
+        virtual_size: section.virtual_size,
+      });
+    }
+  }
+
+  // DLLs without an entry point have 0, which isn't in any section
+  let (entry_point, has_import_directory) = match &pe_file.headers.nt_headers.optional_header {
+    Some(optional_header) => (
+      optional_header.common().address_of_entry_point,
+      optional_header
+        .data_directory(DataDirectoryTableField::IMPORT_TABLE)
+        .is_some(),
+    ),
+    None => (0, false),
+  };
+
+  // managed binaries only import _CorExeMain, resource only DLLs and drivers can import nothing at
+  // all, a stub needs code to run and an import table to start from
+  if pe_file.clr.is_none()
+    && entry_point != 0
+    && has_import_directory
+    && pe_file.imports.len() < TINY_IMPORT_COUNT
+  {
+    let mut modules = pe_file
+      .imports
+      .iter()
+      .map(|x| x.module.to_lowercase())
+      .collect::<Vec<String>>();
+    modules.sort();
+    modules.dedup();
+    signs.push(PackerSign::TinyImportTable {
+      functions: pe_file.imports.len(),
+      modules: modules.len(),
+    });
+  }
+
+  // by the section flags, MinGW, Delphi and Go name their code CODE, .itext and the like
+  if entry_point != 0 {
+    let section = pe_file
+      .section_table
+      .iter()
+      .find(|x| x.contains_rva(entry_point));
+    if !section.is_some_and(is_code) {
+      signs.push(PackerSign::EntryPointOutsideCode {
+        entry_point,
+        section: section.map(|x| x.name.clone()),
+      });
+    }
+  }
+  signs
+}
+
+fn is_code(section: &SectionEntry) -> bool {
+  section.has_characteristic(SectionCharacteristics::IMAGE_SCN_MEM_EXECUTE)
+    || section.has_characteristic(SectionCharacteristics::IMAGE_SCN_CNT_CODE)
+}
@@ -0,0 +1,37 @@
+use crate::analysis::CpuFeatureUse;
+use crate::parser::SectionData;
+use iced_x86::{Code, CpuidFeature};
+use std::collections::BTreeMap;
+
+// Enough to find the code that needs a feature without dumping every instruction
+const FIRST_ADDRESSES: usize = 4;
+
+// Every CPUID feature the decoded code needs, the most used first
+pub fn build_cpu_features(section: &SectionData) -> Vec<CpuFeatureUse> {
+  let mut uses: BTreeMap<CpuidFeature, CpuFeatureUse> = BTreeMap::new();
+  for data in &section.data {
+    let instr = &data.instr;
+    if instr.code() == Code::DeclareByte || instr.is_invalid() {
+      continue;
+    }
+    for feature in instr.cpuid_features() {
+      let entry = uses.entry(*feature).or_insert_with(|| CpuFeatureUse {
+        feature: format!("{:?}", feature),
+        count: 0,
+        addresses: vec![],
+      });
+      entry.count += 1;
+      if entry.addresses.len() < FIRST_ADDRESSES {
+        entry.addresses.push(instr.ip());
+      }
+    }
+  }
+
+  let mut uses = uses.into_values().collect::<Vec<CpuFeatureUse>>();
+  uses.sort_by(|a, b| {
+    b.count
+      .cmp(&a.count)
+      .then_with(|| a.feature.cmp(&b.feature))
+  });
+  uses
+}
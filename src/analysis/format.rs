@@ -0,0 +1,42 @@
+use crate::analysis::{FormatOptions, HexStyle, SymbolTable, Syntax};
+use iced_x86::{
+  Formatter, GasFormatter, Instruction, IntelFormatter, MasmFormatter, NasmFormatter,
+  SymbolResolver,
+};
+
+// A formatter for the chosen syntax that prints names instead of the addresses they stand for
+pub fn build_formatter(symbols: &SymbolTable, options: &FormatOptions) -> Box<dyn Formatter> {
+  let resolver: Option<Box<dyn SymbolResolver>> = Some(Box::new(symbols.clone()));
+  let mut formatter: Box<dyn Formatter> = match options.syntax {
+    Syntax::Intel => Box::new(IntelFormatter::with_options(resolver, None)),
+    Syntax::Masm => Box::new(MasmFormatter::with_options(resolver, None)),
+    Syntax::Nasm => Box::new(NasmFormatter::with_options(resolver, None)),
+    Syntax::Gas => Box::new(GasFormatter::with_options(resolver, None)),
+  };
+
+  let formatter_options = formatter.options_mut();
+  formatter_options.set_uppercase_mnemonics(options.uppercase_mnemonics);
+  match options.hex_style {
+    HexStyle::Default => {}
+    HexStyle::Prefix => {
+      formatter_options.set_hex_prefix("0x");
+      formatter_options.set_hex_suffix("");
+    }
+    HexStyle::Suffix => {
+      formatter_options.set_hex_prefix("");
+      formatter_options.set_hex_suffix("h");
+    }
+  }
+  if options.digit_separators {
+    formatter_options.set_digit_separator("_");
+  }
+  formatter_options.set_show_branch_size(options.branch_size);
+
+  formatter
+}
+
+pub fn format_instruction(formatter: &mut dyn Formatter, instr: &Instruction) -> String {
+  let mut text = String::new();
+  formatter.format(instr, &mut text);
+  text
+}
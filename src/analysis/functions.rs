@@ -0,0 +1,170 @@
+use crate::analysis::Function;
+use crate::parser::{InstructionData, PEFile};
+use iced_x86::{Code, FlowControl, Mnemonic, OpKind, Register};
+use std::collections::BTreeMap;
+
+// Finds function starts from everything that names or calls code, and ends from .pdata or the next start
+pub fn discover_functions(pe_file: &PEFile) -> Vec<Function> {
+  let instructions = &pe_file.text_section.data;
+  let (section_start, section_end) = match (instructions.first(), instructions.last()) {
+    (Some(first), Some(last)) => (first.instr.ip(), last.instr.ip() + last.size as u64),
+    _ => return vec![],
+  };
+  let image_base = match &pe_file.headers.nt_headers.optional_header {
+    Some(optional_header) => optional_header.image_base(),
+    None => 0,
+  };
+
+  let mut starts: BTreeMap<u64, Option<String>> = BTreeMap::new();
+  let mut known_ends: BTreeMap<u64, u64> = BTreeMap::new();
+
+  // named starts first, so their names win over the anonymous ones below
+  for export in pe_file.exports.iter().filter(|x| x.forwarder.is_none()) {
+    starts.insert(image_base + export.rva as u64, export.name.clone());
+  }
+  for symbol in pe_file.symbols.iter().filter(|x| x.is_function) {
+    let name = starts.entry(image_base + symbol.rva as u64).or_default();
+    if name.is_none() {
+      *name = Some(symbol.name.clone());
+    }
+  }
+  if let Some(optional_header) = &pe_file.headers.nt_headers.optional_header {
+    let entry_point = image_base + optional_header.common().address_of_entry_point as u64;
+    let name = starts.entry(entry_point).or_default();
+    if name.is_none() {
+      *name = Some("entry_point".to_owned());
+    }
+  }
+  for function in &pe_file.runtime_functions {
+    if function.begin_address < function.end_address {
+      let start = image_base + function.begin_address as u64;
+      starts.entry(start).or_default();
+      known_ends.insert(start, image_base + function.end_address as u64);
+    }
+  }
+
+  for (i, data) in instructions.iter().enumerate() {
+    if is_data(data) {
+      continue;
+    }
+    if data.instr.flow_control() == FlowControl::Call {
+      starts.entry(data.instr.near_branch_target()).or_default();
+    }
+    let after_padding = i == 0 || is_padding(&instructions[i - 1]);
+    if after_padding && is_prologue(data, instructions.get(i + 1)) {
+      starts.entry(data.instr.ip()).or_default();
+    }
+  }
+
+  // only keep starts that line up with a decoded instruction
+  let starts = starts
+    .into_iter()
+    .filter(|(start, _)| (section_start..section_end).contains(start))
+    .filter(|(start, _)| match instruction_index(instructions, *start) {
+      Some(index) => !is_data(&instructions[index]),
+      None => false,
+    })
+    .collect::<Vec<(u64, Option<String>)>>();
+
+  let mut functions = Vec::with_capacity(starts.len());
+  for (i, (start, name)) in starts.iter().enumerate() {
+    let next_start = match starts.get(i + 1) {
+      Some((next, _)) => *next,
+      None => section_end,
+    };
+    let end = match known_ends.get(start) {
+      Some(end) => (*end).min(section_end),
+      None => trim_padding(instructions, *start, next_start),
+    };
+    functions.push(Function {
+      start: *start,
+      end,
+      name: name.clone(),
+    });
+  }
+
+  functions
+}
+
+// Instructions are sorted by address, returns the one starting exactly at the address
+pub fn instruction_index(instructions: &[InstructionData], address: u64) -> Option<usize> {
+  instructions
+    .binary_search_by_key(&address, |x| x.instr.ip())
+    .ok()
+}
+
+// The instruction the address falls in, for addresses that point into the middle of one
+pub fn containing_instruction_index(
+  instructions: &[InstructionData],
+  address: u64,
+) -> Option<usize> {
+  let index = instructions
+    .partition_point(|x| x.instr.ip() <= address)
+    .checked_sub(1)?;
+  let data = &instructions[index];
+  if address < data.instr.ip() + data.size as u64 {
+    Some(index)
+  } else {
+    None
+  }
+}
+
+fn is_data(data: &InstructionData) -> bool {
+  data.instr.code() == Code::DeclareByte
+}
+
+// Compilers align functions with int3 or nop runs
+fn is_padding(data: &InstructionData) -> bool {
+  is_data(data) || matches!(data.instr.mnemonic(), Mnemonic::Int3 | Mnemonic::Nop)
+}
+
+fn is_prologue(data: &InstructionData, next: Option<&InstructionData>) -> bool {
+  let instr = &data.instr;
+  match instr.mnemonic() {
+    // push ebp; mov ebp, esp
+    Mnemonic::Push if matches!(instr.op0_register(), Register::EBP | Register::RBP) => match next {
+      Some(next) => {
+        next.instr.mnemonic() == Mnemonic::Mov
+          && matches!(next.instr.op0_register(), Register::EBP | Register::RBP)
+          && matches!(next.instr.op1_register(), Register::ESP | Register::RSP)
+      }
+      None => false,
+    },
+    // sub rsp, imm, x64 functions allocate their whole frame up front
+    Mnemonic::Sub => {
+      instr.op0_register() == Register::RSP
+        && matches!(
+          instr.op1_kind(),
+          OpKind::Immediate8to64 | OpKind::Immediate32to64
+        )
+    }
+    // mov [rsp+8], rcx, MSVC spills arguments to the home space first
+    Mnemonic::Mov => {
+      instr.op0_kind() == OpKind::Memory
+        && instr.memory_base() == Register::RSP
+        && matches!(
+          instr.op1_register(),
+          Register::RCX | Register::RDX | Register::R8 | Register::R9 | Register::RBX
+        )
+    }
+    _ => false,
+  }
+}
+
+// Without .pdata the function runs until the next start, minus any alignment padding
+fn trim_padding(instructions: &[InstructionData], start: u64, next_start: u64) -> u64 {
+  let first = match instruction_index(instructions, start) {
+    Some(index) => index,
+    None => return start,
+  };
+  let mut end = start;
+  for data in instructions[first..]
+    .iter()
+    .take_while(|x| x.instr.ip() < next_start)
+  {
+    if !is_padding(data) {
+      end = data.instr.ip() + data.size as u64;
+    }
+  }
+  end
+}
@@ -0,0 +1,364 @@
+pub use crate::analysis::assemble::{assemble, parse_number};
+pub use crate::analysis::cfg::build_cfg;
+pub use crate::analysis::entropy::{build_entropy, shannon_entropy, ENTROPY_WINDOW, HIGH_ENTROPY};
+pub use crate::analysis::features::build_cpu_features;
+pub use crate::analysis::format::{build_formatter, format_instruction};
+pub use crate::analysis::functions::{
+  containing_instruction_index, discover_functions, instruction_index,
+};
+pub use crate::analysis::strings::{
+  build_string_refs, extract_strings, referenced_string, MIN_STRING_LENGTH,
+};
+pub use crate::analysis::symbols::build_symbols;
+pub use crate::analysis::xrefs::build_xrefs;
+use iced_x86::{FormatterTextKind, Instruction, SymbolResolver, SymbolResult};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use strum::{EnumIter, EnumString, IntoEnumIterator, IntoStaticStr};
+
+mod assemble;
+mod cfg;
+mod entropy;
+mod features;
+mod format;
+mod functions;
+mod strings;
+mod symbols;
+mod xrefs;
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Function {
+  pub start: u64,           // VA of the first instruction
+  pub end: u64,             // VA of the byte after the last instruction, padding excluded
+  pub name: Option<String>, // From exports or symbols, None when only the code was seen
+}
+
+impl Function {
+  pub fn size(&self) -> u64 {
+    self.end - self.start
+  }
+
+  pub fn display_name(&self) -> String {
+    match &self.name {
+      Some(name) => name.clone(),
+      None => format!("sub_{:x}", self.start),
+    }
+  }
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ControlFlowGraph {
+  pub function_start: u64,
+  pub blocks: Vec<BasicBlock>, // Sorted by address, the first block is the function entry
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct BasicBlock {
+  pub start: u64,
+  pub end: u64, // VA of the byte after the last instruction
+  #[serde(skip)]
+  pub first_instruction: usize, // Index into SectionData.data
+  pub instruction_count: usize,
+  pub edges: Vec<Edge>, // Calls made in the block, then the edges its last instruction leaves by
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Edge {
+  pub kind: EdgeKind,
+  pub target: Option<u64>, // None for returns and targets only known at runtime
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+  Fallthrough,
+  Conditional, // Taken side of a conditional branch
+  Unconditional,
+  Call,
+  Return,
+  Indirect, // Jump through a register or memory
+  Case,     // One target of a recovered jump table
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Xref {
+  pub from: u64, // VA of the referencing instruction
+  pub to: u64,
+  pub kind: XrefKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum XrefKind {
+  Call,
+  Jump,
+  Read,
+  Write,
+  ReadWrite,
+  Offset, // The address is taken by lea or an immediate, the memory isn't accessed
+}
+
+// Xrefs indexed by both ends, the lists are in the order the referencing instructions appear
+#[derive(Debug, Default)]
+pub struct XrefIndex {
+  to: BTreeMap<u64, Vec<Xref>>,
+  from: BTreeMap<u64, Vec<Xref>>,
+}
+
+impl XrefIndex {
+  pub fn insert(&mut self, xref: Xref) {
+    self.to.entry(xref.to).or_default().push(xref);
+    self.from.entry(xref.from).or_default().push(xref);
+  }
+
+  pub fn references_to(&self, address: u64) -> &[Xref] {
+    self.to.get(&address).map(|x| x.as_slice()).unwrap_or(&[])
+  }
+
+  pub fn references_from(&self, address: u64) -> &[Xref] {
+    self.from.get(&address).map(|x| x.as_slice()).unwrap_or(&[])
+  }
+
+  // Every referenced address, in order
+  pub fn targets(&self) -> impl Iterator<Item = u64> + '_ {
+    self.to.keys().copied()
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DataString {
+  pub address: u64,
+  pub text: String,
+  pub encoding: StringEncoding,
+}
+
+impl DataString {
+  // Quoted and escaped like a C literal, wide strings get the L prefix
+  pub fn quoted(&self) -> String {
+    match self.encoding {
+      StringEncoding::Ascii => format!("{:?}", self.text),
+      StringEncoding::Utf16Le => format!("L{:?}", self.text),
+    }
+  }
+}
+
+// A run of printable characters anywhere in the sections or the overlay, like strings(1) finds
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtractedString {
+  pub offset: usize,        // File offset
+  pub region: String,       // Section name, or Overlay
+  pub address: Option<u64>, // VA, the overlay isn't loaded
+  pub text: String,
+  pub encoding: StringEncoding,
+  pub references: Vec<u64>, // Instructions that point at the start of the string
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StringEncoding {
+  Ascii,
+  #[serde(rename = "utf16le")]
+  Utf16Le,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CpuFeatureUse {
+  pub feature: String,     // iced's CpuidFeature name, AVX2, AVX512F, BMI2...
+  pub count: usize,        // Instructions that need it
+  pub addresses: Vec<u64>, // The first few of them
+}
+
+impl CpuFeatureUse {
+  // Case insensitive, a trailing * matches a whole family like AVX512*
+  pub fn matches(&self, pattern: &str) -> bool {
+    let feature = self.feature.to_ascii_uppercase();
+    let pattern = pattern.to_ascii_uppercase();
+    match pattern.strip_suffix('*') {
+      Some(prefix) => feature.starts_with(prefix),
+      None => feature == pattern,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EntropyReport {
+  pub file: f64,
+  pub sections: Vec<SectionEntropy>, // In section table order
+  #[serde(skip)]
+  pub windows: Vec<f64>, // ENTROPY_WINDOW bytes each, starting every window_step bytes
+  #[serde(skip)]
+  pub window_step: usize,
+  pub packer_signs: Vec<PackerSign>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SectionEntropy {
+  pub name: String,
+  pub entropy: f64, // Of the raw data, 0 when there is none
+  pub raw_size: u32,
+  pub virtual_size: u32,
+  pub executable: bool,
+}
+
+// Things packers and crypters leave behind, each one alone can be innocent
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PackerSign {
+  HighEntropyCode {
+    section: String,
+    entropy: f64,
+  },
+  EmptyRawData {
+    section: String,
+    virtual_size: u32,
+  },
+  TinyImportTable {
+    functions: usize,
+    modules: usize,
+  },
+  EntryPointOutsideCode {
+    entry_point: u32,
+    section: Option<String>,
+  },
+}
+
+impl PackerSign {
+  pub fn description(&self) -> String {
+    match self {
+      PackerSign::HighEntropyCode { section, entropy } => format!(
+        "{} is executable with entropy {:.2}, code is usually below 6.5",
+        section, entropy
+      ),
+      PackerSign::EmptyRawData {
+        section,
+        virtual_size,
+      } => format!(
+        "{} has no raw data but {:#x} bytes of virtual size to unpack into",
+        section, virtual_size
+      ),
+      PackerSign::TinyImportTable { functions, modules } => format!(
+        "Only {} functions imported from {} modules",
+        functions, modules
+      ),
+      PackerSign::EntryPointOutsideCode {
+        entry_point,
+        section,
+      } => match section {
+        Some(section) => format!(
+          "Entry point {:#x} is in {}, which isn't marked as code",
+          entry_point, section
+        ),
+        None => format!("Entry point {:#x} isn't in any section", entry_point),
+      },
+    }
+  }
+}
+
+// Names by VA, cheap to clone so the formatters can own a copy
+#[derive(Debug, Default, Clone)]
+pub struct SymbolTable {
+  names: Rc<BTreeMap<u64, (String, FormatterTextKind)>>,
+}
+
+impl SymbolTable {
+  pub fn name(&self, address: u64) -> Option<&str> {
+    self.names.get(&address).map(|(name, _)| name.as_str())
+  }
+
+  // Exact match first, then ignoring case, since import names are typed from memory
+  pub fn address(&self, name: &str) -> Option<u64> {
+    self
+      .names
+      .iter()
+      .find(|(_, (x, _))| x == name)
+      .or_else(|| {
+        self
+          .names
+          .iter()
+          .find(|(_, (x, _))| x.eq_ignore_ascii_case(name))
+      })
+      .map(|(address, _)| *address)
+  }
+
+  // The same table with the names the user gave, which win over everything else
+  pub fn with_labels(&self, labels: &BTreeMap<u64, String>) -> SymbolTable {
+    if labels.is_empty() {
+      return self.clone();
+    }
+    let mut names = self.names.as_ref().clone();
+    for (address, label) in labels {
+      let kind = names
+        .get(address)
+        .map(|(_, kind)| *kind)
+        .unwrap_or(FormatterTextKind::Label);
+      names.insert(*address, (label.clone(), kind));
+    }
+    SymbolTable {
+      names: Rc::new(names),
+    }
+  }
+}
+
+impl SymbolResolver for SymbolTable {
+  fn symbol(
+    &mut self,
+    _instruction: &Instruction,
+    _operand: u32,
+    _instruction_operand: Option<u32>,
+    address: u64,
+    _address_size: u32,
+  ) -> Option<SymbolResult<'_>> {
+    let (name, kind) = self.names.get(&address)?;
+    Some(SymbolResult::with_str_kind(address, name, *kind))
+  }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, EnumIter, EnumString, IntoStaticStr)]
+#[strum(serialize_all = "lowercase")]
+pub enum Syntax {
+  Intel,
+  #[default]
+  Masm,
+  Nasm,
+  Gas, // AT&T operand order
+}
+
+impl Syntax {
+  pub fn next(self) -> Self {
+    Syntax::iter()
+      .cycle()
+      .skip_while(|x| *x != self)
+      .nth(1)
+      .unwrap_or_default()
+  }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum HexStyle {
+  #[default]
+  Default, // Whatever the syntax uses, 0x for GAS and an h suffix for the rest
+  Prefix,
+  Suffix,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FormatOptions {
+  pub syntax: Syntax,
+  pub uppercase_mnemonics: bool,
+  pub hex_style: HexStyle,
+  pub digit_separators: bool, // 1234_5678h
+  pub branch_size: bool,      // jmp short / jmp near ptr
+}
+
+impl Default for FormatOptions {
+  fn default() -> Self {
+    FormatOptions {
+      syntax: Syntax::default(),
+      uppercase_mnemonics: false,
+      hex_style: HexStyle::default(),
+      digit_separators: false,
+      branch_size: true,
+    }
+  }
+}
@@ -0,0 +1,195 @@
+use crate::analysis::{DataString, ExtractedString, StringEncoding, XrefIndex};
+use crate::parser::{FileRegionKind, PEFile, SectionCharacteristics};
+use std::collections::BTreeMap;
+
+pub const MIN_STRING_LENGTH: usize = 4;
+const MAX_STRING_LENGTH: usize = 1024;
+
+// Strings in the data sections that the code points at, keyed by their VA
+pub fn build_string_refs(pe_file: &PEFile, xrefs: &XrefIndex) -> BTreeMap<u64, DataString> {
+  let image_base = match &pe_file.headers.nt_headers.optional_header {
+    Some(optional_header) => optional_header.image_base(),
+    None => 0,
+  };
+
+  xrefs
+    .targets()
+    .filter_map(|address| {
+      let rva = u32::try_from(address.checked_sub(image_base)?).ok()?;
+      let section = pe_file.section_table.iter().find(|x| x.contains_rva(rva))?;
+      if !section.has_characteristic(SectionCharacteristics::IMAGE_SCN_CNT_INITIALIZED_DATA)
+        || section.has_characteristic(SectionCharacteristics::IMAGE_SCN_MEM_EXECUTE)
+      {
+        return None;
+      }
+      let delta = rva - section.virtual_address;
+      if delta >= section.size_of_raw_data {
+        return None;
+      }
+      let start = section.pointer_to_raw_data as usize + delta as usize;
+      let end = section.pointer_to_raw_data as usize + section.size_of_raw_data as usize;
+      let bytes = pe_file.bytes.get(start..end.min(pe_file.bytes.len()))?;
+      let string = decode_string(bytes, address)?;
+      Some((address, string))
+    })
+    .collect()
+}
+
+// The first string the instruction at the address points at
+pub fn referenced_string<'a>(
+  xrefs: &XrefIndex,
+  strings: &'a BTreeMap<u64, DataString>,
+  address: u64,
+) -> Option<&'a DataString> {
+  xrefs
+    .references_from(address)
+    .iter()
+    .find_map(|x| strings.get(&x.to))
+}
+
+// Every ASCII and UTF-16LE string of at least min_length characters in the sections and the overlay,
+// in file order
+pub fn extract_strings(
+  pe_file: &PEFile,
+  xrefs: &XrefIndex,
+  min_length: usize,
+) -> Vec<ExtractedString> {
+  let image_base = match &pe_file.headers.nt_headers.optional_header {
+    Some(optional_header) => optional_header.image_base(),
+    None => 0,
+  };
+  let min_length = min_length.max(1);
+
+  let mut strings = vec![];
+  for region in pe_file.file_regions() {
+    if !matches!(
+      region.kind,
+      FileRegionKind::Section | FileRegionKind::Overlay
+    ) {
+      continue;
+    }
+    let section = pe_file
+      .section_table
+      .iter()
+      .find(|x| region.kind == FileRegionKind::Section && x.name == region.name);
+    let bytes = &pe_file.bytes[region.range.clone()];
+    let runs = ascii_runs(bytes, min_length)
+      .into_iter()
+      .map(|(start, text)| (start, text, StringEncoding::Ascii))
+      .chain(
+        utf16_runs(bytes, min_length)
+          .into_iter()
+          .map(|(start, text)| (start, text, StringEncoding::Utf16Le)),
+      );
+    for (start, text, encoding) in runs {
+      let offset = region.range.start + start;
+      let address = section
+        .and_then(|x| x.offset_to_rva(u32::try_from(offset).ok()?))
+        .map(|rva| image_base + rva as u64);
+      let references = match address {
+        Some(address) => xrefs
+          .references_to(address)
+          .iter()
+          .map(|x| x.from)
+          .collect(),
+        None => vec![],
+      };
+      strings.push(ExtractedString {
+        offset,
+        region: region.name.clone(),
+        address,
+        text,
+        encoding,
+        references,
+      });
+    }
+  }
+  strings.sort_by_key(|x| x.offset);
+  strings
+}
+
+// Printable runs between anything else, the start is relative to the bytes
+fn ascii_runs(bytes: &[u8], min_length: usize) -> Vec<(usize, String)> {
+  let mut runs = vec![];
+  let mut start = 0;
+  for (i, b) in bytes.iter().chain([0].iter()).enumerate() {
+    if is_printable(*b) {
+      continue;
+    }
+    if i - start >= min_length {
+      runs.push((start, bytes[start..i].iter().map(|x| *x as char).collect()));
+    }
+    start = i + 1;
+  }
+  runs
+}
+
+// Printable ASCII widened to two bytes, tried at both alignments since nothing keeps strings aligned
+fn utf16_runs(bytes: &[u8], min_length: usize) -> Vec<(usize, String)> {
+  let mut runs = vec![];
+  for alignment in 0..2 {
+    let units = bytes.get(alignment..).unwrap_or_default().chunks_exact(2);
+    let mut text = String::new();
+    let mut start = alignment;
+    for (i, unit) in units.enumerate() {
+      if unit[1] == 0 && is_printable(unit[0]) {
+        if text.is_empty() {
+          start = alignment + i * 2;
+        }
+        text.push(unit[0] as char);
+        continue;
+      }
+      if text.len() >= min_length {
+        runs.push((start, std::mem::take(&mut text)));
+      }
+      text.clear();
+    }
+    if text.len() >= min_length {
+      runs.push((start, text));
+    }
+  }
+  runs
+}
+
+// A string starting at the first byte, UTF-16LE is tried first since its ASCII half is one character long
+fn decode_string(bytes: &[u8], address: u64) -> Option<DataString> {
+  let utf16 = bytes
+    .chunks_exact(2)
+    .take(MAX_STRING_LENGTH)
+    .map(|x| u16::from_le_bytes([x[0], x[1]]))
+    .take_while(|x| *x < 0x80 && is_printable(*x as u8))
+    .map(|x| x as u8 as char)
+    .collect::<String>();
+  if utf16.len() >= MIN_STRING_LENGTH {
+    return Some(DataString {
+      address,
+      text: utf16,
+      encoding: StringEncoding::Utf16Le,
+    });
+  }
+
+  let ascii = bytes
+    .iter()
+    .take(MAX_STRING_LENGTH)
+    .take_while(|x| is_printable(**x))
+    .map(|x| *x as char)
+    .collect::<String>();
+  // a string has to end somewhere, otherwise this is likely binary data that happens to be printable
+  let terminated = match bytes.get(ascii.len()) {
+    Some(next) => *next == 0,
+    None => true,
+  };
+  if ascii.len() >= MIN_STRING_LENGTH && terminated {
+    return Some(DataString {
+      address,
+      text: ascii,
+      encoding: StringEncoding::Ascii,
+    });
+  }
+
+  None
+}
+
+fn is_printable(b: u8) -> bool {
+  b.is_ascii_graphic() || matches!(b, b' ' | b'\t' | b'\r' | b'\n')
+}
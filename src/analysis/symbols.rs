@@ -0,0 +1,47 @@
+use crate::analysis::{Function, SymbolTable};
+use crate::parser::PEFile;
+use iced_x86::FormatterTextKind;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+// Names every address we know something about, imports first since an IAT slot has no other name
+pub fn build_symbols(pe_file: &PEFile, functions: &[Function]) -> SymbolTable {
+  let image_base = match &pe_file.headers.nt_headers.optional_header {
+    Some(optional_header) => optional_header.image_base(),
+    None => 0,
+  };
+
+  let mut names: BTreeMap<u64, (String, FormatterTextKind)> = BTreeMap::new();
+  for import in &pe_file.imports {
+    names.insert(
+      image_base + import.iat_rva as u64,
+      (import.display_name(), FormatterTextKind::Function),
+    );
+  }
+  for export in pe_file.exports.iter().filter(|x| x.forwarder.is_none()) {
+    if let Some(name) = &export.name {
+      names
+        .entry(image_base + export.rva as u64)
+        .or_insert((name.clone(), FormatterTextKind::Function));
+    }
+  }
+  for symbol in &pe_file.symbols {
+    let kind = match symbol.is_function {
+      true => FormatterTextKind::Function,
+      false => FormatterTextKind::Data,
+    };
+    names
+      .entry(image_base + symbol.rva as u64)
+      .or_insert((symbol.name.clone(), kind));
+  }
+  // the rest of the functions only have the generated sub_ names
+  for function in functions {
+    names
+      .entry(function.start)
+      .or_insert((function.display_name(), FormatterTextKind::Function));
+  }
+
+  SymbolTable {
+    names: Rc::new(names),
+  }
+}
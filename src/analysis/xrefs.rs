@@ -0,0 +1,108 @@
+use crate::analysis::{Xref, XrefIndex, XrefKind};
+use crate::parser::PEFile;
+use iced_x86::{
+  Code, FlowControl, Instruction, InstructionInfoFactory, OpAccess, OpKind, Register,
+};
+use std::ops::Range;
+
+// Collects every address the decoded code branches to, reads, writes or takes the address of
+pub fn build_xrefs(pe_file: &PEFile) -> XrefIndex {
+  let image = match &pe_file.headers.nt_headers.optional_header {
+    Some(optional_header) => {
+      let image_base = optional_header.image_base();
+      image_base..image_base + optional_header.size_of_image() as u64
+    }
+    None => 0..0,
+  };
+
+  let mut index = XrefIndex::default();
+  let mut factory = InstructionInfoFactory::new();
+  for data in &pe_file.text_section.data {
+    let instr = &data.instr;
+    if instr.code() == Code::DeclareByte || instr.is_invalid() {
+      continue;
+    }
+    for (to, kind) in instruction_references(instr, &mut factory, &image) {
+      index.insert(Xref {
+        from: instr.ip(),
+        to,
+        kind,
+      });
+    }
+    // the dispatch of a switch reads its table and jumps to every case
+    if let Some(table) = pe_file.text_section.jump_table(instr.ip()) {
+      let references = [(table.table, XrefKind::Read)].into_iter().chain(
+        table
+          .unique_targets()
+          .into_iter()
+          .map(|x| (x, XrefKind::Jump)),
+      );
+      for (to, kind) in references {
+        if index.references_from(instr.ip()).iter().all(|x| x.to != to) {
+          index.insert(Xref {
+            from: instr.ip(),
+            to,
+            kind,
+          });
+        }
+      }
+    }
+  }
+  index
+}
+
+fn instruction_references(
+  instr: &Instruction,
+  factory: &mut InstructionInfoFactory,
+  image: &Range<u64>,
+) -> Vec<(u64, XrefKind)> {
+  let mut references = vec![];
+
+  match instr.flow_control() {
+    FlowControl::Call => references.push((instr.near_branch_target(), XrefKind::Call)),
+    FlowControl::ConditionalBranch
+    | FlowControl::UnconditionalBranch
+    | FlowControl::XbeginXabortXend => {
+      references.push((instr.near_branch_target(), XrefKind::Jump))
+    }
+    _ => {}
+  }
+
+  // RIP-relative operands are already resolved to an absolute address by iced
+  let info = factory.info(instr);
+  for memory in info.used_memory() {
+    if memory.base() != Register::None || !image.contains(&memory.displacement()) {
+      continue;
+    }
+    let kind = match memory.access() {
+      OpAccess::Write | OpAccess::CondWrite => XrefKind::Write,
+      OpAccess::ReadWrite | OpAccess::ReadCondWrite => XrefKind::ReadWrite,
+      _ => XrefKind::Read,
+    };
+    references.push((memory.displacement(), kind));
+  }
+
+  for operand in 0..instr.op_count() {
+    let address = match instr.op_kind(operand) {
+      // lea only computes the address, so the memory is never accessed
+      OpKind::Memory if info.op_access(operand) == OpAccess::NoMemAccess => {
+        if instr.is_ip_rel_memory_operand() {
+          instr.ip_rel_memory_address()
+        } else if instr.memory_base() == Register::None && instr.memory_index() == Register::None {
+          instr.memory_displacement64()
+        } else {
+          continue;
+        }
+      }
+      OpKind::Immediate32 | OpKind::Immediate64 | OpKind::Immediate32to64 => {
+        instr.immediate(operand)
+      }
+      _ => continue,
+    };
+    if image.contains(&address) {
+      references.push((address, XrefKind::Offset));
+    }
+  }
+
+  references
+}
@@ -0,0 +1,157 @@
+use crate::analysis::{
+  build_cfg, build_cpu_features, build_entropy, build_formatter, build_string_refs, build_symbols,
+  build_xrefs, discover_functions, format_instruction, referenced_string, CpuFeatureUse,
+  ExtractedString, FormatOptions, StringEncoding,
+};
+use crate::parser::PEFile;
+use serde_json::{json, Value};
+
+// Machine readable dump of what the TUI shows, for scripts and pipelines
+pub fn to_json(pe_file: &PEFile, options: &FormatOptions) -> Value {
+  let nt_headers = &pe_file.headers.nt_headers;
+  let machine: String = nt_headers.file_header.machine.clone().into();
+  let (image_base, entry_point) = match &nt_headers.optional_header {
+    Some(optional_header) => (
+      optional_header.image_base(),
+      optional_header.common().address_of_entry_point,
+    ),
+    None => (0, 0),
+  };
+
+  let sections = pe_file
+    .section_table
+    .iter()
+    .map(|x| {
+      json!({
+        "name": x.name,
+        "virtual_address": x.virtual_address,
+        "virtual_size": x.virtual_size,
+        "pointer_to_raw_data": x.pointer_to_raw_data,
+        "size_of_raw_data": x.size_of_raw_data,
+      })
+    })
+    .collect::<Vec<Value>>();
+
+  let instructions = &pe_file.text_section.data;
+  let functions = discover_functions(pe_file);
+  let mut formatter = build_formatter(&build_symbols(pe_file, &functions), options);
+  let xrefs = build_xrefs(pe_file);
+  let strings = build_string_refs(pe_file, &xrefs);
+  let functions = functions
+    .iter()
+    .map(|function| {
+      let cfg = build_cfg(&pe_file.text_section, function);
+      let blocks = cfg
+        .blocks
+        .iter()
+        .map(|block| {
+          let block_instructions = instructions
+            [block.first_instruction..block.first_instruction + block.instruction_count]
+            .iter()
+            .map(|x| {
+              let mut value = json!({
+                "address": x.instr.ip(),
+                "bytes": x.bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>(),
+                "text": format_instruction(formatter.as_mut(), &x.instr),
+              });
+              if let Some(string) = referenced_string(&xrefs, &strings, x.instr.ip()) {
+                value["string"] = json!(string);
+              }
+              if let Some(table) = pe_file.text_section.jump_table(x.instr.ip()) {
+                value["jump_table"] = json!({
+                  "table": table.table,
+                  "targets": table.targets,
+                });
+              }
+              value
+            })
+            .collect::<Vec<Value>>();
+          let mut value = json!(block);
+          value["instructions"] = json!(block_instructions);
+          value
+        })
+        .collect::<Vec<Value>>();
+      json!({
+        "name": function.display_name(),
+        "start": function.start,
+        "end": function.end,
+        "size": function.size(),
+        "blocks": blocks,
+      })
+    })
+    .collect::<Vec<Value>>();
+
+  json!({
+    "machine": machine,
+    "image_base": image_base,
+    "entry_point": entry_point,
+    "sections": sections,
+    "functions": functions,
+    "cpu_features": build_cpu_features(&pe_file.text_section),
+    "entropy": build_entropy(pe_file),
+  })
+}
+
+// One line per feature, count and the first addresses, for reading in a terminal or grepping in CI
+pub fn cpu_feature_report(features: &[CpuFeatureUse]) -> String {
+  let width = features.iter().map(|x| x.feature.len()).max().unwrap_or(0);
+  features
+    .iter()
+    .map(|x| {
+      let addresses = x
+        .addresses
+        .iter()
+        .map(|a| format!("{:#x}", a))
+        .collect::<Vec<String>>()
+        .join(" ");
+      let more = if x.count > x.addresses.len() {
+        " ..."
+      } else {
+        ""
+      };
+      format!(
+        "{:<width$} {:>8}  {}{}",
+        x.feature, x.count, addresses, more
+      )
+    })
+    .collect::<Vec<String>>()
+    .join("\n")
+}
+
+// One line per string like strings(1) with the offset, where it is loaded and what references it
+pub fn strings_report(strings: &[ExtractedString]) -> String {
+  let width = strings.iter().map(|x| x.region.len()).max().unwrap_or(0);
+  strings
+    .iter()
+    .map(|x| {
+      let address = match x.address {
+        Some(address) => format!("{:#x}", address),
+        None => "-".to_owned(),
+      };
+      let references = x
+        .references
+        .iter()
+        .map(|a| format!("{:#x}", a))
+        .collect::<Vec<String>>()
+        .join(" ");
+      let encoding = match x.encoding {
+        StringEncoding::Ascii => "a",
+        StringEncoding::Utf16Le => "u",
+      };
+      format!(
+        "{:08x} {:<width$} {:>18} {} {:?}{}",
+        x.offset,
+        x.region,
+        address,
+        encoding,
+        x.text,
+        if references.is_empty() {
+          String::new()
+        } else {
+          format!("  <- {}", references)
+        }
+      )
+    })
+    .collect::<Vec<String>>()
+    .join("\n")
+}
@@ -0,0 +1,5 @@
+pub mod analysis;
+pub mod export;
+pub mod parser;
+pub mod project;
+pub mod tui;
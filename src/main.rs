@@ -1,20 +1,81 @@
+use asm_testing::analysis::{self, FormatOptions, HexStyle, Syntax};
+use asm_testing::{export, parser, tui};
 use std::io::BufReader;
 use std::io::{Read, Write};
 
-mod parser;
-mod tui;
-
 fn main() {
-  let args = std::env::args().collect::<Vec<String>>();
-  if args.len() < 2 {
-    print_color(
-      "Please provide a file to disassemble",
-      termcolor::Color::Yellow,
-    );
-    return;
+  let args = std::env::args().skip(1).collect::<Vec<String>>();
+  let (flags, files): (Vec<&String>, Vec<&String>) = args.iter().partition(|x| x.starts_with("--"));
+
+  let mut mode = parser::DisassemblyMode::RecursiveDescent;
+  let mut json = false;
+  let mut features = false;
+  let mut strings = false;
+  let mut min_string_length = analysis::MIN_STRING_LENGTH;
+  let mut denied_features: Vec<&str> = vec![];
+  let mut format_options = FormatOptions::default();
+  for flag in flags {
+    match flag.as_str() {
+      "--linear" => mode = parser::DisassemblyMode::LinearSweep,
+      "--json" => json = true,
+      "--features" => features = true,
+      "--strings" => strings = true,
+      "--uppercase-mnemonics" => format_options.uppercase_mnemonics = true,
+      "--hex-prefix" => format_options.hex_style = HexStyle::Prefix,
+      "--hex-suffix" => format_options.hex_style = HexStyle::Suffix,
+      "--digit-separators" => format_options.digit_separators = true,
+      "--no-branch-size" => format_options.branch_size = false,
+      // --deny-features=AVX512*,BMI2 fails the run when the code needs any of them
+      _ if flag.starts_with("--deny-features=") => denied_features.extend(
+        flag["--deny-features=".len()..]
+          .split(',')
+          .filter(|x| !x.is_empty()),
+      ),
+      _ if flag.starts_with("--min-string-length=") => {
+        match flag["--min-string-length=".len()..].parse::<usize>() {
+          Ok(length) if length > 0 => min_string_length = length,
+          _ => {
+            print_color(
+              "The minimum string length has to be a number above 0",
+              termcolor::Color::Red,
+            );
+            return;
+          }
+        }
+      }
+      _ if flag.starts_with("--syntax=") => match flag["--syntax=".len()..].parse::<Syntax>() {
+        Ok(syntax) => format_options.syntax = syntax,
+        Err(_) => {
+          print_color(
+            "Unknown syntax, use intel, masm, nasm or gas",
+            termcolor::Color::Red,
+          );
+          return;
+        }
+      },
+      _ => {
+        print_color(&format!("Unknown option {}", flag), termcolor::Color::Red);
+        return;
+      }
+    }
   }
 
-  let bytes = open_file_and_read_bytes(&args[1]);
+  let file = match files.first() {
+    Some(file) => file,
+    None => {
+      print_color(
+        "Please provide a file to disassemble",
+        termcolor::Color::Yellow,
+      );
+      print_color(
+        "Usage: asm_testing [--linear] [--json] [--features] [--deny-features=AVX512*,BMI2] [--strings] [--min-string-length=4] [--syntax=intel|masm|nasm|gas] [--uppercase-mnemonics] [--hex-prefix|--hex-suffix] [--digit-separators] [--no-branch-size] <file>",
+        termcolor::Color::Yellow,
+      );
+      return;
+    }
+  };
+
+  let bytes = open_file_and_read_bytes(file);
   let bytes = match bytes {
     Ok(bytes) => bytes,
     Err(err) => {
@@ -28,7 +89,7 @@ fn main() {
     return;
   }
 
-  let data = parser::parse_pe(bytes);
+  let data = parser::parse_pe(bytes, mode);
   let pe_file = match data {
     Ok(data) => data,
     Err(_) => {
@@ -39,7 +100,42 @@ fn main() {
       return;
     }
   };
-  let _ = tui::draw(pe_file);
+  let extract_strings = || {
+    let xrefs = analysis::build_xrefs(&pe_file);
+    analysis::extract_strings(&pe_file, &xrefs, min_string_length)
+  };
+  if json {
+    let mut value = export::to_json(&pe_file, &format_options);
+    // only with --strings, every string in the file would dwarf the rest of the dump
+    if strings {
+      value["strings"] = serde_json::json!(extract_strings());
+    }
+    println!("{}", value);
+  }
+  if features || !denied_features.is_empty() {
+    let used = analysis::build_cpu_features(&pe_file.text_section);
+    if features && !json {
+      println!("{}", export::cpu_feature_report(&used));
+    }
+    let denied = used
+      .iter()
+      .filter(|x| denied_features.iter().any(|pattern| x.matches(pattern)))
+      .cloned()
+      .collect::<Vec<analysis::CpuFeatureUse>>();
+    if !denied.is_empty() {
+      // stdout only carries the report or JSON, so a CI job can still parse it
+      eprint_color("Denied CPU features used:", termcolor::Color::Red);
+      eprint_color(&export::cpu_feature_report(&denied), termcolor::Color::Red);
+      std::process::exit(1);
+    }
+  }
+  if strings && !json {
+    println!("{}", export::strings_report(&extract_strings()));
+  }
+  if json || features || strings || !denied_features.is_empty() {
+    return;
+  }
+  let _ = tui::draw(pe_file, file, format_options, min_string_length);
 }
 
 fn print_color(text: &str, color: termcolor::Color) {
@@ -52,6 +148,23 @@ fn print_color(text: &str, color: termcolor::Color) {
   stdout.reset().unwrap();
 }
 
+// Colored only when a terminal reads it, a redirected log gets plain text
+fn eprint_color(text: &str, color: termcolor::Color) {
+  use std::io::IsTerminal;
+  use termcolor::{ColorChoice, ColorSpec, StandardStream, WriteColor};
+  let choice = if std::io::stderr().is_terminal() {
+    ColorChoice::Auto
+  } else {
+    ColorChoice::Never
+  };
+  let mut stderr = StandardStream::stderr(choice);
+  stderr
+    .set_color(ColorSpec::new().set_fg(Some(color)))
+    .unwrap();
+  writeln!(&mut stderr, "{}", text).unwrap();
+  stderr.reset().unwrap();
+}
+
 fn open_file_and_read_bytes(file_path: &str) -> Result<Vec<u8>, String> {
   let file = std::fs::File::open(file_path).map_err(|e| match e.kind() {
     std::io::ErrorKind::NotFound => "File not found".to_owned(),
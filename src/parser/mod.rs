@@ -1,31 +1,243 @@
 use crate::parser::header_parse::{parse_pe_header, parse_sections_table};
+use crate::parser::parse_bound_import::parse_bound_imports;
+use crate::parser::parse_cil::parse_cil_methods;
+use crate::parser::parse_clr::parse_clr;
+use crate::parser::parse_dos_stub::parse_dos_stub;
+use crate::parser::parse_exports::parse_exports;
+use crate::parser::parse_imports::parse_imports;
+use crate::parser::parse_pdata::parse_runtime_functions;
+use crate::parser::parse_rich::parse_rich_header;
+use crate::parser::parse_symbols::parse_symbols;
 use crate::parser::parse_text::parse_text_section;
+use crate::parser::parse_tls::parse_tls_callbacks;
+pub use crate::parser::utils::{
+  rva_to_offset, ClrFlags, DataDirectoryTableField, DosStubAnomaly, SectionCharacteristics,
+  StaleBindingReason,
+};
 use crate::parser::utils::{
-  Characteristics, DLLCharacteristics, DataDirectoryTableField, MachineType,
-  OptionalHeaderSubSystem,
+  Characteristics, DLLCharacteristics, MachineType, OptionalHeaderSubSystem,
 };
 use iced_x86::Instruction;
+use std::ops::Range;
 use winnow::stream::Stream;
 use winnow::PResult;
-use winnow::Parser;
 
 mod header_parse;
+mod parse_bound_import;
+mod parse_cil;
+mod parse_clr;
+mod parse_dos_stub;
+mod parse_exports;
+mod parse_imports;
+mod parse_jump_table;
+mod parse_pdata;
+mod parse_rich;
+mod parse_symbols;
 mod parse_text;
+mod parse_tls;
 mod utils;
 
 #[derive(Debug)]
 pub struct PEFile {
+  pub bytes: Vec<u8>, // The whole file as read from disk
   pub headers: PEHeader,
   pub section_table: Vec<SectionEntry>,
   // pub sections_data: Vec<SectionData>,
   pub text_section: SectionData,
+  pub dos_stub_program: DosStubProgram,
+  pub rich_header: Option<RichHeader>, // Only present for binaries linked by MSVC
+  pub bound_imports: BoundImports,
+  pub exports: Vec<ExportEntry>,
+  pub imports: Vec<ImportEntry>,
+  pub tls_callbacks: Vec<u32>, // RVAs of the TLS callbacks
+  pub runtime_functions: Vec<RuntimeFunction>, // .pdata, x64 only
+  pub symbols: Vec<CoffSymbol>,
+  pub clr: Option<ClrData>, // Only present for managed (.NET) binaries
+}
+
+impl PEFile {
+  // Overwrites bytes of the file, returns what was there so the change can be undone
+  pub fn patch(&mut self, offset: usize, bytes: &[u8]) -> Option<Vec<u8>> {
+    let range = offset..offset.checked_add(bytes.len())?;
+    let original = self.bytes.get(range.clone())?.to_vec();
+    self.bytes[range].copy_from_slice(bytes);
+    Some(original)
+  }
+
+  // Where the file on disk ends up once the headers and every section's raw data are accounted for
+  pub fn overlay_offset(&self) -> usize {
+    let headers_end = self
+      .file_regions_without_overlay()
+      .iter()
+      .map(|x| x.range.end)
+      .max()
+      .unwrap_or(0);
+    headers_end.min(self.bytes.len())
+  }
+
+  // The structures the file is made of in file order, gaps such as header padding are left out
+  pub fn file_regions(&self) -> Vec<FileRegion> {
+    let mut regions = self.file_regions_without_overlay();
+    let overlay = self.overlay_offset();
+    if overlay < self.bytes.len() {
+      regions.push(FileRegion {
+        kind: FileRegionKind::Overlay,
+        name: "Overlay".to_owned(),
+        range: overlay..self.bytes.len(),
+      });
+    }
+    regions
+  }
+
+  fn file_regions_without_overlay(&self) -> Vec<FileRegion> {
+    let len = self.bytes.len();
+    let region = |kind, name: &str, start: usize, size: usize| FileRegion {
+      kind,
+      name: name.to_owned(),
+      range: start.min(len)..start.saturating_add(size).min(len),
+    };
+
+    let e_lfanew = self.headers.dos_header.e_lfanew as usize;
+    let nt_headers_size =
+      4 + 20 + self.headers.nt_headers.file_header.size_of_optional_header as usize;
+    let section_table_size = self.section_table.len() * 40;
+    let mut regions = vec![
+      region(FileRegionKind::DosHeader, "DOS Header", 0, 0x40),
+      region(
+        FileRegionKind::DosStub,
+        "DOS Stub",
+        0x40,
+        e_lfanew.saturating_sub(0x40),
+      ),
+      region(
+        FileRegionKind::NtHeaders,
+        "NT Headers",
+        e_lfanew,
+        nt_headers_size,
+      ),
+      region(
+        FileRegionKind::SectionTable,
+        "Section Table",
+        e_lfanew + nt_headers_size,
+        section_table_size,
+      ),
+    ];
+    for section in &self.section_table {
+      regions.push(region(
+        FileRegionKind::Section,
+        &section.name,
+        section.pointer_to_raw_data as usize,
+        section.size_of_raw_data as usize,
+      ));
+    }
+    regions.retain(|x| !x.range.is_empty());
+    regions.sort_by_key(|x| x.range.start);
+    regions
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileRegion {
+  pub kind: FileRegionKind,
+  pub name: String,        // The section name for sections
+  pub range: Range<usize>, // File offsets, clamped to the file
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileRegionKind {
+  DosHeader,
+  DosStub,
+  NtHeaders,
+  SectionTable,
+  Section,
+  Overlay, // Data appended after the last section, installers and signatures live here
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum DisassemblyMode {
+  #[default]
+  RecursiveDescent, // Follows control flow from known entry points, unreached bytes are data
+  LinearSweep, // Decodes every byte in order
 }
 
 #[derive(Debug, Default)]
 pub struct SectionData {
   pub name: String,
-  pub data: Vec<InstructionData>,
+  pub data: Vec<InstructionData>, // Bytes that were never reached are declared with db
   pub bytes: Vec<u8>,
+  pub mode: DisassemblyMode, // Recursive descent falls back to a linear sweep when nothing seeds it
+  pub jump_tables: Vec<JumpTable>, // Sorted by the dispatch address
+}
+
+impl SectionData {
+  pub fn jump_table(&self, dispatch: u64) -> Option<&JumpTable> {
+    let index = self
+      .jump_tables
+      .binary_search_by_key(&dispatch, |x| x.dispatch)
+      .ok()?;
+    self.jump_tables.get(index)
+  }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct JumpTable {
+  pub dispatch: u64,             // VA of the indirect jmp
+  pub table: u64,                // VA of the first entry
+  pub targets: Vec<Option<u64>>, // One per case, in table order, None if the entry doesn't point at code
+}
+
+impl JumpTable {
+  // Sorted and deduplicated, several cases often share a body
+  pub fn unique_targets(&self) -> Vec<u64> {
+    let mut targets = self.targets.iter().flatten().copied().collect::<Vec<u64>>();
+    targets.sort();
+    targets.dedup();
+    targets
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct ExportEntry {
+  pub ordinal: u32,
+  pub name: Option<String>, // Exports can be by ordinal only
+  pub rva: u32,
+  pub forwarder: Option<String>, // "DLL.Function" when the export is forwarded to another DLL
+}
+
+#[derive(Debug, Default)]
+pub struct ImportEntry {
+  pub module: String,       // DLL name as written in the import descriptor
+  pub name: Option<String>, // None when imported by ordinal
+  pub ordinal: Option<u16>,
+  pub iat_rva: u32, // The slot the loader fills with the function's address
+}
+
+impl ImportEntry {
+  // "KERNEL32!CreateFileW", the way debuggers name imported functions
+  pub fn display_name(&self) -> String {
+    let module = match self.module.rsplit_once('.') {
+      Some((module, _)) => module,
+      None => &self.module,
+    };
+    match (&self.name, self.ordinal) {
+      (Some(name), _) => format!("{}!{}", module, name),
+      (None, Some(ordinal)) => format!("{}!#{}", module, ordinal),
+      (None, None) => format!("{}!?", module),
+    }
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct CoffSymbol {
+  pub name: String,
+  pub rva: u32,
+  pub is_function: bool, // Complex type is IMAGE_SYM_DTYPE_FUNCTION
+}
+
+#[derive(Debug, Default)]
+pub struct RuntimeFunction {
+  pub begin_address: u32, // RVA of the first byte of the function
+  pub end_address: u32,   // RVA of the byte after the function
 }
 
 #[derive(Debug)]
@@ -51,6 +263,28 @@ pub struct SectionEntry {
   pub characteristics: u32, // TODO parse this into vec of characteristics, bitfield
 }
 
+impl SectionEntry {
+  pub fn has_characteristic(&self, flag: SectionCharacteristics) -> bool {
+    let value: u32 = flag.into();
+    self.characteristics & value != 0
+  }
+
+  // In u64 so a section header claiming to end past 4GB can't wrap around
+  pub fn contains_rva(&self, rva: u32) -> bool {
+    let end = self.virtual_address as u64 + self.virtual_size.max(self.size_of_raw_data) as u64;
+    rva >= self.virtual_address && (rva as u64) < end
+  }
+
+  // The RVA a file offset is loaded at, None when the offset isn't in the section's raw data
+  pub fn offset_to_rva(&self, offset: u32) -> Option<u32> {
+    let delta = offset.checked_sub(self.pointer_to_raw_data)?;
+    if delta >= self.size_of_raw_data {
+      return None;
+    }
+    self.virtual_address.checked_add(delta)
+  }
+}
+
 #[derive(Debug)]
 pub struct PEHeader {
   pub dos_header: DOSHeader,
@@ -78,6 +312,55 @@ impl Default for OptionalHeader {
   }
 }
 
+impl OptionalHeader {
+  pub fn data_directories(&self) -> &[DataDirectory] {
+    match self {
+      OptionalHeader::ImageOptionalHeader32(header) => &header.data_directories,
+      OptionalHeader::ImageOptionalHeader64(header) => &header.data_directories,
+      OptionalHeader::ImageOptionalHeaderRom(_) => &[],
+    }
+  }
+
+  pub fn data_directory(&self, field: DataDirectoryTableField) -> Option<&DataDirectory> {
+    self
+      .data_directories()
+      .iter()
+      .find(|x| x.field == field && x.virtual_address != 0)
+  }
+
+  pub fn common(&self) -> &CommonOptionalHeaderFields {
+    match self {
+      OptionalHeader::ImageOptionalHeader32(header) => &header.common,
+      OptionalHeader::ImageOptionalHeader64(header) => &header.common,
+      OptionalHeader::ImageOptionalHeaderRom(header) => &header.common,
+    }
+  }
+
+  pub fn image_base(&self) -> u64 {
+    match self {
+      OptionalHeader::ImageOptionalHeader32(header) => header.image_base as u64,
+      OptionalHeader::ImageOptionalHeader64(header) => header.image_base,
+      OptionalHeader::ImageOptionalHeaderRom(_) => 0,
+    }
+  }
+
+  pub fn size_of_image(&self) -> u32 {
+    match self {
+      OptionalHeader::ImageOptionalHeader32(header) => header.size_of_image,
+      OptionalHeader::ImageOptionalHeader64(header) => header.size_of_image,
+      OptionalHeader::ImageOptionalHeaderRom(_) => 0,
+    }
+  }
+
+  pub fn dll_characteristics(&self) -> &[DLLCharacteristics] {
+    match self {
+      OptionalHeader::ImageOptionalHeader32(header) => &header.dll_characteristics,
+      OptionalHeader::ImageOptionalHeader64(header) => &header.dll_characteristics,
+      OptionalHeader::ImageOptionalHeaderRom(_) => &[],
+    }
+  }
+}
+
 #[derive(Debug, Default)]
 pub struct CommonOptionalHeaderFields {
   pub magic: u16,
@@ -175,6 +458,171 @@ pub struct CharacteristicsBlock {
   pub value: u16,
 }
 
+#[derive(Debug, Default)]
+pub struct BoundImports {
+  pub descriptors: Vec<BoundImportDescriptor>,
+  pub stale_reasons: Vec<StaleBindingReason>, // From the DllCharacteristics, apply to every descriptor
+}
+
+#[derive(Debug, Default)]
+pub struct BoundImportDescriptor {
+  // https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#the-bound-import-table
+  pub time_date_stamp: u32, // Timestamp of the DLL the import was bound against
+  pub module_name: String, // Resolved from OffsetModuleName, relative to the start of the bound import table
+  pub forwarder_refs: Vec<BoundForwarderRef>,
+  pub stale_reasons: Vec<StaleBindingReason>, // Only what the timestamp says about this module
+}
+
+#[derive(Debug, Default)]
+pub struct BoundForwarderRef {
+  pub time_date_stamp: u32,
+  pub module_name: String,
+  pub stale_reasons: Vec<StaleBindingReason>,
+}
+
+#[derive(Debug, Default)]
+pub struct ClrData {
+  pub header: ClrHeader,
+  pub metadata_version: String, // Runtime version the metadata was built against, e.g. v4.0.30319
+  pub streams: Vec<MetadataStream>,
+  pub types: Vec<ManagedType>,
+  pub methods: Vec<ManagedMethod>,
+  pub type_refs: Vec<String>, // Indexed by TypeRef row - 1
+  pub fields: Vec<String>,    // Indexed by Field row - 1
+  pub member_refs: Vec<ManagedMemberRef>,
+  pub assembly_refs: Vec<ManagedAssemblyRef>,
+  pub user_strings: Vec<u8>, // Raw #US heap, used to resolve ldstr tokens
+  pub method_bodies: Vec<CilMethodBody>,
+}
+
+#[derive(Debug, Default)]
+pub struct ClrHeader {
+  // https://learn.microsoft.com/en-us/windows/win32/api/corhdr/ns-corhdr-image_cor20_header
+  pub cb: u32, // Size of the header in bytes
+  pub major_runtime_version: u16,
+  pub minor_runtime_version: u16,
+  pub metadata_rva: u32,
+  pub metadata_size: u32,
+  pub flags: Vec<ClrFlags>,   // (u32)
+  pub entry_point_token: u32, // Metadata token of the entry point, or an RVA when COMIMAGE_FLAGS_NATIVE_ENTRYPOINT is set
+  pub resources_rva: u32,
+  pub resources_size: u32,
+  pub strong_name_signature_rva: u32,
+  pub strong_name_signature_size: u32,
+  pub code_manager_table_rva: u32, // Always 0
+  pub code_manager_table_size: u32,
+  pub vtable_fixups_rva: u32,
+  pub vtable_fixups_size: u32,
+  pub export_address_table_jumps_rva: u32, // Always 0
+  pub export_address_table_jumps_size: u32,
+  pub managed_native_header_rva: u32, // Only set for precompiled (ReadyToRun / NGEN) images
+  pub managed_native_header_size: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct MetadataStream {
+  pub name: String, // #~, #Strings, #US, #GUID or #Blob
+  pub offset: u32,  // Relative to the start of the metadata root
+  pub size: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct ManagedType {
+  pub token: u32,
+  pub flags: u32,
+  pub namespace: String,
+  pub name: String,
+  pub extends: String,
+  pub methods: Vec<usize>, // Indexes into ClrData.methods
+}
+
+impl ManagedType {
+  pub fn full_name(&self) -> String {
+    if self.namespace.is_empty() {
+      return self.name.clone();
+    }
+    format!("{}.{}", self.namespace, self.name)
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct ManagedMethod {
+  pub token: u32,
+  pub rva: u32, // 0 for abstract, runtime and P/Invoke methods
+  pub flags: u16,
+  pub name: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ManagedMemberRef {
+  pub token: u32,
+  pub parent: String, // Name of the referenced type, module or method
+  pub name: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ManagedAssemblyRef {
+  pub token: u32,
+  pub name: String,
+  pub culture: String,
+  pub major_version: u16,
+  pub minor_version: u16,
+  pub build_number: u16,
+  pub revision_number: u16,
+}
+
+#[derive(Debug, Default)]
+pub struct CilMethodBody {
+  // https://www.ecma-international.org/publications-and-standards/standards/ecma-335/ (II.25.4 Common Intermediate Language physical layout)
+  pub token: u32,
+  pub name: String,       // Type::Method
+  pub offset: usize, // Offset of the method header in the section, same as InstructionData.offset
+  pub header_size: usize, // 1 for tiny headers, 12 for fat headers
+  pub max_stack: u16,
+  pub code_size: u32,
+  pub local_var_sig_tok: u32, // 0 for tiny headers or methods without locals
+  pub instructions: Vec<CilInstructionData>,
+}
+
+#[derive(Debug, Default)]
+pub struct CilInstructionData {
+  pub offset: usize,
+  pub size: usize,
+  pub bytes: Vec<u8>,
+  pub mnemonic: &'static str,
+  pub operand: String,
+}
+
+#[derive(Debug, Default)]
+pub struct DosStubProgram {
+  pub entry_offset: usize,                // File offset of e_cs:e_ip
+  pub instructions: Vec<InstructionData>, // 16-bit code, offsets are file offsets
+  pub message: Option<String>,            // "$" terminated string printed by the stub
+  pub anomalies: Vec<DosStubAnomaly>,     // Empty for the stub the MSVC linker writes
+}
+
+#[derive(Debug, Default)]
+pub struct RichHeader {
+  pub offset: usize, // File offset of the "DanS" marker
+  pub key: u32,      // XOR key, also the checksum the linker computed
+  pub checksum: u32, // Checksum computed from the file, should match the key
+  pub hash: String, // MD5 of the decoded header, used to cluster samples built with the same toolchain
+  pub entries: Vec<RichEntry>,
+}
+
+#[derive(Debug, Default)]
+pub struct RichEntry {
+  // @comp.id, the tool and its build number
+  pub product_id: u16,
+  pub build_number: u16,
+  pub count: u32, // Number of objects built by this tool
+  pub product_name: Option<&'static str>,
+  pub visual_studio_version: Option<&'static str>,
+}
+
+// Size of the DOS header, the DOS stub starts right after it
+pub const DOS_HEADER_SIZE: usize = 64;
+
 #[derive(Debug, Default)]
 pub struct DOSHeader {
   pub e_magic: String,
@@ -198,24 +646,119 @@ pub struct DOSHeader {
   pub e_lfanew: u32,     // File address of new exe header
 }
 
-fn parse_pe_file<'s>(input: &mut &'s [u8]) -> PResult<PEFile> {
+fn parse_pe_file(input: &mut &[u8], mode: DisassemblyMode) -> PResult<PEFile> {
   let start = input.checkpoint();
+  let bytes = input.to_vec();
   let headers = parse_pe_header(input)?;
   let section_table = parse_sections_table(input, &headers)?;
   input.reset(start);
-  let text_section = parse_text_section(input, &section_table, &headers.nt_headers.file_header)?;
+  let dos_stub_program = parse_dos_stub(&headers);
+  let rich_header = parse_rich_header(input, &headers);
+  // a broken bound import table should not stop the rest of the file from being shown
+  let bound_imports = parse_bound_imports(input, &headers, &section_table).unwrap_or_default();
+  input.reset(start);
+  let exports = parse_exports(input, &headers, &section_table).unwrap_or_default();
+  input.reset(start);
+  let imports = parse_imports(input, &headers, &section_table).unwrap_or_default();
+  input.reset(start);
+  let tls_callbacks = parse_tls_callbacks(input, &headers, &section_table).unwrap_or_default();
+  input.reset(start);
+  let runtime_functions =
+    parse_runtime_functions(input, &headers, &section_table).unwrap_or_default();
+  input.reset(start);
+  let symbols =
+    parse_symbols(input, &headers.nt_headers.file_header, &section_table).unwrap_or_default();
+  input.reset(start);
+
+  let seeds = code_seeds(
+    &headers,
+    &exports,
+    &tls_callbacks,
+    &runtime_functions,
+    &symbols,
+  );
+  let text_section = parse_text_section(input, &section_table, &headers, &seeds, mode)?;
+  input.reset(start);
+
+  let mut clr = parse_clr(input, &headers, &section_table).unwrap_or_default();
+  if let (Some(clr), Some(text_entry)) =
+    (&mut clr, section_table.iter().find(|x| x.name == ".text"))
+  {
+    clr.method_bodies = parse_cil_methods(input, text_entry, clr);
+  }
 
   let pe_file = PEFile {
+    bytes,
     headers,
     section_table,
     text_section,
+    dos_stub_program,
+    rich_header,
+    bound_imports,
+    exports,
+    imports,
+    tls_callbacks,
+    runtime_functions,
+    symbols,
+    clr,
   };
 
   Ok(pe_file)
 }
 
-pub fn parse_pe(bytes: Vec<u8>) -> Result<PEFile, ()> {
+// Every address known to hold code seeds the recursive descent
+fn code_seeds(
+  headers: &PEHeader,
+  exports: &[ExportEntry],
+  tls_callbacks: &[u32],
+  runtime_functions: &[RuntimeFunction],
+  symbols: &[CoffSymbol],
+) -> Vec<u32> {
+  let mut seeds = vec![];
+  if let Some(optional_header) = &headers.nt_headers.optional_header {
+    seeds.push(optional_header.common().address_of_entry_point);
+  }
+  seeds.extend(
+    exports
+      .iter()
+      .filter(|x| x.forwarder.is_none())
+      .map(|x| x.rva),
+  );
+  seeds.extend_from_slice(tls_callbacks);
+  seeds.extend(
+    runtime_functions
+      .iter()
+      .filter(|x| x.begin_address < x.end_address)
+      .map(|x| x.begin_address),
+  );
+  seeds.extend(symbols.iter().filter(|x| x.is_function).map(|x| x.rva));
+  seeds
+}
+
+// Decodes .text again from the file bytes, after they were patched
+pub fn redecode_text_section(pe_file: &mut PEFile) -> anyhow::Result<()> {
+  let seeds = code_seeds(
+    &pe_file.headers,
+    &pe_file.exports,
+    &pe_file.tls_callbacks,
+    &pe_file.runtime_functions,
+    &pe_file.symbols,
+  );
+  let mut input = pe_file.bytes.as_slice();
+  let text_section = parse_text_section(
+    &mut input,
+    &pe_file.section_table,
+    &pe_file.headers,
+    &seeds,
+    pe_file.text_section.mode,
+  )
+  .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+  pe_file.text_section = text_section;
+  Ok(())
+}
+
+pub fn parse_pe(bytes: Vec<u8>, mode: DisassemblyMode) -> anyhow::Result<PEFile> {
   let mut bytes = bytes.as_slice();
-  let res = parse_pe_file.parse_next(&mut bytes).map_err(|_| ())?;
+  let res = parse_pe_file(&mut bytes, mode).map_err(|e| anyhow::anyhow!("{:?}", e))?;
   Ok(res)
 }
@@ -0,0 +1,117 @@
+use crate::parser::utils::{
+  get_le_u16, get_le_u32, get_rva_string, rva_to_offset, DLLCharacteristics,
+  DataDirectoryTableField, StaleBindingReason,
+};
+use crate::parser::{
+  BoundForwarderRef, BoundImportDescriptor, BoundImports, PEHeader, SectionEntry,
+};
+use winnow::error::ErrMode;
+use winnow::error::ErrorKind;
+use winnow::error::ParserError;
+use winnow::PResult;
+use winnow::Parser;
+
+pub fn parse_bound_imports(
+  input: &mut &[u8],
+  pe_header: &PEHeader,
+  sections: &[SectionEntry],
+) -> PResult<BoundImports> {
+  let directory = match &pe_header.nt_headers.optional_header {
+    Some(optional_header) => optional_header.data_directory(DataDirectoryTableField::BOUND_IMPORT),
+    None => None,
+  };
+  let directory = match directory {
+    Some(directory) => directory,
+    None => return Ok(BoundImports::default()),
+  };
+
+  let start = rva_to_offset(sections, directory.virtual_address)
+    .ok_or(ErrMode::from_error_kind(input, ErrorKind::Fail))?;
+  if start + directory.size as usize > input.len() {
+    return Err(ErrMode::from_error_kind(input, ErrorKind::Eof));
+  }
+
+  let mut entries = &input[start..start + directory.size as usize];
+  // module names are offsets from the start of the table, which is also where the descriptors begin
+  let module_name = |offset: u16| {
+    directory
+      .virtual_address
+      .checked_add(offset as u32)
+      .and_then(|rva| get_rva_string(input, sections, rva))
+      .ok_or(ErrMode::from_error_kind(input, ErrorKind::Verify))
+  };
+
+  let mut descriptors = Vec::new();
+
+  loop {
+    let time_date_stamp = get_le_u32.parse_next(&mut entries)?;
+    let offset_module_name = get_le_u16.parse_next(&mut entries)?;
+    let number_of_module_forwarder_refs = get_le_u16.parse_next(&mut entries)?;
+
+    // the table is terminated by a zeroed descriptor
+    if time_date_stamp == 0 && offset_module_name == 0 {
+      break;
+    }
+
+    let mut forwarder_refs = Vec::new();
+    for _ in 0..number_of_module_forwarder_refs {
+      let time_date_stamp = get_le_u32.parse_next(&mut entries)?;
+      let offset_module_name = get_le_u16.parse_next(&mut entries)?;
+      let _reserved = get_le_u16.parse_next(&mut entries)?;
+      forwarder_refs.push(BoundForwarderRef {
+        time_date_stamp,
+        module_name: module_name(offset_module_name)?,
+        stale_reasons: stale_reasons(time_date_stamp),
+      });
+    }
+
+    descriptors.push(BoundImportDescriptor {
+      time_date_stamp,
+      module_name: module_name(offset_module_name)?,
+      forwarder_refs,
+      stale_reasons: stale_reasons(time_date_stamp),
+    });
+  }
+
+  Ok(BoundImports {
+    descriptors,
+    stale_reasons: image_stale_reasons(pe_header),
+  })
+}
+
+// Reasons that apply to every binding in the image, the loader ignores bindings it can't trust
+fn image_stale_reasons(pe_header: &PEHeader) -> Vec<StaleBindingReason> {
+  let mut reasons = Vec::new();
+  let dll_characteristics = match &pe_header.nt_headers.optional_header {
+    Some(optional_header) => optional_header.dll_characteristics(),
+    None => &[],
+  };
+
+  for characteristic in dll_characteristics {
+    match characteristic {
+      DLLCharacteristics::IMAGE_DLLCHARACTERISTICS_DYNAMIC_BASE => {
+        reasons.push(StaleBindingReason::DynamicBase)
+      }
+      DLLCharacteristics::IMAGE_DLLCHARACTERISTICS_NO_BIND => {
+        reasons.push(StaleBindingReason::NoBind)
+      }
+      _ => {}
+    }
+  }
+
+  reasons
+}
+
+// Reasons the timestamp gives for a single module
+fn stale_reasons(time_date_stamp: u32) -> Vec<StaleBindingReason> {
+  let mut reasons = Vec::new();
+
+  if time_date_stamp == 0 {
+    reasons.push(StaleBindingReason::ZeroTimestamp);
+  } else if time_date_stamp as i64 > chrono::Utc::now().timestamp() {
+    // reproducible builds store a hash here, so it can't be matched against the DLL on disk
+    reasons.push(StaleBindingReason::FutureTimestamp);
+  }
+
+  reasons
+}
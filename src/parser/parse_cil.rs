@@ -0,0 +1,620 @@
+use crate::parser::utils::{get_le_u16, get_le_u32, get_single_u8};
+use crate::parser::{CilInstructionData, CilMethodBody, ClrData, SectionEntry};
+use winnow::error::ErrMode;
+use winnow::error::ErrorKind;
+use winnow::error::ParserError;
+use winnow::token::take_while;
+use winnow::PResult;
+use winnow::Parser;
+
+// Method header flags, ECMA-335 II.25.4.4
+const CORILMETHOD_TINY_FORMAT: u8 = 0x2;
+const CORILMETHOD_FAT_FORMAT: u8 = 0x3;
+
+#[derive(Clone, Copy)]
+enum Operand {
+  None,
+  Int8,
+  UInt8,
+  UInt16,
+  Int32,
+  Int64,
+  Float32,
+  Float64,
+  Token,
+  Branch8,
+  Branch32,
+  Switch,
+}
+
+// ECMA-335 Partition III, single byte opcodes
+fn one_byte_opcode(opcode: u8) -> Option<(&'static str, Operand)> {
+  use Operand::*;
+  let op = match opcode {
+    0x00 => ("nop", None),
+    0x01 => ("break", None),
+    0x02 => ("ldarg.0", None),
+    0x03 => ("ldarg.1", None),
+    0x04 => ("ldarg.2", None),
+    0x05 => ("ldarg.3", None),
+    0x06 => ("ldloc.0", None),
+    0x07 => ("ldloc.1", None),
+    0x08 => ("ldloc.2", None),
+    0x09 => ("ldloc.3", None),
+    0x0a => ("stloc.0", None),
+    0x0b => ("stloc.1", None),
+    0x0c => ("stloc.2", None),
+    0x0d => ("stloc.3", None),
+    0x0e => ("ldarg.s", UInt8),
+    0x0f => ("ldarga.s", UInt8),
+    0x10 => ("starg.s", UInt8),
+    0x11 => ("ldloc.s", UInt8),
+    0x12 => ("ldloca.s", UInt8),
+    0x13 => ("stloc.s", UInt8),
+    0x14 => ("ldnull", None),
+    0x15 => ("ldc.i4.m1", None),
+    0x16 => ("ldc.i4.0", None),
+    0x17 => ("ldc.i4.1", None),
+    0x18 => ("ldc.i4.2", None),
+    0x19 => ("ldc.i4.3", None),
+    0x1a => ("ldc.i4.4", None),
+    0x1b => ("ldc.i4.5", None),
+    0x1c => ("ldc.i4.6", None),
+    0x1d => ("ldc.i4.7", None),
+    0x1e => ("ldc.i4.8", None),
+    0x1f => ("ldc.i4.s", Int8),
+    0x20 => ("ldc.i4", Int32),
+    0x21 => ("ldc.i8", Int64),
+    0x22 => ("ldc.r4", Float32),
+    0x23 => ("ldc.r8", Float64),
+    0x25 => ("dup", None),
+    0x26 => ("pop", None),
+    0x27 => ("jmp", Token),
+    0x28 => ("call", Token),
+    0x29 => ("calli", Token),
+    0x2a => ("ret", None),
+    0x2b => ("br.s", Branch8),
+    0x2c => ("brfalse.s", Branch8),
+    0x2d => ("brtrue.s", Branch8),
+    0x2e => ("beq.s", Branch8),
+    0x2f => ("bge.s", Branch8),
+    0x30 => ("bgt.s", Branch8),
+    0x31 => ("ble.s", Branch8),
+    0x32 => ("blt.s", Branch8),
+    0x33 => ("bne.un.s", Branch8),
+    0x34 => ("bge.un.s", Branch8),
+    0x35 => ("bgt.un.s", Branch8),
+    0x36 => ("ble.un.s", Branch8),
+    0x37 => ("blt.un.s", Branch8),
+    0x38 => ("br", Branch32),
+    0x39 => ("brfalse", Branch32),
+    0x3a => ("brtrue", Branch32),
+    0x3b => ("beq", Branch32),
+    0x3c => ("bge", Branch32),
+    0x3d => ("bgt", Branch32),
+    0x3e => ("ble", Branch32),
+    0x3f => ("blt", Branch32),
+    0x40 => ("bne.un", Branch32),
+    0x41 => ("bge.un", Branch32),
+    0x42 => ("bgt.un", Branch32),
+    0x43 => ("ble.un", Branch32),
+    0x44 => ("blt.un", Branch32),
+    0x45 => ("switch", Switch),
+    0x46 => ("ldind.i1", None),
+    0x47 => ("ldind.u1", None),
+    0x48 => ("ldind.i2", None),
+    0x49 => ("ldind.u2", None),
+    0x4a => ("ldind.i4", None),
+    0x4b => ("ldind.u4", None),
+    0x4c => ("ldind.i8", None),
+    0x4d => ("ldind.i", None),
+    0x4e => ("ldind.r4", None),
+    0x4f => ("ldind.r8", None),
+    0x50 => ("ldind.ref", None),
+    0x51 => ("stind.ref", None),
+    0x52 => ("stind.i1", None),
+    0x53 => ("stind.i2", None),
+    0x54 => ("stind.i4", None),
+    0x55 => ("stind.i8", None),
+    0x56 => ("stind.r4", None),
+    0x57 => ("stind.r8", None),
+    0x58 => ("add", None),
+    0x59 => ("sub", None),
+    0x5a => ("mul", None),
+    0x5b => ("div", None),
+    0x5c => ("div.un", None),
+    0x5d => ("rem", None),
+    0x5e => ("rem.un", None),
+    0x5f => ("and", None),
+    0x60 => ("or", None),
+    0x61 => ("xor", None),
+    0x62 => ("shl", None),
+    0x63 => ("shr", None),
+    0x64 => ("shr.un", None),
+    0x65 => ("neg", None),
+    0x66 => ("not", None),
+    0x67 => ("conv.i1", None),
+    0x68 => ("conv.i2", None),
+    0x69 => ("conv.i4", None),
+    0x6a => ("conv.i8", None),
+    0x6b => ("conv.r4", None),
+    0x6c => ("conv.r8", None),
+    0x6d => ("conv.u4", None),
+    0x6e => ("conv.u8", None),
+    0x6f => ("callvirt", Token),
+    0x70 => ("cpobj", Token),
+    0x71 => ("ldobj", Token),
+    0x72 => ("ldstr", Token),
+    0x73 => ("newobj", Token),
+    0x74 => ("castclass", Token),
+    0x75 => ("isinst", Token),
+    0x76 => ("conv.r.un", None),
+    0x79 => ("unbox", Token),
+    0x7a => ("throw", None),
+    0x7b => ("ldfld", Token),
+    0x7c => ("ldflda", Token),
+    0x7d => ("stfld", Token),
+    0x7e => ("ldsfld", Token),
+    0x7f => ("ldsflda", Token),
+    0x80 => ("stsfld", Token),
+    0x81 => ("stobj", Token),
+    0x82 => ("conv.ovf.i1.un", None),
+    0x83 => ("conv.ovf.i2.un", None),
+    0x84 => ("conv.ovf.i4.un", None),
+    0x85 => ("conv.ovf.i8.un", None),
+    0x86 => ("conv.ovf.u1.un", None),
+    0x87 => ("conv.ovf.u2.un", None),
+    0x88 => ("conv.ovf.u4.un", None),
+    0x89 => ("conv.ovf.u8.un", None),
+    0x8a => ("conv.ovf.i.un", None),
+    0x8b => ("conv.ovf.u.un", None),
+    0x8c => ("box", Token),
+    0x8d => ("newarr", Token),
+    0x8e => ("ldlen", None),
+    0x8f => ("ldelema", Token),
+    0x90 => ("ldelem.i1", None),
+    0x91 => ("ldelem.u1", None),
+    0x92 => ("ldelem.i2", None),
+    0x93 => ("ldelem.u2", None),
+    0x94 => ("ldelem.i4", None),
+    0x95 => ("ldelem.u4", None),
+    0x96 => ("ldelem.i8", None),
+    0x97 => ("ldelem.i", None),
+    0x98 => ("ldelem.r4", None),
+    0x99 => ("ldelem.r8", None),
+    0x9a => ("ldelem.ref", None),
+    0x9b => ("stelem.i", None),
+    0x9c => ("stelem.i1", None),
+    0x9d => ("stelem.i2", None),
+    0x9e => ("stelem.i4", None),
+    0x9f => ("stelem.i8", None),
+    0xa0 => ("stelem.r4", None),
+    0xa1 => ("stelem.r8", None),
+    0xa2 => ("stelem.ref", None),
+    0xa3 => ("ldelem", Token),
+    0xa4 => ("stelem", Token),
+    0xa5 => ("unbox.any", Token),
+    0xb3 => ("conv.ovf.i1", None),
+    0xb4 => ("conv.ovf.u1", None),
+    0xb5 => ("conv.ovf.i2", None),
+    0xb6 => ("conv.ovf.u2", None),
+    0xb7 => ("conv.ovf.i4", None),
+    0xb8 => ("conv.ovf.u4", None),
+    0xb9 => ("conv.ovf.i8", None),
+    0xba => ("conv.ovf.u8", None),
+    0xc2 => ("refanyval", Token),
+    0xc3 => ("ckfinite", None),
+    0xc6 => ("mkrefany", Token),
+    0xd0 => ("ldtoken", Token),
+    0xd1 => ("conv.u2", None),
+    0xd2 => ("conv.u1", None),
+    0xd3 => ("conv.i", None),
+    0xd4 => ("conv.ovf.i", None),
+    0xd5 => ("conv.ovf.u", None),
+    0xd6 => ("add.ovf", None),
+    0xd7 => ("add.ovf.un", None),
+    0xd8 => ("mul.ovf", None),
+    0xd9 => ("mul.ovf.un", None),
+    0xda => ("sub.ovf", None),
+    0xdb => ("sub.ovf.un", None),
+    0xdc => ("endfinally", None),
+    0xdd => ("leave", Branch32),
+    0xde => ("leave.s", Branch8),
+    0xdf => ("stind.i", None),
+    0xe0 => ("conv.u", None),
+    _ => return Option::None,
+  };
+  Some(op)
+}
+
+// ECMA-335 Partition III, opcodes prefixed with 0xfe
+fn two_byte_opcode(opcode: u8) -> Option<(&'static str, Operand)> {
+  use Operand::*;
+  let op = match opcode {
+    0x00 => ("arglist", None),
+    0x01 => ("ceq", None),
+    0x02 => ("cgt", None),
+    0x03 => ("cgt.un", None),
+    0x04 => ("clt", None),
+    0x05 => ("clt.un", None),
+    0x06 => ("ldftn", Token),
+    0x07 => ("ldvirtftn", Token),
+    0x09 => ("ldarg", UInt16),
+    0x0a => ("ldarga", UInt16),
+    0x0b => ("starg", UInt16),
+    0x0c => ("ldloc", UInt16),
+    0x0d => ("ldloca", UInt16),
+    0x0e => ("stloc", UInt16),
+    0x0f => ("localloc", None),
+    0x11 => ("endfilter", None),
+    0x12 => ("unaligned.", UInt8),
+    0x13 => ("volatile.", None),
+    0x14 => ("tail.", None),
+    0x15 => ("initobj", Token),
+    0x16 => ("constrained.", Token),
+    0x17 => ("cpblk", None),
+    0x18 => ("initblk", None),
+    0x19 => ("no.", UInt8),
+    0x1a => ("rethrow", None),
+    0x1c => ("sizeof", Token),
+    0x1d => ("refanytype", None),
+    0x1e => ("readonly.", None),
+    _ => return Option::None,
+  };
+  Some(op)
+}
+
+// #US entries are prefixed with a compressed length and hold UTF-16 plus a trailing flag byte
+fn user_string(heap: &[u8], index: usize) -> Option<String> {
+  let first = *heap.get(index)? as usize;
+  let (length, start) = if first & 0x80 == 0 {
+    (first, index + 1)
+  } else if first & 0xc0 == 0x80 {
+    (
+      ((first & 0x3f) << 8) | *heap.get(index + 1)? as usize,
+      index + 2,
+    )
+  } else {
+    let length = ((first & 0x1f) << 24)
+      | (*heap.get(index + 1)? as usize) << 16
+      | (*heap.get(index + 2)? as usize) << 8
+      | *heap.get(index + 3)? as usize;
+    (length, index + 4)
+  };
+  let bytes = heap.get(start..start + length.saturating_sub(1))?;
+  let units = bytes
+    .chunks_exact(2)
+    .map(|x| u16::from_le_bytes([x[0], x[1]]))
+    .collect::<Vec<u16>>();
+  Some(String::from_utf16_lossy(&units))
+}
+
+fn token_name(clr: &ClrData, method_names: &[String], token: u32) -> String {
+  let index = (token & 0x00ff_ffff) as usize;
+  let name = match token >> 24 {
+    0x01 => clr.type_refs.get(index.wrapping_sub(1)).cloned(),
+    0x02 => clr.types.get(index.wrapping_sub(1)).map(|x| x.full_name()),
+    0x04 => clr.fields.get(index.wrapping_sub(1)).cloned(),
+    0x06 => method_names.get(index.wrapping_sub(1)).cloned(),
+    0x0a => clr
+      .member_refs
+      .get(index.wrapping_sub(1))
+      .map(|x| format!("{}::{}", x.parent, x.name)),
+    0x70 => user_string(&clr.user_strings, index).map(|x| format!("{:?}", x)),
+    _ => None,
+  };
+  name.unwrap_or(format!("token({:#010x})", token))
+}
+
+fn parse_instruction(
+  input: &mut &[u8],
+  clr: &ClrData,
+  method_names: &[String],
+  offset: usize,
+) -> PResult<(&'static str, String)> {
+  let opcode = get_single_u8.parse_next(input)?;
+  let (mnemonic, operand) = match opcode {
+    0xfe => two_byte_opcode(get_single_u8.parse_next(input)?),
+    _ => one_byte_opcode(opcode),
+  }
+  .ok_or(ErrMode::from_error_kind(input, ErrorKind::Verify))?;
+
+  // branch targets are relative to the start of the next instruction
+  let operand = match operand {
+    Operand::None => String::new(),
+    Operand::Int8 => format!("{}", get_single_u8.parse_next(input)? as i8),
+    Operand::UInt8 => format!("{}", get_single_u8.parse_next(input)?),
+    Operand::UInt16 => format!("{}", get_le_u16.parse_next(input)?),
+    Operand::Int32 => format!("{}", get_le_u32.parse_next(input)? as i32),
+    Operand::Int64 => format!(
+      "{}",
+      u64::from_le_bytes(
+        take_while(8, |_| true)
+          .parse_next(input)?
+          .try_into()
+          .map_err(|_| ErrMode::from_error_kind(input, ErrorKind::Eof))?
+      ) as i64
+    ),
+    Operand::Float32 => format!("{}", f32::from_bits(get_le_u32.parse_next(input)?)),
+    Operand::Float64 => format!(
+      "{}",
+      f64::from_bits(u64::from_le_bytes(
+        take_while(8, |_| true)
+          .parse_next(input)?
+          .try_into()
+          .map_err(|_| ErrMode::from_error_kind(input, ErrorKind::Eof))?
+      ))
+    ),
+    Operand::Token => token_name(clr, method_names, get_le_u32.parse_next(input)?),
+    Operand::Branch8 => {
+      let delta = get_single_u8.parse_next(input)? as i8 as isize;
+      format!("{:#x}", (offset as isize + 2 + delta) as usize)
+    }
+    Operand::Branch32 => {
+      let delta = get_le_u32.parse_next(input)? as i32 as isize;
+      format!("{:#x}", (offset as isize + 5 + delta) as usize)
+    }
+    Operand::Switch => {
+      let count = get_le_u32.parse_next(input)? as usize;
+      let next = offset + 5 + count * 4;
+      let mut targets = Vec::new();
+      for _ in 0..count {
+        let delta = get_le_u32.parse_next(input)? as i32 as isize;
+        targets.push(format!("{:#x}", (next as isize + delta) as usize));
+      }
+      format!("({})", targets.join(", "))
+    }
+  };
+
+  Ok((mnemonic, operand))
+}
+
+fn parse_method_body(
+  section_bytes: &[u8],
+  offset: usize,
+  clr: &ClrData,
+  method_names: &[String],
+) -> PResult<CilMethodBody> {
+  let mut input = section_bytes
+    .get(offset..)
+    .ok_or(ErrMode::from_error_kind(&section_bytes, ErrorKind::Eof))?;
+  let first = get_single_u8.parse_next(&mut input)?;
+
+  let mut body = CilMethodBody {
+    offset,
+    ..Default::default()
+  };
+  match first & 0x3 {
+    CORILMETHOD_TINY_FORMAT => {
+      body.header_size = 1;
+      body.max_stack = 8;
+      body.code_size = (first >> 2) as u32;
+    }
+    CORILMETHOD_FAT_FORMAT => {
+      let second = get_single_u8.parse_next(&mut input)?;
+      // header size is stored in dwords in the top 4 bits
+      body.header_size = (second >> 4) as usize * 4;
+      body.max_stack = get_le_u16.parse_next(&mut input)?;
+      body.code_size = get_le_u32.parse_next(&mut input)?;
+      body.local_var_sig_tok = get_le_u32.parse_next(&mut input)?;
+    }
+    _ => return Err(ErrMode::from_error_kind(&input, ErrorKind::Verify)),
+  }
+
+  let code_start = offset + body.header_size;
+  let code = section_bytes
+    .get(code_start..code_start + body.code_size as usize)
+    .ok_or(ErrMode::from_error_kind(&input, ErrorKind::Eof))?;
+  let mut code_input = code;
+
+  while !code_input.is_empty() {
+    let instr_offset = code_start + (code.len() - code_input.len());
+    let before = code_input;
+    let (mnemonic, operand) =
+      match parse_instruction(&mut code_input, clr, method_names, instr_offset) {
+        Ok(x) => x,
+        // keep what was decoded so far, the rest is shown as raw bytes
+        Err(_) => {
+          body.instructions.push(CilInstructionData {
+            offset: instr_offset,
+            size: before.len(),
+            bytes: before.to_vec(),
+            mnemonic: ".bytes",
+            operand: String::new(),
+          });
+          break;
+        }
+      };
+    let size = before.len() - code_input.len();
+    body.instructions.push(CilInstructionData {
+      offset: instr_offset,
+      size,
+      bytes: before[..size].to_vec(),
+      mnemonic,
+      operand,
+    });
+  }
+
+  Ok(body)
+}
+
+// Decodes the IL of every method with a body that lives in the given section
+pub fn parse_cil_methods(
+  input: &[u8],
+  section: &SectionEntry,
+  clr: &ClrData,
+) -> Vec<CilMethodBody> {
+  let start = section.pointer_to_raw_data as usize;
+  let section_bytes = match input.get(start..start + section.size_of_raw_data as usize) {
+    Some(bytes) => bytes,
+    None => return vec![],
+  };
+
+  let mut method_names = clr
+    .methods
+    .iter()
+    .map(|x| x.name.clone())
+    .collect::<Vec<String>>();
+  for managed_type in &clr.types {
+    for index in &managed_type.methods {
+      method_names[*index] = format!("{}::{}", managed_type.full_name(), clr.methods[*index].name);
+    }
+  }
+
+  let mut bodies = Vec::new();
+  for (index, method) in clr.methods.iter().enumerate() {
+    if method.rva == 0 {
+      continue;
+    }
+    // in u64 so a section that ends past 4GB can't wrap around
+    if method.rva < section.virtual_address
+      || method.rva as u64 >= section.virtual_address as u64 + section.size_of_raw_data as u64
+    {
+      continue;
+    }
+    let offset = (method.rva - section.virtual_address) as usize;
+    if let Ok(mut body) = parse_method_body(section_bytes, offset, clr, &method_names) {
+      body.token = method.token;
+      body.name = method_names[index].clone();
+      bodies.push(body);
+    }
+  }
+
+  bodies.sort_by_key(|x| x.offset);
+  bodies
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn decode(section: &[u8]) -> CilMethodBody {
+    parse_method_body(section, 0, &ClrData::default(), &[]).unwrap()
+  }
+
+  // a tiny header keeps the code size in its top 6 bits
+  fn tiny(code: &[u8]) -> Vec<u8> {
+    [
+      &[((code.len() as u8) << 2) | CORILMETHOD_TINY_FORMAT][..],
+      code,
+    ]
+    .concat()
+  }
+
+  fn decoded(body: &CilMethodBody) -> Vec<(&str, &str, usize)> {
+    body
+      .instructions
+      .iter()
+      .map(|x| (x.mnemonic, x.operand.as_str(), x.offset))
+      .collect()
+  }
+
+  #[test]
+  fn tiny_header() {
+    let body = decode(&tiny(&[0x16, 0x17, 0x2a])); // ldc.i4.0, ldc.i4.1, ret
+    assert_eq!(body.header_size, 1);
+    assert_eq!(body.max_stack, 8);
+    assert_eq!(body.code_size, 3);
+    assert_eq!(
+      decoded(&body),
+      [("ldc.i4.0", "", 1), ("ldc.i4.1", "", 2), ("ret", "", 3)]
+    );
+  }
+
+  #[test]
+  fn fat_header() {
+    let section = [
+      &[CORILMETHOD_FAT_FORMAT | 0x10, 0x30][..], // init locals, 3 dword header
+      &4u16.to_le_bytes(),                        // max stack
+      &2u32.to_le_bytes(),                        // code size
+      &0x1100_0001u32.to_le_bytes(),              // locals signature token
+      &[0x00, 0x2a],                              // nop, ret
+    ]
+    .concat();
+    let body = decode(&section);
+    assert_eq!(body.header_size, 12);
+    assert_eq!(body.max_stack, 4);
+    assert_eq!(body.code_size, 2);
+    assert_eq!(body.local_var_sig_tok, 0x1100_0001);
+    assert_eq!(decoded(&body), [("nop", "", 12), ("ret", "", 13)]);
+  }
+
+  #[test]
+  fn unknown_header_format() {
+    assert!(parse_method_body(&[0x01, 0x2a], 0, &ClrData::default(), &[]).is_err());
+  }
+
+  #[test]
+  fn prefixed_opcodes() {
+    // ceq, ldarg 0x102, readonly., ret
+    let body = decode(&tiny(&[
+      0xfe, 0x01, 0xfe, 0x09, 0x02, 0x01, 0xfe, 0x1e, 0x2a,
+    ]));
+    assert_eq!(
+      decoded(&body),
+      [
+        ("ceq", "", 1),
+        ("ldarg", "258", 3),
+        ("readonly.", "", 7),
+        ("ret", "", 9)
+      ]
+    );
+  }
+
+  #[test]
+  fn unknown_prefixed_opcode_is_kept_as_bytes() {
+    let body = decode(&tiny(&[0xfe, 0xff, 0x2a]));
+    assert_eq!(body.instructions.len(), 1);
+    assert_eq!(body.instructions[0].mnemonic, ".bytes");
+    assert_eq!(body.instructions[0].bytes, [0xfe, 0xff, 0x2a]);
+  }
+
+  #[test]
+  fn switch_targets() {
+    // the switch at 1 is 13 bytes, its targets count from the ret at 14
+    let code = [
+      &[0x45][..],
+      &2u32.to_le_bytes(),
+      &0i32.to_le_bytes(),
+      &(-2i32).to_le_bytes(),
+      &[0x2a],
+    ]
+    .concat();
+    let body = decode(&tiny(&code));
+    assert_eq!(
+      decoded(&body),
+      [("switch", "(0xe, 0xc)", 1), ("ret", "", 14)]
+    );
+  }
+
+  #[test]
+  fn branch_targets() {
+    // br.s -2 jumps to itself, br +0 to the next instruction
+    let body = decode(&tiny(&[0x2b, 0xfe, 0x38, 0x00, 0x00, 0x00, 0x00, 0x2a]));
+    assert_eq!(
+      decoded(&body),
+      [("br.s", "0x1", 1), ("br", "0x8", 3), ("ret", "", 8)]
+    );
+  }
+
+  #[test]
+  fn section_ending_past_4gb() {
+    let section = SectionEntry {
+      virtual_address: 0xffff_f000,
+      size_of_raw_data: 0x2000,
+      ..Default::default()
+    };
+    let clr = ClrData {
+      methods: vec![crate::parser::ManagedMethod {
+        rva: 0xffff_f010,
+        ..Default::default()
+      }],
+      ..Default::default()
+    };
+    let mut bytes = vec![0u8; 0x2000];
+    bytes[0x10..0x12].copy_from_slice(&tiny(&[0x2a]));
+    let bodies = parse_cil_methods(&bytes, &section, &clr);
+    assert_eq!(bodies.len(), 1);
+    assert_eq!(bodies[0].offset, 0x10);
+  }
+}
@@ -0,0 +1,588 @@
+use crate::parser::utils::{
+  get_le_u16, get_le_u32, get_le_u64, get_rva_slice, get_single_u8, ClrFlags,
+  DataDirectoryTableField,
+};
+use crate::parser::{
+  ClrData, ClrHeader, ManagedAssemblyRef, ManagedMemberRef, ManagedMethod, ManagedType,
+  MetadataStream, PEHeader, SectionEntry,
+};
+use winnow::error::ErrMode;
+use winnow::error::ErrorKind;
+use winnow::error::ParserError;
+use winnow::token::take_while;
+use winnow::PResult;
+use winnow::Parser;
+
+// https://www.ecma-international.org/publications-and-standards/standards/ecma-335/ (II.22 Metadata logical format: tables)
+const TABLE_MODULE: usize = 0x00;
+const TABLE_TYPE_REF: usize = 0x01;
+const TABLE_TYPE_DEF: usize = 0x02;
+const TABLE_FIELD: usize = 0x04;
+const TABLE_METHOD_DEF: usize = 0x06;
+const TABLE_PARAM: usize = 0x08;
+const TABLE_INTERFACE_IMPL: usize = 0x09;
+const TABLE_MEMBER_REF: usize = 0x0a;
+const TABLE_DECL_SECURITY: usize = 0x0e;
+const TABLE_STAND_ALONE_SIG: usize = 0x11;
+const TABLE_EVENT: usize = 0x14;
+const TABLE_PROPERTY: usize = 0x17;
+const TABLE_MODULE_REF: usize = 0x1a;
+const TABLE_TYPE_SPEC: usize = 0x1b;
+const TABLE_ASSEMBLY: usize = 0x20;
+const TABLE_ASSEMBLY_REF: usize = 0x23;
+const TABLE_FILE: usize = 0x26;
+const TABLE_EXPORTED_TYPE: usize = 0x27;
+const TABLE_MANIFEST_RESOURCE: usize = 0x28;
+const TABLE_GENERIC_PARAM: usize = 0x2a;
+const TABLE_METHOD_SPEC: usize = 0x2b;
+const TABLE_GENERIC_PARAM_CONSTRAINT: usize = 0x2c;
+const TABLE_COUNT: usize = 0x2d;
+
+// Unused slots in a coded index
+const NONE: usize = usize::MAX;
+
+struct CodedIndex {
+  tag_bits: u32,
+  tables: &'static [usize],
+}
+
+const TYPE_DEF_OR_REF: CodedIndex = CodedIndex {
+  tag_bits: 2,
+  tables: &[TABLE_TYPE_DEF, TABLE_TYPE_REF, TABLE_TYPE_SPEC],
+};
+const HAS_CONSTANT: CodedIndex = CodedIndex {
+  tag_bits: 2,
+  tables: &[TABLE_FIELD, TABLE_PARAM, TABLE_PROPERTY],
+};
+const HAS_CUSTOM_ATTRIBUTE: CodedIndex = CodedIndex {
+  tag_bits: 5,
+  tables: &[
+    TABLE_METHOD_DEF,
+    TABLE_FIELD,
+    TABLE_TYPE_REF,
+    TABLE_TYPE_DEF,
+    TABLE_PARAM,
+    TABLE_INTERFACE_IMPL,
+    TABLE_MEMBER_REF,
+    TABLE_MODULE,
+    TABLE_DECL_SECURITY,
+    TABLE_PROPERTY,
+    TABLE_EVENT,
+    TABLE_STAND_ALONE_SIG,
+    TABLE_MODULE_REF,
+    TABLE_TYPE_SPEC,
+    TABLE_ASSEMBLY,
+    TABLE_ASSEMBLY_REF,
+    TABLE_FILE,
+    TABLE_EXPORTED_TYPE,
+    TABLE_MANIFEST_RESOURCE,
+    TABLE_GENERIC_PARAM,
+    TABLE_GENERIC_PARAM_CONSTRAINT,
+    TABLE_METHOD_SPEC,
+  ],
+};
+const HAS_FIELD_MARSHAL: CodedIndex = CodedIndex {
+  tag_bits: 1,
+  tables: &[TABLE_FIELD, TABLE_PARAM],
+};
+const HAS_DECL_SECURITY: CodedIndex = CodedIndex {
+  tag_bits: 2,
+  tables: &[TABLE_TYPE_DEF, TABLE_METHOD_DEF, TABLE_ASSEMBLY],
+};
+const MEMBER_REF_PARENT: CodedIndex = CodedIndex {
+  tag_bits: 3,
+  tables: &[
+    TABLE_TYPE_DEF,
+    TABLE_TYPE_REF,
+    TABLE_MODULE_REF,
+    TABLE_METHOD_DEF,
+    TABLE_TYPE_SPEC,
+  ],
+};
+const HAS_SEMANTICS: CodedIndex = CodedIndex {
+  tag_bits: 1,
+  tables: &[TABLE_EVENT, TABLE_PROPERTY],
+};
+const METHOD_DEF_OR_REF: CodedIndex = CodedIndex {
+  tag_bits: 1,
+  tables: &[TABLE_METHOD_DEF, TABLE_MEMBER_REF],
+};
+const MEMBER_FORWARDED: CodedIndex = CodedIndex {
+  tag_bits: 1,
+  tables: &[TABLE_FIELD, TABLE_METHOD_DEF],
+};
+const IMPLEMENTATION: CodedIndex = CodedIndex {
+  tag_bits: 2,
+  tables: &[TABLE_FILE, TABLE_ASSEMBLY_REF, TABLE_EXPORTED_TYPE],
+};
+const CUSTOM_ATTRIBUTE_TYPE: CodedIndex = CodedIndex {
+  tag_bits: 3,
+  tables: &[NONE, NONE, TABLE_METHOD_DEF, TABLE_MEMBER_REF, NONE],
+};
+const RESOLUTION_SCOPE: CodedIndex = CodedIndex {
+  tag_bits: 2,
+  tables: &[
+    TABLE_MODULE,
+    TABLE_MODULE_REF,
+    TABLE_ASSEMBLY_REF,
+    TABLE_TYPE_REF,
+  ],
+};
+const TYPE_OR_METHOD_DEF: CodedIndex = CodedIndex {
+  tag_bits: 1,
+  tables: &[TABLE_TYPE_DEF, TABLE_METHOD_DEF],
+};
+
+enum Column {
+  U16,
+  U32,
+  String,
+  Guid,
+  Blob,
+  Table(usize),
+  Coded(&'static CodedIndex),
+}
+
+fn table_columns(table: usize) -> &'static [Column] {
+  use Column::*;
+  match table {
+    0x00 => &[U16, String, Guid, Guid, Guid], // Module
+    0x01 => &[Coded(&RESOLUTION_SCOPE), String, String], // TypeRef
+    0x02 => &[
+      U32,
+      String,
+      String,
+      Coded(&TYPE_DEF_OR_REF),
+      Table(TABLE_FIELD),
+      Table(TABLE_METHOD_DEF),
+    ], // TypeDef
+    0x03 => &[Table(TABLE_FIELD)],            // FieldPtr
+    0x04 => &[U16, String, Blob],             // Field
+    0x05 => &[Table(TABLE_METHOD_DEF)],       // MethodPtr
+    0x06 => &[U32, U16, U16, String, Blob, Table(TABLE_PARAM)], // MethodDef
+    0x07 => &[Table(TABLE_PARAM)],            // ParamPtr
+    0x08 => &[U16, U16, String],              // Param
+    0x09 => &[Table(TABLE_TYPE_DEF), Coded(&TYPE_DEF_OR_REF)], // InterfaceImpl
+    0x0a => &[Coded(&MEMBER_REF_PARENT), String, Blob], // MemberRef
+    0x0b => &[U16, Coded(&HAS_CONSTANT), Blob], // Constant (type byte + padding byte)
+    0x0c => &[
+      Coded(&HAS_CUSTOM_ATTRIBUTE),
+      Coded(&CUSTOM_ATTRIBUTE_TYPE),
+      Blob,
+    ], // CustomAttribute
+    0x0d => &[Coded(&HAS_FIELD_MARSHAL), Blob], // FieldMarshal
+    0x0e => &[U16, Coded(&HAS_DECL_SECURITY), Blob], // DeclSecurity
+    0x0f => &[U16, U32, Table(TABLE_TYPE_DEF)], // ClassLayout
+    0x10 => &[U32, Table(TABLE_FIELD)],       // FieldLayout
+    0x11 => &[Blob],                          // StandAloneSig
+    0x12 => &[Table(TABLE_TYPE_DEF), Table(TABLE_EVENT)], // EventMap
+    0x13 => &[Table(TABLE_EVENT)],            // EventPtr
+    0x14 => &[U16, String, Coded(&TYPE_DEF_OR_REF)], // Event
+    0x15 => &[Table(TABLE_TYPE_DEF), Table(TABLE_PROPERTY)], // PropertyMap
+    0x16 => &[Table(TABLE_PROPERTY)],         // PropertyPtr
+    0x17 => &[U16, String, Blob],             // Property
+    0x18 => &[U16, Table(TABLE_METHOD_DEF), Coded(&HAS_SEMANTICS)], // MethodSemantics
+    0x19 => &[
+      Table(TABLE_TYPE_DEF),
+      Coded(&METHOD_DEF_OR_REF),
+      Coded(&METHOD_DEF_OR_REF),
+    ], // MethodImpl
+    0x1a => &[String],                        // ModuleRef
+    0x1b => &[Blob],                          // TypeSpec
+    0x1c => &[
+      U16,
+      Coded(&MEMBER_FORWARDED),
+      String,
+      Table(TABLE_MODULE_REF),
+    ], // ImplMap
+    0x1d => &[U32, Table(TABLE_FIELD)],       // FieldRVA
+    0x1e => &[U32, U32],                      // EncLog
+    0x1f => &[U32],                           // EncMap
+    0x20 => &[U32, U16, U16, U16, U16, U32, Blob, String, String], // Assembly
+    0x21 => &[U32],                           // AssemblyProcessor
+    0x22 => &[U32, U32, U32],                 // AssemblyOS
+    0x23 => &[U16, U16, U16, U16, U32, Blob, String, String, Blob], // AssemblyRef
+    0x24 => &[U32, Table(TABLE_ASSEMBLY_REF)], // AssemblyRefProcessor
+    0x25 => &[U32, U32, U32, Table(TABLE_ASSEMBLY_REF)], // AssemblyRefOS
+    0x26 => &[U32, String, Blob],             // File
+    0x27 => &[U32, U32, String, String, Coded(&IMPLEMENTATION)], // ExportedType
+    0x28 => &[U32, U32, String, Coded(&IMPLEMENTATION)], // ManifestResource
+    0x29 => &[Table(TABLE_TYPE_DEF), Table(TABLE_TYPE_DEF)], // NestedClass
+    0x2a => &[U16, U16, Coded(&TYPE_OR_METHOD_DEF), String], // GenericParam
+    0x2b => &[Coded(&METHOD_DEF_OR_REF), Blob], // MethodSpec
+    0x2c => &[Table(TABLE_GENERIC_PARAM), Coded(&TYPE_DEF_OR_REF)], // GenericParamConstraint
+    _ => &[],
+  }
+}
+
+struct MetadataTables<'a> {
+  rows: [u32; TABLE_COUNT],
+  offsets: [usize; TABLE_COUNT],
+  data: &'a [u8],
+  strings: &'a [u8],
+  large_strings: bool,
+  large_guids: bool,
+  large_blobs: bool,
+}
+
+impl<'a> MetadataTables<'a> {
+  fn column_size(&self, column: &Column) -> usize {
+    match column {
+      Column::U16 => 2,
+      Column::U32 => 4,
+      Column::String => 2 + 2 * self.large_strings as usize,
+      Column::Guid => 2 + 2 * self.large_guids as usize,
+      Column::Blob => 2 + 2 * self.large_blobs as usize,
+      Column::Table(table) => 2 + 2 * (self.rows[*table] > 0xffff) as usize,
+      Column::Coded(coded) => {
+        let max_rows = coded
+          .tables
+          .iter()
+          .filter(|x| **x != NONE)
+          .map(|x| self.rows[*x])
+          .max()
+          .unwrap_or(0);
+        2 + 2 * (max_rows >= 1 << (16 - coded.tag_bits)) as usize
+      }
+    }
+  }
+
+  fn row_size(&self, table: usize) -> usize {
+    table_columns(table)
+      .iter()
+      .map(|x| self.column_size(x))
+      .sum()
+  }
+
+  // rows are 1 based, like metadata tokens
+  fn row(&self, table: usize, index: u32) -> Option<Vec<u32>> {
+    if index == 0 || index > self.rows[table] {
+      return None;
+    }
+    let row_size = self.row_size(table);
+    let start = self.offsets[table] + (index as usize - 1) * row_size;
+    let mut bytes = self.data.get(start..start + row_size)?;
+
+    let mut values = Vec::new();
+    for column in table_columns(table) {
+      let value = match self.column_size(column) {
+        2 => get_le_u16.parse_next(&mut bytes).ok()? as u32,
+        _ => get_le_u32.parse_next(&mut bytes).ok()?,
+      };
+      values.push(value);
+    }
+    Some(values)
+  }
+
+  fn string(&self, index: u32) -> String {
+    match self.strings.get(index as usize..) {
+      Some(bytes) => {
+        let bytes = bytes.iter().take_while(|b| **b != 0).copied().collect();
+        String::from_utf8(bytes).unwrap_or_default()
+      }
+      None => String::new(),
+    }
+  }
+
+  fn type_ref_name(&self, index: u32) -> String {
+    match self.row(TABLE_TYPE_REF, index) {
+      Some(row) => join_name(&self.string(row[2]), &self.string(row[1])),
+      None => format!("TypeRef({:#x})", index),
+    }
+  }
+
+  fn type_def_name(&self, index: u32) -> String {
+    match self.row(TABLE_TYPE_DEF, index) {
+      Some(row) => join_name(&self.string(row[2]), &self.string(row[1])),
+      None => format!("TypeDef({:#x})", index),
+    }
+  }
+
+  fn coded_name(&self, coded: &CodedIndex, value: u32) -> String {
+    let tag = (value & ((1 << coded.tag_bits) - 1)) as usize;
+    let index = value >> coded.tag_bits;
+    match coded.tables.get(tag) {
+      Some(&TABLE_TYPE_DEF) => self.type_def_name(index),
+      Some(&TABLE_TYPE_REF) => self.type_ref_name(index),
+      Some(&TABLE_MODULE_REF) => match self.row(TABLE_MODULE_REF, index) {
+        Some(row) => self.string(row[0]),
+        None => format!("ModuleRef({:#x})", index),
+      },
+      Some(&TABLE_METHOD_DEF) => match self.row(TABLE_METHOD_DEF, index) {
+        Some(row) => self.string(row[3]),
+        None => format!("MethodDef({:#x})", index),
+      },
+      Some(&TABLE_TYPE_SPEC) => format!("TypeSpec({:#x})", index),
+      _ => String::new(),
+    }
+  }
+}
+
+fn join_name(namespace: &str, name: &str) -> String {
+  if namespace.is_empty() {
+    return name.to_owned();
+  }
+  format!("{}.{}", namespace, name)
+}
+
+fn make_token(table: usize, index: u32) -> u32 {
+  (table as u32) << 24 | index
+}
+
+fn parse_clr_header(input: &mut &[u8]) -> PResult<ClrHeader> {
+  Ok(ClrHeader {
+    cb: get_le_u32.parse_next(input)?,
+    major_runtime_version: get_le_u16.parse_next(input)?,
+    minor_runtime_version: get_le_u16.parse_next(input)?,
+    metadata_rva: get_le_u32.parse_next(input)?,
+    metadata_size: get_le_u32.parse_next(input)?,
+    flags: ClrFlags::from_u32(get_le_u32.parse_next(input)?),
+    entry_point_token: get_le_u32.parse_next(input)?,
+    resources_rva: get_le_u32.parse_next(input)?,
+    resources_size: get_le_u32.parse_next(input)?,
+    strong_name_signature_rva: get_le_u32.parse_next(input)?,
+    strong_name_signature_size: get_le_u32.parse_next(input)?,
+    code_manager_table_rva: get_le_u32.parse_next(input)?,
+    code_manager_table_size: get_le_u32.parse_next(input)?,
+    vtable_fixups_rva: get_le_u32.parse_next(input)?,
+    vtable_fixups_size: get_le_u32.parse_next(input)?,
+    export_address_table_jumps_rva: get_le_u32.parse_next(input)?,
+    export_address_table_jumps_size: get_le_u32.parse_next(input)?,
+    managed_native_header_rva: get_le_u32.parse_next(input)?,
+    managed_native_header_size: get_le_u32.parse_next(input)?,
+  })
+}
+
+fn parse_metadata_root(input: &mut &[u8]) -> PResult<(String, Vec<MetadataStream>)> {
+  let signature = get_le_u32.parse_next(input)?;
+  // "BSJB"
+  if signature != 0x424a5342 {
+    return Err(ErrMode::from_error_kind(input, ErrorKind::Verify));
+  }
+  let _major_version = get_le_u16.parse_next(input)?;
+  let _minor_version = get_le_u16.parse_next(input)?;
+  let _reserved = get_le_u32.parse_next(input)?;
+  let length = get_le_u32.parse_next(input)? as usize;
+  let version = take_while(length, |_| true).parse_next(input)?;
+  let version = String::from_utf8_lossy(version)
+    .trim_end_matches('\0')
+    .to_owned();
+  let _flags = get_le_u16.parse_next(input)?;
+  let number_of_streams = get_le_u16.parse_next(input)?;
+
+  let mut streams = Vec::new();
+  for _ in 0..number_of_streams {
+    let offset = get_le_u32.parse_next(input)?;
+    let size = get_le_u32.parse_next(input)?;
+
+    // name is null terminated and padded to the next 4 byte boundary
+    let mut name = Vec::new();
+    loop {
+      let chunk = take_while(4, |_| true).parse_next(input)?;
+      if chunk.len() != 4 {
+        return Err(ErrMode::from_error_kind(input, ErrorKind::Eof));
+      }
+      name.extend(chunk.iter().take_while(|b| **b != 0));
+      if chunk.contains(&0) {
+        break;
+      }
+    }
+
+    streams.push(MetadataStream {
+      name: String::from_utf8_lossy(&name).into_owned(),
+      offset,
+      size,
+    });
+  }
+
+  Ok((version, streams))
+}
+
+pub fn parse_clr(
+  input: &mut &[u8],
+  pe_header: &PEHeader,
+  sections: &[SectionEntry],
+) -> PResult<Option<ClrData>> {
+  let directory = match &pe_header.nt_headers.optional_header {
+    Some(optional_header) => {
+      optional_header.data_directory(DataDirectoryTableField::CLR_RUNTIME_HEADER)
+    }
+    None => None,
+  };
+  let directory = match directory {
+    Some(directory) => directory,
+    None => return Ok(None),
+  };
+
+  let file = *input;
+  let mut header_bytes = get_rva_slice(file, sections, directory.virtual_address, 72)
+    .ok_or(ErrMode::from_error_kind(input, ErrorKind::Eof))?;
+  let header = parse_clr_header(&mut header_bytes)?;
+
+  let metadata = get_rva_slice(file, sections, header.metadata_rva, header.metadata_size)
+    .ok_or(ErrMode::from_error_kind(input, ErrorKind::Eof))?;
+  let (metadata_version, streams) = parse_metadata_root(&mut &metadata[..])?;
+
+  // a stream whose end doesn't fit in 32 bits can't be in the metadata, it counts as missing
+  let stream = |name: &str| {
+    streams.iter().find(|x| x.name == name).and_then(|x| {
+      let end = x.offset.checked_add(x.size)?;
+      metadata.get(x.offset as usize..end as usize)
+    })
+  };
+  let strings = stream("#Strings").unwrap_or(&[]);
+  // #- is the uncompressed (edit and continue) variant, its header has the same layout
+  let mut table_stream = stream("#~")
+    .or(stream("#-"))
+    .ok_or(ErrMode::from_error_kind(input, ErrorKind::Verify))?;
+
+  let _reserved = get_le_u32.parse_next(&mut table_stream)?;
+  let _major_version = get_single_u8.parse_next(&mut table_stream)?;
+  let _minor_version = get_single_u8.parse_next(&mut table_stream)?;
+  let heap_sizes = get_single_u8.parse_next(&mut table_stream)?;
+  let _reserved = get_single_u8.parse_next(&mut table_stream)?;
+  let valid = get_le_u64.parse_next(&mut table_stream)?;
+  let _sorted = get_le_u64.parse_next(&mut table_stream)?;
+
+  let mut rows = [0u32; TABLE_COUNT];
+  for (table, count) in rows.iter_mut().enumerate() {
+    if valid & (1 << table) != 0 {
+      *count = get_le_u32.parse_next(&mut table_stream)?;
+    }
+  }
+  // tables past GenericParamConstraint are unknown, but their row counts still have to be skipped
+  for _ in 0..(valid >> TABLE_COUNT).count_ones() {
+    let _rows = get_le_u32.parse_next(&mut table_stream)?;
+  }
+  // extra data flag, only emitted by some obfuscators
+  if heap_sizes & 0x40 != 0 {
+    let _extra_data = get_le_u32.parse_next(&mut table_stream)?;
+  }
+
+  let mut tables = MetadataTables {
+    rows,
+    offsets: [0; TABLE_COUNT],
+    data: table_stream,
+    strings,
+    large_strings: heap_sizes & 0x01 != 0,
+    large_guids: heap_sizes & 0x02 != 0,
+    large_blobs: heap_sizes & 0x04 != 0,
+  };
+  let mut offset = 0;
+  for table in 0..TABLE_COUNT {
+    tables.offsets[table] = offset;
+    offset += tables.row_size(table) * tables.rows[table] as usize;
+  }
+
+  let mut methods = Vec::new();
+  for index in 1..=tables.rows[TABLE_METHOD_DEF] {
+    let row = tables
+      .row(TABLE_METHOD_DEF, index)
+      .ok_or(ErrMode::from_error_kind(input, ErrorKind::Eof))?;
+    methods.push(ManagedMethod {
+      token: make_token(TABLE_METHOD_DEF, index),
+      rva: row[0],
+      flags: row[2] as u16,
+      name: tables.string(row[3]),
+    });
+  }
+
+  let mut types = Vec::new();
+  for index in 1..=tables.rows[TABLE_TYPE_DEF] {
+    let row = tables
+      .row(TABLE_TYPE_DEF, index)
+      .ok_or(ErrMode::from_error_kind(input, ErrorKind::Eof))?;
+    // a type owns methods up to the start of the next type's method list
+    let method_start = row[5] as usize;
+    let method_end = match tables.row(TABLE_TYPE_DEF, index + 1) {
+      Some(next) => next[5] as usize,
+      None => methods.len() + 1,
+    };
+    types.push(ManagedType {
+      token: make_token(TABLE_TYPE_DEF, index),
+      flags: row[0],
+      name: tables.string(row[1]),
+      namespace: tables.string(row[2]),
+      extends: match row[3] {
+        0 => String::new(),
+        extends => tables.coded_name(&TYPE_DEF_OR_REF, extends),
+      },
+      methods: (method_start.max(1)..method_end.min(methods.len() + 1))
+        .map(|x| x - 1)
+        .collect(),
+    });
+  }
+
+  let type_refs = (1..=tables.rows[TABLE_TYPE_REF])
+    .map(|x| tables.type_ref_name(x))
+    .collect();
+
+  // fields are only kept by name, qualified with the type that owns them
+  let mut fields = (1..=tables.rows[TABLE_FIELD])
+    .map(|x| match tables.row(TABLE_FIELD, x) {
+      Some(row) => tables.string(row[1]),
+      None => String::new(),
+    })
+    .collect::<Vec<String>>();
+  for index in 1..=tables.rows[TABLE_TYPE_DEF] {
+    let field_start = tables
+      .row(TABLE_TYPE_DEF, index)
+      .map_or(0, |x| x[4] as usize);
+    let field_end = match tables.row(TABLE_TYPE_DEF, index + 1) {
+      Some(next) => next[4] as usize,
+      None => fields.len() + 1,
+    };
+    let type_name = tables.type_def_name(index);
+    for field in fields
+      .iter_mut()
+      .take(field_end.saturating_sub(1))
+      .skip(field_start.saturating_sub(1))
+    {
+      *field = format!("{}::{}", type_name, field);
+    }
+  }
+
+  let mut member_refs = Vec::new();
+  for index in 1..=tables.rows[TABLE_MEMBER_REF] {
+    let row = tables
+      .row(TABLE_MEMBER_REF, index)
+      .ok_or(ErrMode::from_error_kind(input, ErrorKind::Eof))?;
+    member_refs.push(ManagedMemberRef {
+      token: make_token(TABLE_MEMBER_REF, index),
+      parent: tables.coded_name(&MEMBER_REF_PARENT, row[0]),
+      name: tables.string(row[1]),
+    });
+  }
+
+  let mut assembly_refs = Vec::new();
+  for index in 1..=tables.rows[TABLE_ASSEMBLY_REF] {
+    let row = tables
+      .row(TABLE_ASSEMBLY_REF, index)
+      .ok_or(ErrMode::from_error_kind(input, ErrorKind::Eof))?;
+    assembly_refs.push(ManagedAssemblyRef {
+      token: make_token(TABLE_ASSEMBLY_REF, index),
+      major_version: row[0] as u16,
+      minor_version: row[1] as u16,
+      build_number: row[2] as u16,
+      revision_number: row[3] as u16,
+      name: tables.string(row[6]),
+      culture: tables.string(row[7]),
+    });
+  }
+
+  let user_strings = stream("#US").unwrap_or(&[]).to_vec();
+
+  Ok(Some(ClrData {
+    header,
+    metadata_version,
+    streams,
+    types,
+    methods,
+    type_refs,
+    fields,
+    member_refs,
+    assembly_refs,
+    user_strings,
+    method_bodies: vec![],
+  }))
+}
@@ -0,0 +1,122 @@
+use crate::parser::utils::DosStubAnomaly;
+use crate::parser::{DosStubProgram, InstructionData, PEHeader, DOS_HEADER_SIZE};
+use iced_x86::{Code, Decoder, DecoderOptions, FlowControl, Register};
+
+const MAX_MESSAGE_LENGTH: usize = 256;
+const MAX_INSTRUCTIONS: usize = 64;
+
+// push cs; pop ds; mov dx, 0xe; mov ah, 9; int 0x21; mov ax, 0x4c01; int 0x21
+const STANDARD_STUB_CODE: &[u8] = &[
+  0x0e, 0x1f, 0xba, 0x0e, 0x00, 0xb4, 0x09, 0xcd, 0x21, 0xb8, 0x01, 0x4c, 0xcd, 0x21,
+];
+const STANDARD_STUB_MESSAGE: &str = "This program cannot be run in DOS mode.";
+
+// The stub is a real mode MZ program, DOS loads everything after the header paragraphs and jumps to e_cs:e_ip
+pub fn parse_dos_stub(pe_header: &PEHeader) -> DosStubProgram {
+  let dos_header = &pe_header.dos_header;
+  let stub = &pe_header.dos_stub;
+  let load_offset = dos_header.e_cparhdr as usize * 16;
+  let code_offset = dos_header.e_cs as usize * 16;
+  let entry_offset = load_offset + code_offset + dos_header.e_ip as usize;
+
+  let mut program = DosStubProgram {
+    entry_offset,
+    ..Default::default()
+  };
+
+  let entry = match entry_offset.checked_sub(DOS_HEADER_SIZE) {
+    Some(entry) if entry < stub.len() => entry,
+    _ => {
+      program.anomalies.push(DosStubAnomaly::EntryOutsideStub);
+      return program;
+    }
+  };
+
+  let mut decoder = Decoder::with_ip(
+    16,
+    &stub[entry..],
+    dos_header.e_ip as u64,
+    DecoderOptions::NONE,
+  );
+  let mut exit_function = None;
+  let mut message_offset = None;
+
+  while decoder.can_decode() && program.instructions.len() < MAX_INSTRUCTIONS {
+    let position = decoder.position();
+    let instr = decoder.decode();
+    if instr.is_invalid() {
+      break;
+    }
+    program.instructions.push(InstructionData {
+      instr,
+      offset: DOS_HEADER_SIZE + entry + position,
+      size: instr.len(),
+      bytes: stub[entry + position..entry + position + instr.len()].to_vec(),
+    });
+
+    match instr.code() {
+      Code::Mov_r16_imm16 if instr.op0_register() == Register::DX => {
+        // the stub points ds at cs, so the message is relative to the code segment
+        message_offset = Some(load_offset + code_offset + instr.immediate16() as usize);
+      }
+      Code::Mov_r16_imm16 if instr.op0_register() == Register::AX => {
+        exit_function = Some((instr.immediate16() >> 8) as u8);
+      }
+      Code::Mov_r8_imm8 if instr.op0_register() == Register::AH => {
+        exit_function = Some(instr.immediate8());
+      }
+      _ => {}
+    }
+
+    // int 0x21 with ah = 0x4c terminates the program, anything after it is data
+    let terminated = match instr.code() {
+      Code::Int_imm8 => {
+        instr.immediate8() == 0x20 || (instr.immediate8() == 0x21 && exit_function == Some(0x4c))
+      }
+      _ => matches!(
+        instr.flow_control(),
+        FlowControl::Return | FlowControl::UnconditionalBranch
+      ),
+    };
+    if terminated {
+      break;
+    }
+  }
+
+  program.message = message_offset
+    .and_then(|x| x.checked_sub(DOS_HEADER_SIZE))
+    .and_then(|x| dollar_terminated_string(stub, x));
+
+  if entry != 0 {
+    program.anomalies.push(DosStubAnomaly::EntryPoint);
+  }
+  if !stub[entry..].starts_with(STANDARD_STUB_CODE) {
+    program.anomalies.push(DosStubAnomaly::Code);
+  }
+  match &program.message {
+    Some(message) if message != STANDARD_STUB_MESSAGE => {
+      program.anomalies.push(DosStubAnomaly::Message)
+    }
+    Some(_) => {}
+    None => program.anomalies.push(DosStubAnomaly::NoMessage),
+  }
+
+  program
+}
+
+// int 0x21 function 9 prints up to the first "$"
+fn dollar_terminated_string(stub: &[u8], offset: usize) -> Option<String> {
+  let bytes = stub.get(offset..)?;
+  let length = bytes
+    .iter()
+    .take(MAX_MESSAGE_LENGTH)
+    .position(|x| *x == b'$')?;
+  let message = &bytes[..length];
+  if !message
+    .iter()
+    .all(|x| x.is_ascii_graphic() || x.is_ascii_whitespace())
+  {
+    return None;
+  }
+  Some(String::from_utf8_lossy(message).trim_end().to_owned())
+}
@@ -0,0 +1,91 @@
+use crate::parser::utils::{
+  get_le_u16, get_le_u32, get_rva_slice, get_rva_string, DataDirectoryTableField,
+};
+use crate::parser::{ExportEntry, PEHeader, SectionEntry};
+use winnow::error::ErrMode;
+use winnow::error::ErrorKind;
+use winnow::error::ParserError;
+use winnow::PResult;
+use winnow::Parser;
+
+pub fn parse_exports(
+  input: &mut &[u8],
+  pe_header: &PEHeader,
+  sections: &[SectionEntry],
+) -> PResult<Vec<ExportEntry>> {
+  let directory = match &pe_header.nt_headers.optional_header {
+    Some(optional_header) => optional_header.data_directory(DataDirectoryTableField::EXPORT_TABLE),
+    None => None,
+  };
+  let directory = match directory {
+    Some(directory) => directory,
+    None => return Ok(vec![]),
+  };
+
+  let mut table = get_rva_slice(input, sections, directory.virtual_address, 40)
+    .ok_or(ErrMode::from_error_kind(input, ErrorKind::Eof))?;
+  let _characteristics = get_le_u32.parse_next(&mut table)?;
+  let _time_date_stamp = get_le_u32.parse_next(&mut table)?;
+  let _major_version = get_le_u16.parse_next(&mut table)?;
+  let _minor_version = get_le_u16.parse_next(&mut table)?;
+  let _name = get_le_u32.parse_next(&mut table)?;
+  let base = get_le_u32.parse_next(&mut table)?;
+  let number_of_functions = get_le_u32.parse_next(&mut table)?;
+  let number_of_names = get_le_u32.parse_next(&mut table)?;
+  let address_of_functions = get_le_u32.parse_next(&mut table)?;
+  let address_of_names = get_le_u32.parse_next(&mut table)?;
+  let address_of_name_ordinals = get_le_u32.parse_next(&mut table)?;
+
+  let mut functions = get_rva_slice(
+    input,
+    sections,
+    address_of_functions,
+    number_of_functions.saturating_mul(4),
+  )
+  .ok_or(ErrMode::from_error_kind(input, ErrorKind::Eof))?;
+  let mut names = get_rva_slice(
+    input,
+    sections,
+    address_of_names,
+    number_of_names.saturating_mul(4),
+  )
+  .unwrap_or_default();
+  let mut name_ordinals = get_rva_slice(
+    input,
+    sections,
+    address_of_name_ordinals,
+    number_of_names.saturating_mul(2),
+  )
+  .unwrap_or_default();
+
+  let mut entries = Vec::new();
+  for index in 0..number_of_functions {
+    entries.push(ExportEntry {
+      ordinal: base.wrapping_add(index),
+      name: None,
+      rva: get_le_u32.parse_next(&mut functions)?,
+      forwarder: None,
+    });
+  }
+
+  // names are a separate table, each one points back into the function table by index
+  while !names.is_empty() && !name_ordinals.is_empty() {
+    let name_rva = get_le_u32.parse_next(&mut names)?;
+    let index = get_le_u16.parse_next(&mut name_ordinals)?;
+    if let Some(entry) = entries.get_mut(index as usize) {
+      entry.name = get_rva_string(input, sections, name_rva);
+    }
+  }
+
+  // an address inside the export directory is a "DLL.Function" string rather than code
+  // a size running past 4GB is cut there, no RVA can be above it anyway
+  let directory_range =
+    directory.virtual_address..directory.virtual_address.saturating_add(directory.size);
+  for entry in &mut entries {
+    if directory_range.contains(&entry.rva) {
+      entry.forwarder = get_rva_string(input, sections, entry.rva);
+    }
+  }
+
+  Ok(entries.into_iter().filter(|x| x.rva != 0).collect())
+}
@@ -0,0 +1,101 @@
+use crate::parser::utils::{
+  get_le_u32, get_le_u64, get_rva_slice, get_rva_string, DataDirectoryTableField,
+};
+use crate::parser::{ImportEntry, OptionalHeader, PEHeader, SectionEntry};
+use winnow::error::ErrMode;
+use winnow::error::ErrorKind;
+use winnow::error::ParserError;
+use winnow::PResult;
+use winnow::Parser;
+
+const DESCRIPTOR_SIZE: u32 = 20;
+
+// One entry per imported function, in IAT order, so every slot can be named
+pub fn parse_imports(
+  input: &mut &[u8],
+  pe_header: &PEHeader,
+  sections: &[SectionEntry],
+) -> PResult<Vec<ImportEntry>> {
+  let optional_header = match &pe_header.nt_headers.optional_header {
+    Some(optional_header) => optional_header,
+    None => return Ok(vec![]),
+  };
+  let directory = match optional_header.data_directory(DataDirectoryTableField::IMPORT_TABLE) {
+    Some(directory) => directory,
+    None => return Ok(vec![]),
+  };
+  let is_64 = matches!(optional_header, OptionalHeader::ImageOptionalHeader64(_));
+  let pointer_size = if is_64 { 8 } else { 4 };
+  let ordinal_flag = if is_64 { 1 << 63 } else { 1 << 31 };
+
+  let mut entries = Vec::new();
+  // the descriptor array ends with a zeroed descriptor
+  let mut descriptor_rva = directory.virtual_address;
+  loop {
+    let mut descriptor = get_rva_slice(input, sections, descriptor_rva, DESCRIPTOR_SIZE)
+      .ok_or(ErrMode::from_error_kind(input, ErrorKind::Eof))?;
+    let original_first_thunk = get_le_u32.parse_next(&mut descriptor)?;
+    let _time_date_stamp = get_le_u32.parse_next(&mut descriptor)?;
+    let _forwarder_chain = get_le_u32.parse_next(&mut descriptor)?;
+    let name = get_le_u32.parse_next(&mut descriptor)?;
+    let first_thunk = get_le_u32.parse_next(&mut descriptor)?;
+    if name == 0 && first_thunk == 0 {
+      break;
+    }
+    // thunk and descriptor RVAs come from the file, a walk that would wrap past 4GB stops there
+    let next_descriptor_rva = descriptor_rva.checked_add(DESCRIPTOR_SIZE);
+
+    let module = get_rva_string(input, sections, name).unwrap_or_default();
+    // the IAT is overwritten when the image is bound, the lookup table keeps the names
+    let mut lookup_rva = match original_first_thunk {
+      0 => first_thunk,
+      rva => rva,
+    };
+    let mut iat_rva = first_thunk;
+    while let Some(mut thunk) = get_rva_slice(input, sections, lookup_rva, pointer_size) {
+      let value = if is_64 {
+        get_le_u64.parse_next(&mut thunk)?
+      } else {
+        get_le_u32.parse_next(&mut thunk)? as u64
+      };
+      if value == 0 {
+        break;
+      }
+
+      let (name, ordinal) = if value & ordinal_flag != 0 {
+        (None, Some(value as u16))
+      } else {
+        // IMAGE_IMPORT_BY_NAME, the name follows a two byte hint
+        let name_rva = (value as u32).checked_add(2);
+        (
+          name_rva.and_then(|rva| get_rva_string(input, sections, rva)),
+          None,
+        )
+      };
+      entries.push(ImportEntry {
+        module: module.clone(),
+        name,
+        ordinal,
+        iat_rva,
+      });
+
+      match (
+        lookup_rva.checked_add(pointer_size),
+        iat_rva.checked_add(pointer_size),
+      ) {
+        (Some(lookup), Some(iat)) => {
+          lookup_rva = lookup;
+          iat_rva = iat;
+        }
+        _ => break,
+      }
+    }
+
+    match next_descriptor_rva {
+      Some(rva) => descriptor_rva = rva,
+      None => break,
+    }
+  }
+
+  Ok(entries)
+}
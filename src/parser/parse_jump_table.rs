@@ -0,0 +1,379 @@
+use crate::parser::utils::{get_le_u32, get_le_u64, get_rva_slice};
+use crate::parser::{JumpTable, SectionEntry};
+use iced_x86::{ConditionCode, FlowControl, Instruction, Mnemonic, OpKind, Register};
+use std::ops::Range;
+use winnow::Parser;
+
+// Tables without a recognizable bounds check are read until an entry leaves the code
+const MAX_CASES: usize = 512;
+
+#[derive(Debug, Clone, Copy)]
+enum EntryKind {
+  Absolute(u32), // VAs of the given size, jmp [index*4+table]
+  Rva,           // MSVC x64, RVAs added to __ImageBase
+  Relative,      // GCC/Clang PIC, signed offsets from the start of the table
+}
+
+// Recovers the targets of a switch from the instructions leading up to the indirect jump, the last one in the trace
+pub fn parse_jump_table(
+  input: &[u8],
+  sections: &[SectionEntry],
+  image_base: u64,
+  code: &Range<u64>,
+  trace: &[Instruction],
+) -> Option<JumpTable> {
+  let (jump, previous) = trace.split_last()?;
+  if jump.flow_control() != FlowControl::IndirectBranch {
+    return None;
+  }
+
+  let (table, index, kind, load) = match jump.op0_kind() {
+    // jmp dword ptr [eax*4+table], jmp qword ptr [rax*8+table]
+    OpKind::Memory => {
+      let size = jump.memory_size().size() as u32;
+      if jump.memory_base() != Register::None
+        || jump.memory_index() == Register::None
+        || jump.memory_index_scale() != size
+      {
+        return None;
+      }
+      (
+        jump.memory_displacement64(),
+        jump.memory_index(),
+        EntryKind::Absolute(size),
+        previous.len(),
+      )
+    }
+    // movsxd rax,[rdx+rax*4] / add rax,rdx / jmp rax, the base register comes from a lea
+    OpKind::Register => {
+      let target = jump.op0_register().full_register();
+      let add = previous.iter().rposition(|x| {
+        x.mnemonic() == Mnemonic::Add
+          && x.op0_kind() == OpKind::Register
+          && x.op0_register().full_register() == target
+          && x.op1_kind() == OpKind::Register
+      })?;
+      let base = previous[add].op1_register().full_register();
+      let load = previous[..add].iter().rposition(|x| {
+        matches!(x.mnemonic(), Mnemonic::Mov | Mnemonic::Movsxd)
+          && x.op0_kind() == OpKind::Register
+          && x.op0_register().full_register() == target
+          && x.op1_kind() == OpKind::Memory
+      })?;
+      let load_instr = &previous[load];
+      if load_instr.memory_base().full_register() != base
+        || load_instr.memory_index() == Register::None
+        || load_instr.memory_index_scale() != 4
+      {
+        return None;
+      }
+      let lea = previous[..load].iter().rev().find(|x| {
+        x.mnemonic() == Mnemonic::Lea
+          && x.op0_register().full_register() == base
+          && x.is_ip_rel_memory_operand()
+      })?;
+      let base_address = lea.ip_rel_memory_address();
+      let displacement = load_instr.memory_displacement64();
+      let (table, kind) = if base_address == image_base && displacement != 0 {
+        (image_base + displacement, EntryKind::Rva)
+      } else if displacement == 0 {
+        (base_address, EntryKind::Relative)
+      } else {
+        return None;
+      };
+      (table, load_instr.memory_index(), kind, load)
+    }
+    _ => return None,
+  };
+
+  let count = case_count(&previous[..load], index);
+  let bounded = count.is_some();
+  let count = count.unwrap_or(MAX_CASES);
+  let entry_size = match kind {
+    EntryKind::Absolute(size) => size,
+    EntryKind::Rva | EntryKind::Relative => 4,
+  };
+  let table_rva = u32::try_from(table.checked_sub(image_base)?).ok()?;
+
+  let mut targets = vec![];
+  for i in 0..count.min(MAX_CASES) as u32 {
+    let entry_rva = match table_rva.checked_add(i * entry_size) {
+      Some(rva) => rva,
+      None => break,
+    };
+    let mut entry = match get_rva_slice(input, sections, entry_rva, entry_size) {
+      Some(entry) => entry,
+      None => break,
+    };
+    let target = match kind {
+      EntryKind::Absolute(8) => get_le_u64.parse_next(&mut entry).ok(),
+      EntryKind::Absolute(_) => get_le_u32.parse_next(&mut entry).ok().map(|x| x as u64),
+      EntryKind::Rva => get_le_u32
+        .parse_next(&mut entry)
+        .ok()
+        .map(|x| image_base + x as u64),
+      EntryKind::Relative => get_le_u32
+        .parse_next(&mut entry)
+        .ok()
+        .map(|x| table.wrapping_add_signed(x as i32 as i64)),
+    };
+    match target {
+      Some(target) if code.contains(&target) => targets.push(Some(target)),
+      // cases the bounds check rules out can hold anything, an unbounded table ends here
+      _ if bounded => targets.push(None),
+      _ => break,
+    }
+  }
+
+  if targets.iter().all(|x| x.is_none()) {
+    return None;
+  }
+  Some(JumpTable {
+    dispatch: jump.ip(),
+    table,
+    targets,
+  })
+}
+
+// Finds the cmp index, imm / ja default that guards the table, following the moves the index went through
+fn case_count(previous: &[Instruction], index: Register) -> Option<usize> {
+  let mut tracked = vec![index.full_register()];
+  let mut cmp = None;
+  for (i, instr) in previous.iter().enumerate().rev() {
+    if instr.op0_kind() != OpKind::Register
+      || !tracked.contains(&instr.op0_register().full_register())
+    {
+      continue;
+    }
+    match instr.mnemonic() {
+      Mnemonic::Cmp if is_immediate(instr.op1_kind()) => {
+        cmp = Some((i, instr.immediate(1)));
+        break;
+      }
+      // and eax, 3 masks the index, no branch needed
+      Mnemonic::And if is_immediate(instr.op1_kind()) => {
+        return usize::try_from(instr.immediate(1)).ok()?.checked_add(1);
+      }
+      Mnemonic::Cmp | Mnemonic::Test => {}
+      // the index was copied from another register, which is the one that got compared
+      Mnemonic::Mov | Mnemonic::Movsxd | Mnemonic::Movzx
+        if instr.op1_kind() == OpKind::Register =>
+      {
+        tracked.retain(|x| *x != instr.op0_register().full_register());
+        tracked.push(instr.op1_register().full_register());
+      }
+      // anything else computed the index, a compare before it doesn't bound the table
+      _ => {
+        tracked.retain(|x| *x != instr.op0_register().full_register());
+        if tracked.is_empty() {
+          return None;
+        }
+      }
+    }
+  }
+
+  let (position, bound) = cmp?;
+  let branch = previous[position + 1..]
+    .iter()
+    .find(|x| x.flow_control() == FlowControl::ConditionalBranch)?;
+  let count = match branch.condition_code() {
+    // ja default, jbe cases
+    ConditionCode::a | ConditionCode::be => bound.checked_add(1)?,
+    ConditionCode::ae | ConditionCode::b => bound,
+    _ => return None,
+  };
+  usize::try_from(count).ok()
+}
+
+fn is_immediate(kind: OpKind) -> bool {
+  matches!(
+    kind,
+    OpKind::Immediate8
+      | OpKind::Immediate8to32
+      | OpKind::Immediate8to64
+      | OpKind::Immediate32
+      | OpKind::Immediate32to64
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use iced_x86::{Decoder, DecoderOptions};
+
+  // code at RVA 0x1000 up to the table at RVA 0x1100, one raw section mapped from offset 0
+  const CODE_RVA: u64 = 0x1000;
+  const TABLE_RVA: u64 = 0x1100;
+  const SECTION_SIZE: usize = 0x1000;
+
+  fn jump_table(bitness: u32, image_base: u64, code: &[u8], entries: &[u8]) -> Option<JumpTable> {
+    let mut bytes = vec![0xcc; SECTION_SIZE];
+    bytes[..code.len()].copy_from_slice(code);
+    let table = (TABLE_RVA - CODE_RVA) as usize;
+    bytes[table..table + entries.len()].copy_from_slice(entries);
+    let sections = [SectionEntry {
+      virtual_address: CODE_RVA as u32,
+      virtual_size: SECTION_SIZE as u32,
+      size_of_raw_data: SECTION_SIZE as u32,
+      ..Default::default()
+    }];
+    let mut decoder = Decoder::with_ip(bitness, code, image_base + CODE_RVA, DecoderOptions::NONE);
+    let trace = decoder.iter().collect::<Vec<Instruction>>();
+    let code_range = image_base + CODE_RVA..image_base + TABLE_RVA;
+    parse_jump_table(&bytes, &sections, image_base, &code_range, &trace)
+  }
+
+  fn u32_entries(values: &[u32]) -> Vec<u8> {
+    values.iter().flat_map(|x| x.to_le_bytes()).collect()
+  }
+
+  // offsets from the start of the table to the given code offsets, as GCC and Clang emit them
+  fn relative_entries(code_offsets: &[u64]) -> Vec<u8> {
+    code_offsets
+      .iter()
+      .flat_map(|x| ((CODE_RVA + x) as i64 - TABLE_RVA as i64).to_le_bytes()[..4].to_vec())
+      .collect()
+  }
+
+  #[test]
+  fn msvc_x86_absolute() {
+    let code = [
+      0x83, 0xf8, 0x03, // cmp eax, 3
+      0x77, 0x3b, // ja default
+      0xff, 0x24, 0x85, 0x00, 0x11, 0x40, 0x00, // jmp dword ptr [eax*4+0x401100]
+    ];
+    // the fifth entry is past the bounds check and is left out
+    let entries = u32_entries(&[0x401020, 0x401028, 0x401030, 0x401020, 0x401038]);
+    let table = jump_table(32, 0x400000, &code, &entries).unwrap();
+    assert_eq!(table.dispatch, 0x401005);
+    assert_eq!(table.table, 0x401100);
+    assert_eq!(
+      table.targets,
+      [
+        Some(0x401020),
+        Some(0x401028),
+        Some(0x401030),
+        Some(0x401020)
+      ]
+    );
+    assert_eq!(table.unique_targets(), [0x401020, 0x401028, 0x401030]);
+  }
+
+  #[test]
+  fn msvc_x64_rva() {
+    let code = [
+      0x83, 0xf8, 0x03, // cmp eax, 3
+      0x77, 0x3b, // ja default
+      0x48, 0x8d, 0x15, 0xf4, 0xef, 0xff, 0xff, // lea rdx, [__ImageBase]
+      0x8b, 0x8c, 0x82, 0x00, 0x11, 0x00, 0x00, // mov ecx, dword ptr [rdx+rax*4+0x1100]
+      0x48, 0x01, 0xd1, // add rcx, rdx
+      0xff, 0xe1, // jmp rcx
+    ];
+    let entries = u32_entries(&[0x1020, 0x1028, 0x1030, 0x1038, 0x1040]);
+    let table = jump_table(64, 0x1_4000_0000, &code, &entries).unwrap();
+    assert_eq!(table.dispatch, 0x1_4000_1016);
+    assert_eq!(table.table, 0x1_4000_1100);
+    assert_eq!(
+      table.targets,
+      [
+        Some(0x1_4000_1020),
+        Some(0x1_4000_1028),
+        Some(0x1_4000_1030),
+        Some(0x1_4000_1038)
+      ]
+    );
+  }
+
+  // cmp edi, 3 / ja default / mov eax, edi, the bound is on the register the index was copied from
+  const GCC_CODE: [u8; 23] = [
+    0x83, 0xff, 0x03, // cmp edi, 3
+    0x77, 0x3b, // ja default
+    0x89, 0xf8, // mov eax, edi
+    0x48, 0x8d, 0x15, 0xf2, 0x00, 0x00, 0x00, // lea rdx, [table]
+    0x48, 0x63, 0x04, 0x82, // movsxd rax, dword ptr [rdx+rax*4]
+    0x48, 0x01, 0xd0, // add rax, rdx
+    0xff, 0xe0, // jmp rax
+  ];
+
+  #[test]
+  fn gcc_relative() {
+    let entries = relative_entries(&[0x20, 0x28, 0x30, 0x38, 0x40]);
+    let table = jump_table(64, 0x1_4000_0000, &GCC_CODE, &entries).unwrap();
+    assert_eq!(table.dispatch, 0x1_4000_1015);
+    assert_eq!(table.table, 0x1_4000_1100);
+    assert_eq!(
+      table.targets,
+      [
+        Some(0x1_4000_1020),
+        Some(0x1_4000_1028),
+        Some(0x1_4000_1030),
+        Some(0x1_4000_1038)
+      ]
+    );
+  }
+
+  #[test]
+  fn bounded_case_outside_the_code_is_kept_as_none() {
+    // case 2 points into the table itself, the cases after it still count
+    let entries = relative_entries(&[0x20, 0x28, 0x180, 0x38]);
+    let table = jump_table(64, 0x1_4000_0000, &GCC_CODE, &entries).unwrap();
+    assert_eq!(
+      table.targets,
+      [
+        Some(0x1_4000_1020),
+        Some(0x1_4000_1028),
+        None,
+        Some(0x1_4000_1038)
+      ]
+    );
+  }
+
+  #[test]
+  fn masked_index() {
+    let code = [
+      0x83, 0xe0, 0x03, // and eax, 3
+      0x48, 0x8d, 0x15, 0xf6, 0x00, 0x00, 0x00, // lea rdx, [table]
+      0x48, 0x63, 0x04, 0x82, // movsxd rax, dword ptr [rdx+rax*4]
+      0x48, 0x01, 0xd0, // add rax, rdx
+      0xff, 0xe0, // jmp rax
+    ];
+    let entries = relative_entries(&[0x20, 0x28, 0x30, 0x38, 0x40, 0x48]);
+    let table = jump_table(64, 0x1_4000_0000, &code, &entries).unwrap();
+    assert_eq!(table.targets.len(), 4);
+  }
+
+  const UNBOUNDED_CODE: [u8; 16] = [
+    0x48, 0x8d, 0x15, 0xf9, 0x00, 0x00, 0x00, // lea rdx, [table]
+    0x48, 0x63, 0x04, 0x82, // movsxd rax, dword ptr [rdx+rax*4]
+    0x48, 0x01, 0xd0, // add rax, rdx
+    0xff, 0xe0, // jmp rax
+  ];
+
+  #[test]
+  fn unbounded_table_ends_at_the_first_entry_outside_the_code() {
+    let mut entries = relative_entries(&[0x20, 0x28, 0x30]);
+    entries.extend(0u32.to_le_bytes()); // the table itself
+    let table = jump_table(64, 0x1_4000_0000, &UNBOUNDED_CODE, &entries).unwrap();
+    assert_eq!(table.targets.len(), 3);
+  }
+
+  #[test]
+  fn unbounded_table_is_capped() {
+    let entries = relative_entries(&[0x20; MAX_CASES + 100]);
+    let table = jump_table(64, 0x1_4000_0000, &UNBOUNDED_CODE, &entries).unwrap();
+    assert_eq!(table.targets.len(), MAX_CASES);
+    assert_eq!(table.unique_targets(), [0x1_4000_1020]);
+  }
+
+  #[test]
+  fn no_targets_is_no_table() {
+    let entries = u32_entries(&[0, 0, 0, 0]);
+    assert!(jump_table(64, 0x1_4000_0000, &GCC_CODE, &entries).is_none());
+  }
+
+  #[test]
+  fn register_jump_without_a_table_load() {
+    let code = [0xff, 0xe0]; // jmp rax
+    assert!(jump_table(64, 0x1_4000_0000, &code, &[]).is_none());
+  }
+}
@@ -0,0 +1,51 @@
+use crate::parser::utils::{get_le_u32, get_rva_slice, DataDirectoryTableField, MachineType};
+use crate::parser::{PEHeader, RuntimeFunction, SectionEntry};
+use winnow::error::ErrMode;
+use winnow::error::ErrorKind;
+use winnow::error::ParserError;
+use winnow::PResult;
+use winnow::Parser;
+
+// The exception directory (.pdata) lists the bounds of every non-leaf function on x64
+pub fn parse_runtime_functions(
+  input: &mut &[u8],
+  pe_header: &PEHeader,
+  sections: &[SectionEntry],
+) -> PResult<Vec<RuntimeFunction>> {
+  // other architectures use a different entry layout
+  if !matches!(
+    pe_header.nt_headers.file_header.machine,
+    MachineType::IMAGE_FILE_MACHINE_AMD64
+  ) {
+    return Ok(vec![]);
+  }
+  let directory = match &pe_header.nt_headers.optional_header {
+    Some(optional_header) => {
+      optional_header.data_directory(DataDirectoryTableField::EXCEPTION_TABLE)
+    }
+    None => None,
+  };
+  let directory = match directory {
+    Some(directory) => directory,
+    None => return Ok(vec![]),
+  };
+
+  let mut table = get_rva_slice(input, sections, directory.virtual_address, directory.size)
+    .ok_or(ErrMode::from_error_kind(input, ErrorKind::Eof))?;
+
+  let mut functions = Vec::new();
+  while table.len() >= 12 {
+    let begin_address = get_le_u32.parse_next(&mut table)?;
+    let end_address = get_le_u32.parse_next(&mut table)?;
+    let _unwind_info = get_le_u32.parse_next(&mut table)?;
+    if begin_address == 0 {
+      break;
+    }
+    functions.push(RuntimeFunction {
+      begin_address,
+      end_address,
+    });
+  }
+
+  Ok(functions)
+}
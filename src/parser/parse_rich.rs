@@ -0,0 +1,278 @@
+use crate::parser::{PEHeader, RichEntry, RichHeader, DOS_HEADER_SIZE};
+use md5::{Digest, Md5};
+
+const RICH_MAGIC: &[u8] = b"Rich";
+const DANS_MAGIC: u32 = 0x536e6144; // "DanS"
+const E_LFANEW_OFFSET: usize = 0x3c;
+
+// The Rich header sits between the DOS stub and the NT headers, it is only written by the MSVC linker
+pub fn parse_rich_header(input: &[u8], pe_header: &PEHeader) -> Option<RichHeader> {
+  let stub = &pe_header.dos_stub;
+
+  // the end marker is in the clear, everything before it is XOR'd with the key that follows it
+  let rich_offset = (0..stub.len().saturating_sub(7))
+    .rev()
+    .find(|x| (DOS_HEADER_SIZE + x) % 4 == 0 && stub[*x..].starts_with(RICH_MAGIC))?;
+  let key = read_u32(stub, rich_offset + 4)?;
+
+  let mut dans_offset = None;
+  let mut position = rich_offset;
+  while position >= 4 {
+    position -= 4;
+    if read_u32(stub, position)? ^ key == DANS_MAGIC {
+      dans_offset = Some(position);
+      break;
+    }
+  }
+  let dans_offset = dans_offset?;
+
+  // "DanS" is followed by three zeroed padding dwords before the first @comp.id
+  let mut cleartext = Vec::with_capacity(rich_offset - dans_offset);
+  let mut position = dans_offset;
+  while position < rich_offset {
+    cleartext.extend_from_slice(&(read_u32(stub, position)? ^ key).to_le_bytes());
+    position += 4;
+  }
+
+  let entries = cleartext
+    .get(16..)?
+    .chunks_exact(8)
+    .map(|x| {
+      let comp_id = u32::from_le_bytes([x[0], x[1], x[2], x[3]]);
+      let product_id = (comp_id >> 16) as u16;
+      let build_number = comp_id as u16;
+      RichEntry {
+        product_id,
+        build_number,
+        count: u32::from_le_bytes([x[4], x[5], x[6], x[7]]),
+        product_name: product_name(product_id),
+        visual_studio_version: visual_studio_version(product_id, build_number),
+      }
+    })
+    .collect::<Vec<RichEntry>>();
+
+  let offset = DOS_HEADER_SIZE + dans_offset;
+  let checksum = checksum(input.get(..offset)?, &entries);
+
+  Some(RichHeader {
+    offset,
+    key,
+    checksum,
+    hash: Md5::digest(&cleartext)
+      .iter()
+      .map(|x| format!("{:02x}", x))
+      .collect(),
+    entries,
+  })
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+  let bytes = bytes.get(offset..offset + 4)?;
+  Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+// The key the linker stores is a checksum of the DOS header, DOS stub and the @comp.id entries
+fn checksum(header_bytes: &[u8], entries: &[RichEntry]) -> u32 {
+  let mut checksum = header_bytes.len() as u32;
+  for (i, b) in header_bytes.iter().enumerate() {
+    // e_lfanew is skipped, it is only known once the Rich header has been written
+    if (E_LFANEW_OFFSET..E_LFANEW_OFFSET + 4).contains(&i) {
+      continue;
+    }
+    checksum = checksum.wrapping_add((*b as u32).rotate_left(i as u32));
+  }
+  for entry in entries {
+    let comp_id = (entry.product_id as u32) << 16 | entry.build_number as u32;
+    checksum = checksum.wrapping_add(comp_id.rotate_left(entry.count));
+  }
+  checksum
+}
+
+// Names of the tools behind the most common product IDs, the rest are shown as raw IDs
+fn product_name(product_id: u16) -> Option<&'static str> {
+  let name = match product_id {
+    0x0000 => "Unknown",
+    0x0001 => "Import0",
+    0x0002 => "Linker510",
+    0x0004 => "Linker600",
+    0x0006 => "Cvtres500",
+    0x0008 => "Utc11_C",
+    0x000a => "Utc12_C",
+    0x000b => "Utc12_CPP",
+    0x000d => "VisualBasic60",
+    0x000e => "Masm613",
+    0x000f => "Masm710",
+    0x0012 => "Masm614",
+    0x0019 => "Implib700",
+    0x001c => "Utc13_C",
+    0x001d => "Utc13_CPP",
+    0x002e => "ILAsm100",
+    0x003d => "Linker700",
+    0x003f => "Export700",
+    0x0040 => "Masm700",
+    0x0045 => "Cvtres700",
+    0x005a => "Linker710",
+    0x005c => "Export710",
+    0x005d => "Implib710",
+    0x005e => "Cvtres710",
+    0x005f => "Utc1310_C",
+    0x0060 => "Utc1310_CPP",
+    0x0063 => "Utc1310_LTCG_C",
+    0x0064 => "Utc1310_LTCG_CPP",
+    0x006d => "Utc1400_C",
+    0x006e => "Utc1400_CPP",
+    0x0071 => "Utc1400_LTCG_C",
+    0x0072 => "Utc1400_LTCG_CPP",
+    0x0078 => "Linker800",
+    0x007a => "Export800",
+    0x007b => "Implib800",
+    0x007c => "Cvtres800",
+    0x007d => "Masm800",
+    0x0083 => "Utc1500_C",
+    0x0084 => "Utc1500_CPP",
+    0x0089 => "Utc1500_LTCG_C",
+    0x008a => "Utc1500_LTCG_CPP",
+    0x0091 => "Linker900",
+    0x0092 => "Export900",
+    0x0093 => "Implib900",
+    0x0094 => "Cvtres900",
+    0x0095 => "Masm900",
+    0x0097 => "Resource",
+    0x009a => "Cvtres1000",
+    0x009b => "Export1000",
+    0x009c => "Implib1000",
+    0x009d => "Linker1000",
+    0x009e => "Masm1000",
+    0x00aa => "Utc1600_C",
+    0x00ab => "Utc1600_CPP",
+    0x00b7 => "Cvtres1100",
+    0x00b8 => "Export1100",
+    0x00b9 => "Implib1100",
+    0x00ba => "Linker1100",
+    0x00bb => "Masm1100",
+    0x00bc => "Utc1700_C",
+    0x00bd => "Utc1700_CPP",
+    0x00c9 => "Cvtres1200",
+    0x00ca => "Export1200",
+    0x00cb => "Implib1200",
+    0x00cc => "Linker1200",
+    0x00cd => "Masm1200",
+    0x00ce => "Utc1800_C",
+    0x00cf => "Utc1800_CPP",
+    0x00ff => "Cvtres1400",
+    0x0100 => "Export1400",
+    0x0101 => "Implib1400",
+    0x0102 => "Linker1400",
+    0x0103 => "Masm1400",
+    0x0104 => "Utc1900_C",
+    0x0105 => "Utc1900_CPP",
+    _ => return None,
+  };
+  Some(name)
+}
+
+// Product IDs are bumped for every toolset, VS2015 onwards share IDs so the build number decides
+fn visual_studio_version(product_id: u16, build_number: u16) -> Option<&'static str> {
+  let version = match product_id {
+    0x00fd..=0x010e => match build_number {
+      0..=24215 => "Visual Studio 2015",
+      24216..=27051 => "Visual Studio 2017",
+      27052..=30159 => "Visual Studio 2019",
+      _ => "Visual Studio 2022",
+    },
+    0x00c7..=0x00fc => "Visual Studio 2013",
+    0x00b5..=0x00c6 => "Visual Studio 2012",
+    0x0098..=0x00b4 => "Visual Studio 2010",
+    0x0083..=0x0097 => "Visual Studio 2008",
+    0x006d..=0x0082 => "Visual Studio 2005",
+    0x005a..=0x006c => "Visual Studio .NET 2003",
+    0x0019..=0x0059 => "Visual Studio .NET 2002",
+    0x0002..=0x0018 => "Visual Studio 6.0",
+    _ => return None,
+  };
+  Some(version)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parser::header_parse::parse_pe_header;
+
+  // MSVC 2022 build of this tool, its Rich header runs from 0x80 to 0xe0
+  const SAMPLE: &[u8] = include_bytes!("../../disassembler_win.exe");
+  const SAMPLE_KEY: u32 = 0x3642_02a5;
+
+  fn rich_header(bytes: &[u8]) -> RichHeader {
+    let pe_header = parse_pe_header(&mut &bytes[..]).unwrap();
+    parse_rich_header(bytes, &pe_header).unwrap()
+  }
+
+  #[test]
+  fn decodes_the_linker_header() {
+    let header = rich_header(SAMPLE);
+    assert_eq!(header.offset, 0x80);
+    assert_eq!(header.key, SAMPLE_KEY);
+    assert_eq!(header.hash, "86f9fef0044a72f9a5dcf728a061d3ec");
+    let entries = header
+      .entries
+      .iter()
+      .map(|x| (x.product_id, x.build_number, x.count))
+      .collect::<Vec<(u16, u16, u32)>>();
+    assert_eq!(
+      entries,
+      [
+        (0x93, 30729, 12),
+        (0x101, 30818, 2),
+        (0x105, 30818, 23),
+        (0x104, 30818, 9),
+        (0x103, 30818, 3),
+        (0x101, 29395, 13),
+        (0x1, 0, 176),
+        (0x0, 0, 9),
+        (0x102, 31106, 1)
+      ]
+    );
+    assert_eq!(header.entries[0].product_name, Some("Implib900"));
+    assert_eq!(
+      header.entries[8].visual_studio_version,
+      Some("Visual Studio 2022")
+    );
+  }
+
+  #[test]
+  fn checksum_matches_the_key() {
+    assert_eq!(rich_header(SAMPLE).checksum, SAMPLE_KEY);
+  }
+
+  #[test]
+  fn checksum_skips_e_lfanew() {
+    let mut bytes = SAMPLE.to_vec();
+    bytes[E_LFANEW_OFFSET..E_LFANEW_OFFSET + 4].copy_from_slice(&0x1234_5678u32.to_le_bytes());
+    assert_eq!(
+      checksum(&bytes[..0x80], &rich_header(SAMPLE).entries),
+      SAMPLE_KEY
+    );
+  }
+
+  #[test]
+  fn edited_stub_breaks_the_checksum() {
+    let mut bytes = SAMPLE.to_vec();
+    bytes[0x4e] ^= 0x20; // a letter of "This program cannot be run in DOS mode"
+    let header = rich_header(&bytes);
+    assert_eq!(header.key, SAMPLE_KEY);
+    assert_ne!(header.checksum, header.key);
+  }
+
+  #[test]
+  fn edited_entry_breaks_the_checksum() {
+    let entries = rich_header(SAMPLE)
+      .entries
+      .into_iter()
+      .map(|mut x| {
+        x.count += 1;
+        x
+      })
+      .collect::<Vec<RichEntry>>();
+    assert_ne!(checksum(&SAMPLE[..0x80], &entries), SAMPLE_KEY);
+  }
+}
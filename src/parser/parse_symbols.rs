@@ -0,0 +1,89 @@
+use crate::parser::utils::{get_le_u16, get_le_u32, get_single_u8};
+use crate::parser::{CoffSymbol, FileHeader, SectionEntry};
+use winnow::error::ErrMode;
+use winnow::error::ErrorKind;
+use winnow::error::ParserError;
+use winnow::token::take;
+use winnow::PResult;
+use winnow::Parser;
+
+const SYMBOL_SIZE: usize = 18;
+const IMAGE_SYM_DTYPE_FUNCTION: u16 = 0x20;
+
+// The COFF symbol table is deprecated for images, but MinGW and debug builds still emit it
+pub fn parse_symbols(
+  input: &mut &[u8],
+  file_header: &FileHeader,
+  sections: &[SectionEntry],
+) -> PResult<Vec<CoffSymbol>> {
+  if file_header.pointer_to_symbol_table == 0 || file_header.number_of_symbols == 0 {
+    return Ok(vec![]);
+  }
+
+  let start = file_header.pointer_to_symbol_table as usize;
+  let table_size = file_header.number_of_symbols as usize * SYMBOL_SIZE;
+  let mut table = input
+    .get(start..start + table_size)
+    .ok_or(ErrMode::from_error_kind(input, ErrorKind::Eof))?;
+  // long names are stored in the string table straight after the symbols
+  let string_table = input.get(start + table_size..).unwrap_or_default();
+
+  let mut symbols = Vec::new();
+  let mut remaining_aux_symbols = 0;
+  while !table.is_empty() {
+    let mut name = take(8usize).parse_next(&mut table)?;
+    let value = get_le_u32.parse_next(&mut table)?;
+    let section_number = get_le_u16.parse_next(&mut table)? as i16;
+    let symbol_type = get_le_u16.parse_next(&mut table)?;
+    let _storage_class = get_single_u8.parse_next(&mut table)?;
+    let number_of_aux_symbols = get_single_u8.parse_next(&mut table)?;
+
+    // auxiliary records share the layout size but not the meaning
+    if remaining_aux_symbols > 0 {
+      remaining_aux_symbols -= 1;
+      continue;
+    }
+    remaining_aux_symbols = number_of_aux_symbols;
+
+    // zero and negative section numbers are undefined, absolute and debug symbols
+    if section_number <= 0 {
+      continue;
+    }
+    let section = match sections.get(section_number as usize - 1) {
+      Some(section) => section,
+      None => continue,
+    };
+
+    let name = if name.starts_with(&[0, 0, 0, 0]) {
+      let _zeroes = get_le_u32.parse_next(&mut name)?;
+      let offset = get_le_u32.parse_next(&mut name)? as usize;
+      null_terminated(string_table.get(offset..).unwrap_or_default())
+    } else {
+      null_terminated(name)
+    };
+    if name.is_empty() {
+      continue;
+    }
+
+    let rva = match section.virtual_address.checked_add(value) {
+      Some(rva) => rva,
+      None => continue,
+    };
+    symbols.push(CoffSymbol {
+      name,
+      rva,
+      is_function: symbol_type & 0xf0 == IMAGE_SYM_DTYPE_FUNCTION,
+    });
+  }
+
+  Ok(symbols)
+}
+
+fn null_terminated(bytes: &[u8]) -> String {
+  let bytes = bytes
+    .iter()
+    .take_while(|x| **x != 0)
+    .copied()
+    .collect::<Vec<u8>>();
+  String::from_utf8_lossy(&bytes).into_owned()
+}
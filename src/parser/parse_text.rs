@@ -1,15 +1,25 @@
+use crate::parser::parse_jump_table::parse_jump_table;
 use crate::parser::utils::MachineType;
-use crate::parser::{FileHeader, InstructionData, SectionData, SectionEntry};
-use iced_x86::{Decoder, DecoderOptions};
+use crate::parser::{
+  DisassemblyMode, InstructionData, JumpTable, PEHeader, SectionData, SectionEntry,
+};
+use iced_x86::{Code, Decoder, DecoderOptions, FlowControl, Instruction};
 use winnow::error::ErrMode;
 use winnow::error::ErrorKind;
 use winnow::error::ParserError;
 use winnow::PResult;
 
-pub fn parse_text_section<'s>(
-  input: &mut &'s [u8],
-  sections: &Vec<SectionEntry>,
-  file_header: &FileHeader,
+// Unreached bytes are grouped into db lines of this many bytes, same as a row in the hex pane
+const DATA_BYTES_PER_LINE: usize = 8;
+// How far back a switch's bounds check and table load are looked for
+const TRACE_LENGTH: usize = 16;
+
+pub fn parse_text_section(
+  input: &mut &[u8],
+  sections: &[SectionEntry],
+  pe_header: &PEHeader,
+  seeds: &[u32],
+  mode: DisassemblyMode,
 ) -> PResult<SectionData> {
   let text_entry = sections
     .iter()
@@ -19,7 +29,7 @@ pub fn parse_text_section<'s>(
   // go to the start of the .text section
   let size = text_entry.size_of_raw_data as usize;
 
-  if text_entry.pointer_to_raw_data + size as u32 > input.len() as u32 {
+  if text_entry.pointer_to_raw_data as usize + size > input.len() {
     return Err(ErrMode::from_error_kind(input, ErrorKind::Eof));
   }
 
@@ -27,37 +37,299 @@ pub fn parse_text_section<'s>(
     return Err(ErrMode::from_error_kind(input, ErrorKind::Fail));
   }
 
-  let text_bytes = &input
-    [text_entry.pointer_to_raw_data as usize..=text_entry.pointer_to_raw_data as usize + size];
+  let file: &[u8] = input;
+  let text_bytes =
+    &file[text_entry.pointer_to_raw_data as usize..text_entry.pointer_to_raw_data as usize + size];
 
-  let mut decoder = Decoder::new(
+  let file_header = &pe_header.nt_headers.file_header;
+  let image_base = match &pe_header.nt_headers.optional_header {
+    Some(optional_header) => optional_header.image_base(),
+    None => 0,
+  };
+  let base_ip = image_base + text_entry.virtual_address as u64;
+  let code = base_ip..base_ip + text_bytes.len() as u64;
+  let resolve_jump_table =
+    |trace: &[Instruction]| parse_jump_table(file, sections, image_base, &code, trace);
+  let mut decoder = Decoder::with_ip(
     file_header.machine.bitness(),
     text_bytes,
+    base_ip,
     match file_header.machine {
       MachineType::IMAGE_FILE_MACHINE_AMD64 => DecoderOptions::AMD,
       _ => DecoderOptions::NONE,
     },
   );
 
+  // seeds are RVAs, only the ones that land in this section are useful
+  let seeds = seeds
+    .iter()
+    .filter(|x| **x >= text_entry.virtual_address)
+    .map(|x| (x - text_entry.virtual_address) as usize)
+    .filter(|x| *x < text_bytes.len())
+    .collect::<Vec<usize>>();
+
+  let ((data, mut jump_tables), mode) = match mode {
+    DisassemblyMode::RecursiveDescent if !seeds.is_empty() => (
+      recursive_descent(&mut decoder, text_bytes, &seeds, &resolve_jump_table),
+      DisassemblyMode::RecursiveDescent,
+    ),
+    _ => (
+      linear_sweep(&mut decoder, text_bytes, &resolve_jump_table),
+      DisassemblyMode::LinearSweep,
+    ),
+  };
+  jump_tables.sort_by_key(|x| x.dispatch);
+
+  Ok(SectionData {
+    data,
+    name: text_entry.name.clone(),
+    bytes: text_bytes.to_vec(),
+    mode,
+    jump_tables,
+  })
+}
+
+fn linear_sweep(
+  decoder: &mut Decoder,
+  text_bytes: &[u8],
+  resolve_jump_table: &dyn Fn(&[Instruction]) -> Option<JumpTable>,
+) -> (Vec<InstructionData>, Vec<JumpTable>) {
   let mut instructions_data: Vec<InstructionData> = Vec::new();
-  let mut total_offset = 0;
+  let mut jump_tables = vec![];
 
   while decoder.can_decode() {
+    let offset = decoder.position();
     let instr = decoder.decode();
     let instr_len = instr.len();
-    let offset = instr.ip() as usize;
     instructions_data.push(InstructionData {
       instr,
       offset,
       size: instr_len,
-      bytes: text_bytes[total_offset..total_offset + instr_len].to_vec(),
+      bytes: text_bytes[offset..offset + instr_len].to_vec(),
     });
-    total_offset += instr_len;
+
+    // every byte is decoded anyway, the table only annotates the jump
+    if instr.flow_control() == FlowControl::IndirectBranch {
+      let trace = instructions_data[instructions_data.len().saturating_sub(TRACE_LENGTH)..]
+        .iter()
+        .map(|x| x.instr)
+        .collect::<Vec<Instruction>>();
+      jump_tables.extend(resolve_jump_table(&trace));
+    }
   }
 
-  Ok(SectionData {
-    data: instructions_data,
-    name: text_entry.name.clone(),
-    bytes: text_bytes.to_vec(),
-  })
+  (instructions_data, jump_tables)
+}
+
+// Follows the control flow from every seed, so data between functions can't desynchronize the decoder
+fn recursive_descent(
+  decoder: &mut Decoder,
+  text_bytes: &[u8],
+  seeds: &[usize],
+  resolve_jump_table: &dyn Fn(&[Instruction]) -> Option<JumpTable>,
+) -> (Vec<InstructionData>, Vec<JumpTable>) {
+  let base_ip = decoder.ip();
+  let end_ip = base_ip + text_bytes.len() as u64;
+
+  let mut instructions: Vec<Option<Instruction>> = vec![None; text_bytes.len()];
+  let mut covered = vec![false; text_bytes.len()];
+  let mut pending = seeds.to_vec();
+  let mut jump_tables = vec![];
+
+  while let Some(start) = pending.pop() {
+    let mut offset = start;
+    // the straight line of code decoded from this start, a switch is recognized from its tail
+    let mut trace: Vec<Instruction> = vec![];
+    while offset < text_bytes.len() && !covered[offset] {
+      decoder.set_ip(base_ip + offset as u64);
+      decoder
+        .set_position(offset)
+        .expect("offset is inside the section");
+      let instr = decoder.decode();
+      let size = instr.len();
+      // stop at invalid code, or at code overlapping an instruction that was already decoded
+      if instr.is_invalid() || covered[offset..offset + size].iter().any(|x| *x) {
+        break;
+      }
+      covered[offset..offset + size].fill(true);
+      instructions[offset] = Some(instr);
+      offset += size;
+      trace.push(instr);
+
+      let target = instr.near_branch_target();
+      if (base_ip..end_ip).contains(&target) {
+        match instr.flow_control() {
+          FlowControl::ConditionalBranch
+          | FlowControl::UnconditionalBranch
+          | FlowControl::Call
+          | FlowControl::XbeginXabortXend => pending.push((target - base_ip) as usize),
+          _ => {}
+        }
+      }
+
+      if instr.flow_control() == FlowControl::IndirectBranch {
+        if let Some(table) = resolve_jump_table(&trace[trace.len().saturating_sub(TRACE_LENGTH)..])
+        {
+          pending.extend(
+            table
+              .unique_targets()
+              .iter()
+              .map(|x| (x - base_ip) as usize),
+          );
+          jump_tables.push(table);
+        }
+      }
+
+      let falls_through = match instr.flow_control() {
+        FlowControl::UnconditionalBranch
+        | FlowControl::IndirectBranch
+        | FlowControl::Return
+        | FlowControl::Exception => false,
+        // int3 is used as padding between functions
+        FlowControl::Interrupt => instr.code() != Code::Int3,
+        _ => true,
+      };
+      if !falls_through {
+        break;
+      }
+    }
+  }
+
+  let mut instructions_data: Vec<InstructionData> = Vec::new();
+  let mut offset = 0;
+  while offset < text_bytes.len() {
+    if let Some(instr) = instructions[offset] {
+      instructions_data.push(InstructionData {
+        instr,
+        offset,
+        size: instr.len(),
+        bytes: text_bytes[offset..offset + instr.len()].to_vec(),
+      });
+      offset += instr.len();
+      continue;
+    }
+
+    // bytes that were never reached, stop at the next instruction or a full line
+    let mut size = 1;
+    while size < DATA_BYTES_PER_LINE && offset + size < text_bytes.len() && !covered[offset + size]
+    {
+      size += 1;
+    }
+    let bytes = &text_bytes[offset..offset + size];
+    let mut instr = Instruction::with_declare_byte(bytes).expect("at most 16 bytes");
+    instr.set_ip(base_ip + offset as u64);
+    instructions_data.push(InstructionData {
+      instr,
+      offset,
+      size,
+      bytes: bytes.to_vec(),
+    });
+    offset += size;
+  }
+
+  (instructions_data, jump_tables)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const BASE: u64 = 0x1_4000_1000;
+
+  fn descend(
+    bytes: &[u8],
+    seeds: &[usize],
+    resolve_jump_table: &dyn Fn(&[Instruction]) -> Option<JumpTable>,
+  ) -> (Vec<InstructionData>, Vec<JumpTable>) {
+    let mut decoder = Decoder::with_ip(64, bytes, BASE, DecoderOptions::NONE);
+    recursive_descent(&mut decoder, bytes, seeds, resolve_jump_table)
+  }
+
+  // offsets decoded as code, the rest of the listing is db lines
+  fn code_offsets(data: &[InstructionData]) -> Vec<usize> {
+    data
+      .iter()
+      .filter(|x| x.instr.code() != Code::DeclareByte)
+      .map(|x| x.offset)
+      .collect()
+  }
+
+  #[test]
+  fn data_between_functions_stays_data() {
+    let mut bytes = vec![
+      0xe8, 0x0b, 0x00, 0x00, 0x00, // call 0x10
+      0xc3, // ret
+    ];
+    bytes.extend([0xff; 10]); // would decode as garbage
+    bytes.extend([0x31, 0xc0, 0xc3]); // 0x10: xor eax, eax / ret
+    let (data, _) = descend(&bytes, &[0], &|_| None);
+    assert_eq!(code_offsets(&data), [0, 5, 0x10, 0x12]);
+    // unreached bytes are cut into lines of 8
+    let lines = data
+      .iter()
+      .filter(|x| x.instr.code() == Code::DeclareByte)
+      .map(|x| (x.offset, x.size))
+      .collect::<Vec<(usize, usize)>>();
+    assert_eq!(lines, [(6, 8), (0xe, 2)]);
+    // the listing covers every byte once
+    assert_eq!(data.iter().map(|x| x.size).sum::<usize>(), bytes.len());
+  }
+
+  #[test]
+  fn both_sides_of_a_branch_are_followed() {
+    let bytes = [
+      0x74, 0x03, // je 5
+      0xc3, // ret
+      0xcc, 0xcc, // padding
+      0x31, 0xc0, // 5: xor eax, eax
+      0xeb, 0x01, // jmp 0xa
+      0xcc, // skipped
+      0xc3, // 0xa: ret
+    ];
+    let (data, _) = descend(&bytes, &[0], &|_| None);
+    assert_eq!(code_offsets(&data), [0, 2, 5, 7, 0xa]);
+  }
+
+  #[test]
+  fn jump_table_targets_are_reachable() {
+    let mut bytes = vec![
+      0xff, 0xe0, // jmp rax
+    ];
+    bytes.resize(0x20, 0xcc);
+    bytes.extend([0xc3; 0x20]); // a ret every byte, only the table's are code
+    let resolve = |trace: &[Instruction]| {
+      let jump = trace.last()?;
+      Some(JumpTable {
+        dispatch: jump.ip(),
+        table: 0,
+        targets: vec![Some(BASE + 0x20), Some(BASE + 0x28), None],
+      })
+    };
+    let (data, jump_tables) = descend(&bytes, &[0], &resolve);
+    assert_eq!(code_offsets(&data), [0, 0x20, 0x28]);
+    assert_eq!(jump_tables.len(), 1);
+    assert_eq!(jump_tables[0].dispatch, BASE);
+  }
+
+  #[test]
+  fn unresolved_indirect_jump_ends_the_path() {
+    let bytes = [
+      0xff, 0xe0, // jmp rax
+      0x31, 0xc0, 0xc3, // never reached
+    ];
+    let (data, jump_tables) = descend(&bytes, &[0], &|_| None);
+    assert_eq!(code_offsets(&data), [0]);
+    assert!(jump_tables.is_empty());
+  }
+
+  #[test]
+  fn overlapping_code_is_not_decoded_twice() {
+    let bytes = [
+      0xeb, 0xff, // jmp 1, into its own second byte
+      0xc0, 0xc3, // 1: inc eax (ff c0) / ret
+    ];
+    let (data, _) = descend(&bytes, &[0], &|_| None);
+    // the jump wins, its target overlaps it and is left alone
+    assert_eq!(code_offsets(&data), [0]);
+  }
 }
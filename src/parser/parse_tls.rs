@@ -0,0 +1,66 @@
+use crate::parser::utils::{get_le_u32, get_le_u64, get_rva_slice, DataDirectoryTableField};
+use crate::parser::{OptionalHeader, PEHeader, SectionEntry};
+use winnow::error::ErrMode;
+use winnow::error::ErrorKind;
+use winnow::error::ParserError;
+use winnow::PResult;
+use winnow::Parser;
+
+// TLS callbacks run before the entry point, returns their RVAs
+pub fn parse_tls_callbacks(
+  input: &mut &[u8],
+  pe_header: &PEHeader,
+  sections: &[SectionEntry],
+) -> PResult<Vec<u32>> {
+  let optional_header = match &pe_header.nt_headers.optional_header {
+    Some(optional_header) => optional_header,
+    None => return Ok(vec![]),
+  };
+  let directory = match optional_header.data_directory(DataDirectoryTableField::TLS_TABLE) {
+    Some(directory) => directory,
+    None => return Ok(vec![]),
+  };
+  let is_64 = matches!(optional_header, OptionalHeader::ImageOptionalHeader64(_));
+  let pointer_size = if is_64 { 8 } else { 4 };
+  let image_base = optional_header.image_base();
+
+  // StartAddressOfRawData, EndAddressOfRawData and AddressOfIndex come before AddressOfCallBacks
+  let mut table = get_rva_slice(input, sections, directory.virtual_address, pointer_size * 4)
+    .ok_or(ErrMode::from_error_kind(input, ErrorKind::Eof))?;
+  let mut pointers = Vec::with_capacity(4);
+  for _ in 0..4 {
+    pointers.push(read_pointer(&mut table, is_64)?);
+  }
+  let address_of_callbacks = pointers[3];
+  if address_of_callbacks == 0 {
+    return Ok(vec![]);
+  }
+
+  // the callbacks are a null terminated array of VAs
+  let mut callbacks = Vec::new();
+  let mut rva = address_of_callbacks.wrapping_sub(image_base) as u32;
+  loop {
+    let mut entry = get_rva_slice(input, sections, rva, pointer_size)
+      .ok_or(ErrMode::from_error_kind(input, ErrorKind::Eof))?;
+    let callback = read_pointer(&mut entry, is_64)?;
+    if callback == 0 {
+      break;
+    }
+    callbacks.push(callback.wrapping_sub(image_base) as u32);
+    // the array's address comes from the file, a walk that would wrap past 4GB stops there
+    match rva.checked_add(pointer_size) {
+      Some(next) => rva = next,
+      None => break,
+    }
+  }
+
+  Ok(callbacks)
+}
+
+fn read_pointer(input: &mut &[u8], is_64: bool) -> PResult<u64> {
+  if is_64 {
+    get_le_u64.parse_next(input)
+  } else {
+    get_le_u32.parse_next(input).map(|x| x as u64)
+  }
+}
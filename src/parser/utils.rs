@@ -1,3 +1,4 @@
+use crate::parser::SectionEntry;
 use strum::{EnumIter, IntoEnumIterator, IntoStaticStr};
 use winnow::error::ErrMode;
 use winnow::error::ErrorKind;
@@ -354,7 +355,7 @@ impl DLLCharacteristics {
   }
 }
 
-#[derive(Debug, Default, EnumIter, Clone, IntoStaticStr)]
+#[derive(Debug, Default, EnumIter, Clone, IntoStaticStr, PartialEq)]
 #[allow(non_camel_case_types)]
 pub enum DataDirectoryTableField {
   #[default]
@@ -401,6 +402,147 @@ impl TryFrom<u32> for DataDirectoryTableField {
   }
 }
 
+#[derive(Debug, Default, EnumIter, Clone, IntoStaticStr, PartialEq)]
+#[allow(non_camel_case_types)]
+pub enum ClrFlags {
+  #[default]
+  COMIMAGE_FLAGS_ILONLY, // The image contains only IL code
+  COMIMAGE_FLAGS_32BITREQUIRED, // The image can only be loaded into a 32-bit process
+  COMIMAGE_FLAGS_IL_LIBRARY,    // The image is a library of precompiled IL
+  COMIMAGE_FLAGS_STRONGNAMESIGNED, // The image is signed with a strong name
+  COMIMAGE_FLAGS_NATIVE_ENTRYPOINT, // The entry point is an RVA to native code rather than a metadata token
+  COMIMAGE_FLAGS_TRACKDEBUGDATA,    // The loader and JIT track debug information about the methods
+  COMIMAGE_FLAGS_32BITPREFERRED,    // The image prefers to run as a 32-bit process
+}
+
+impl From<ClrFlags> for u32 {
+  fn from(value: ClrFlags) -> Self {
+    match value {
+      ClrFlags::COMIMAGE_FLAGS_ILONLY => 0x00000001,
+      ClrFlags::COMIMAGE_FLAGS_32BITREQUIRED => 0x00000002,
+      ClrFlags::COMIMAGE_FLAGS_IL_LIBRARY => 0x00000004,
+      ClrFlags::COMIMAGE_FLAGS_STRONGNAMESIGNED => 0x00000008,
+      ClrFlags::COMIMAGE_FLAGS_NATIVE_ENTRYPOINT => 0x00000010,
+      ClrFlags::COMIMAGE_FLAGS_TRACKDEBUGDATA => 0x00010000,
+      ClrFlags::COMIMAGE_FLAGS_32BITPREFERRED => 0x00020000,
+    }
+  }
+}
+
+impl ClrFlags {
+  pub fn from_u32(value: u32) -> Vec<ClrFlags> {
+    let mut flags = Vec::new();
+
+    for flag in ClrFlags::iter() {
+      let val: u32 = flag.clone().into();
+      if value & val != 0 {
+        flags.push(flag);
+      }
+    }
+
+    flags
+  }
+}
+
+#[derive(Debug, Default, EnumIter, Clone, IntoStaticStr, PartialEq)]
+#[allow(non_camel_case_types)]
+pub enum SectionCharacteristics {
+  #[default]
+  IMAGE_SCN_CNT_CODE, // The section contains executable code
+  IMAGE_SCN_CNT_INITIALIZED_DATA, // The section contains initialized data
+  IMAGE_SCN_CNT_UNINITIALIZED_DATA, // The section contains uninitialized data
+  IMAGE_SCN_MEM_DISCARDABLE,      // The section can be discarded as needed
+  IMAGE_SCN_MEM_SHARED,           // The section can be shared in memory
+  IMAGE_SCN_MEM_EXECUTE,          // The section can be executed as code
+  IMAGE_SCN_MEM_READ,             // The section can be read
+  IMAGE_SCN_MEM_WRITE,            // The section can be written to
+}
+
+impl From<SectionCharacteristics> for u32 {
+  fn from(value: SectionCharacteristics) -> Self {
+    match value {
+      SectionCharacteristics::IMAGE_SCN_CNT_CODE => 0x00000020,
+      SectionCharacteristics::IMAGE_SCN_CNT_INITIALIZED_DATA => 0x00000040,
+      SectionCharacteristics::IMAGE_SCN_CNT_UNINITIALIZED_DATA => 0x00000080,
+      SectionCharacteristics::IMAGE_SCN_MEM_DISCARDABLE => 0x02000000,
+      SectionCharacteristics::IMAGE_SCN_MEM_SHARED => 0x10000000,
+      SectionCharacteristics::IMAGE_SCN_MEM_EXECUTE => 0x20000000,
+      SectionCharacteristics::IMAGE_SCN_MEM_READ => 0x40000000,
+      SectionCharacteristics::IMAGE_SCN_MEM_WRITE => 0x80000000,
+    }
+  }
+}
+
+#[derive(Debug, Clone, IntoStaticStr, PartialEq)]
+pub enum StaleBindingReason {
+  #[strum(serialize = "zero timestamp")]
+  ZeroTimestamp,
+  #[strum(serialize = "timestamp in the future")]
+  FutureTimestamp,
+  #[strum(serialize = "image is relocatable (DYNAMIC_BASE)")]
+  DynamicBase,
+  #[strum(serialize = "image opts out of binding (NO_BIND)")]
+  NoBind,
+}
+
+#[derive(Debug, Clone, IntoStaticStr, PartialEq)]
+pub enum DosStubAnomaly {
+  #[strum(serialize = "entry point is outside the stub")]
+  EntryOutsideStub,
+  #[strum(serialize = "entry point is not at the start of the stub")]
+  EntryPoint,
+  #[strum(serialize = "code differs from the standard stub")]
+  Code,
+  #[strum(serialize = "message differs from the standard stub")]
+  Message,
+  #[strum(serialize = "no message found")]
+  NoMessage,
+}
+
+// Converts an RVA to a file offset, RVAs before the first section live in the headers which are mapped 1:1
+pub fn rva_to_offset(sections: &[SectionEntry], rva: u32) -> Option<usize> {
+  for section in sections {
+    if section.contains_rva(rva) {
+      let delta = rva - section.virtual_address;
+      if delta >= section.size_of_raw_data {
+        return None;
+      }
+      return Some(section.pointer_to_raw_data as usize + delta as usize);
+    }
+  }
+
+  match sections.iter().map(|s| s.virtual_address).min() {
+    Some(first) if rva >= first => None,
+    _ => Some(rva as usize),
+  }
+}
+
+// Returns the raw bytes backing an RVA range, only if the whole range is present in the file
+pub fn get_rva_slice<'a>(
+  input: &'a [u8],
+  sections: &[SectionEntry],
+  rva: u32,
+  size: u32,
+) -> Option<&'a [u8]> {
+  let start = rva_to_offset(sections, rva)?;
+  input.get(start..start.checked_add(size as usize)?)
+}
+
+// Reads a null terminated ASCII string, such as a DLL or function name, at an RVA
+pub fn get_rva_string(input: &[u8], sections: &[SectionEntry], rva: u32) -> Option<String> {
+  let start = rva_to_offset(sections, rva)?;
+  let name = input
+    .get(start..)?
+    .iter()
+    .take_while(|b| **b != 0)
+    .copied()
+    .collect::<Vec<u8>>();
+  if name.is_empty() || !name.is_ascii() {
+    return None;
+  }
+  String::from_utf8(name).ok()
+}
+
 pub fn get_ascii_string<'s>(input: &mut &'s [u8], len: usize) -> PResult<String> {
   let bytes = take_while(len, |b: u8| b.is_ascii()).parse_next(input)?;
   let string = String::from_utf8(bytes.to_vec())
@@ -474,3 +616,56 @@ pub fn get_le_u16_vec<'s>(input: &mut &'s [u8], len: usize) -> PResult<Vec<u16>>
   }
   Ok(arr)
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // a header claiming to end past 4GB, as a fuzzed or hostile file would
+  fn wrapping_section() -> SectionEntry {
+    SectionEntry {
+      virtual_address: 0xffff_f000,
+      virtual_size: 0x2000,
+      size_of_raw_data: 0x1000,
+      pointer_to_raw_data: 0x400,
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn rva_in_a_section_ending_past_4gb() {
+    let sections = [wrapping_section()];
+    assert_eq!(rva_to_offset(&sections, 0xffff_f010), Some(0x410));
+    assert_eq!(rva_to_offset(&sections, 0x10), Some(0x10));
+    assert!(sections[0].contains_rva(u32::MAX));
+  }
+
+  #[test]
+  fn offset_past_4gb_has_no_rva() {
+    let section = SectionEntry {
+      virtual_address: u32::MAX,
+      size_of_raw_data: 0x10,
+      ..Default::default()
+    };
+    assert_eq!(section.offset_to_rva(0), Some(u32::MAX));
+    assert_eq!(section.offset_to_rva(1), None);
+  }
+
+  #[test]
+  fn rva_slice_is_whole_or_nothing() {
+    let sections = [SectionEntry {
+      virtual_address: 0x1000,
+      virtual_size: 0x20,
+      size_of_raw_data: 0x10,
+      pointer_to_raw_data: 0x10,
+      ..Default::default()
+    }];
+    let bytes = [0u8; 0x20];
+    assert_eq!(
+      get_rva_slice(&bytes, &sections, 0x1008, 8).map(|x| x.len()),
+      Some(8)
+    );
+    assert_eq!(get_rva_slice(&bytes, &sections, 0x1008, 0x10), None);
+    assert_eq!(get_rva_slice(&bytes, &sections, 0x1010, 1), None); // virtual only
+  }
+}
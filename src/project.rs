@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+// What was learned about a binary, kept between sessions under the binary's SHA-256 so renamed
+// copies pick it up and a rebuilt binary doesn't get stale notes
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Project {
+  pub sha256: String,
+  #[serde(default)]
+  pub comments: BTreeMap<u64, String>, // By VA, shown after the instruction
+  #[serde(default)]
+  pub labels: BTreeMap<u64, String>, // By VA, replace the generated or imported name
+  #[serde(default)]
+  pub bookmarks: BTreeSet<u64>,
+  #[serde(default)]
+  pub last_address: Option<u64>, // Top of the listing when the binary was closed
+  #[serde(default)]
+  pub last_hex_offset: usize,
+  #[serde(skip)]
+  pub read_only: bool, // A broken project file couldn't be moved aside, a save would overwrite it
+  #[serde(skip)]
+  saved: Option<String>, // What the file on disk holds, None until there is one
+}
+
+impl Project {
+  pub fn empty(bytes: &[u8]) -> Project {
+    Project {
+      sha256: sha256_hex(bytes),
+      ..Default::default()
+    }
+  }
+
+  // The saved project for these bytes, or an empty one the first time they are opened
+  pub fn load(bytes: &[u8]) -> anyhow::Result<Project> {
+    let sha256 = sha256_hex(bytes);
+    let path = match project_path(&sha256) {
+      Some(path) if path.exists() => path,
+      _ => return Ok(Project::empty(bytes)),
+    };
+    let text = std::fs::read_to_string(&path)?;
+    let mut project: Project = serde_json::from_str(&text)?;
+    project.sha256 = sha256;
+    project.saved = Some(text);
+    Ok(project)
+  }
+
+  // Moves a project file that can't be read to .bak, so saving doesn't overwrite what's in it
+  pub fn set_aside(&self) -> anyhow::Result<PathBuf> {
+    let path =
+      project_path(&self.sha256).ok_or(anyhow::anyhow!("No home directory to save into"))?;
+    let backup = path.with_extension("json.bak");
+    if backup.exists() {
+      anyhow::bail!("{} is already taken", backup.display());
+    }
+    std::fs::rename(&path, &backup)?;
+    Ok(backup)
+  }
+
+  pub fn save(&mut self) -> anyhow::Result<()> {
+    let path =
+      project_path(&self.sha256).ok_or(anyhow::anyhow!("No home directory to save into"))?;
+    self.save_to(&path)
+  }
+
+  // Only writes when something changed, and only starts a file once there is more than the position
+  // to keep. The text goes to a temporary file first so a crash can't leave half a project behind
+  fn save_to(&mut self, path: &Path) -> anyhow::Result<()> {
+    if self.read_only {
+      return Ok(());
+    }
+    let text = serde_json::to_string_pretty(self)?;
+    let annotated =
+      !self.comments.is_empty() || !self.labels.is_empty() || !self.bookmarks.is_empty();
+    match &self.saved {
+      Some(saved) if *saved == text => return Ok(()),
+      None if !annotated => return Ok(()),
+      _ => {}
+    }
+    if let Some(dir) = path.parent() {
+      std::fs::create_dir_all(dir)?;
+    }
+    let temp = path.with_extension("json.tmp");
+    std::fs::write(&temp, &text)?;
+    std::fs::rename(&temp, path)?;
+    self.saved = Some(text);
+    Ok(())
+  }
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+  Sha256::digest(bytes)
+    .iter()
+    .map(|b| format!("{:02x}", b))
+    .collect()
+}
+
+// ~/.asm_testing/projects/<sha256>.json
+fn project_path(sha256: &str) -> Option<PathBuf> {
+  let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+  let mut path = PathBuf::from(home);
+  path.push(".asm_testing");
+  path.push("projects");
+  path.push(format!("{}.json", sha256));
+  Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn temp_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("asm_testing_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir.join("project.json")
+  }
+
+  #[test]
+  fn position_alone_doesnt_start_a_file() {
+    let path = temp_path("position");
+    let mut project = Project::empty(b"MZ");
+    project.last_address = Some(0x1_4000_1000);
+    project.save_to(&path).unwrap();
+    assert!(!path.exists());
+
+    project.bookmarks.insert(0x1_4000_1000);
+    project.save_to(&path).unwrap();
+    assert!(path.exists());
+    assert!(!path.with_extension("json.tmp").exists());
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+  }
+
+  #[test]
+  fn unchanged_project_isnt_written_again() {
+    let path = temp_path("unchanged");
+    let mut project = Project::empty(b"MZ");
+    project.comments.insert(0x1000, "entry".to_owned());
+    project.save_to(&path).unwrap();
+    // anything written now would be lost, the project only rewrites the file when it changed
+    std::fs::write(&path, "edited elsewhere").unwrap();
+    project.save_to(&path).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "edited elsewhere");
+
+    project.labels.insert(0x1000, "start".to_owned());
+    project.save_to(&path).unwrap();
+    let saved: Project = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(saved.labels.get(&0x1000).map(String::as_str), Some("start"));
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+  }
+
+  #[test]
+  fn read_only_project_is_never_written() {
+    let path = temp_path("read_only");
+    let mut project = Project::empty(b"MZ");
+    project.read_only = true;
+    project.comments.insert(0x1000, "entry".to_owned());
+    project.save_to(&path).unwrap();
+    assert!(!path.exists());
+  }
+}
@@ -0,0 +1,33 @@
+use crate::tui::App;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnnotationKind {
+  Comment,
+  Label,
+}
+
+// Text typed after ; or l, starts from what is already there and an empty one removes it
+#[derive(Debug)]
+pub struct AnnotationPrompt {
+  pub kind: AnnotationKind,
+  pub address: u64,
+  pub input: String,
+  pub error: Option<String>,
+}
+
+// Labels are typed into the go to and assemble prompts, so they have to read back as one name
+pub fn check_label(app: &App, address: u64, label: &str) -> Result<(), String> {
+  if label
+    .chars()
+    .any(|x| x.is_whitespace() || matches!(x, ',' | '+' | '-' | '*' | '[' | ']'))
+  {
+    return Err("Labels can't have spaces or , + - * [ ]".to_owned());
+  }
+  if crate::analysis::parse_number(label).is_some() {
+    return Err(format!("{} reads as a number", label));
+  }
+  match app.symbols.address(label) {
+    Some(other) if other != address => Err(format!("{:#x} is already named {}", other, label)),
+    _ => Ok(()),
+  }
+}
@@ -0,0 +1,159 @@
+use crate::parser::InstructionData;
+use crate::tui::App;
+use iced_x86::{
+  Instruction, InstructionInfoFactory, OpAccess, OpKind, Register, RflagsBits, UsedMemory,
+};
+use ratatui::{prelude::*, widgets::*};
+
+pub const DETAILS_HEIGHT: u16 = 12;
+
+const FLAG_NAMES: [(u32, &str); 9] = [
+  (RflagsBits::OF, "OF"),
+  (RflagsBits::SF, "SF"),
+  (RflagsBits::ZF, "ZF"),
+  (RflagsBits::AF, "AF"),
+  (RflagsBits::CF, "CF"),
+  (RflagsBits::PF, "PF"),
+  (RflagsBits::DF, "DF"),
+  (RflagsBits::IF, "IF"),
+  (RflagsBits::AC, "AC"),
+];
+
+// Encoding, requirements and side effects of the instruction at the top of the listing
+pub fn render_details(f: &mut Frame, app: &App, area: Rect) {
+  let lines = match app.data.text_section.data.get(app.data_scroll) {
+    Some(data) => details_lines(data),
+    None => vec![],
+  };
+  let p = Paragraph::new(lines)
+    .block(
+      Block::default()
+        .title(" Instruction ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::White))
+        .padding(Padding::new(1, 0, 0, 0)),
+    )
+    .white();
+  f.render_widget(p, area);
+}
+
+fn details_lines(data: &InstructionData) -> Vec<Line<'static>> {
+  let instr = &data.instr;
+  let op_code = instr.op_code();
+  let mut factory = InstructionInfoFactory::new();
+  let info = factory.info(instr);
+
+  let mut read = vec![];
+  let mut written = vec![];
+  for used in info.used_registers() {
+    let name = format!("{:?}", used.register());
+    match used.access() {
+      OpAccess::Read | OpAccess::CondRead => read.push(name),
+      OpAccess::Write | OpAccess::CondWrite => written.push(name),
+      OpAccess::ReadWrite | OpAccess::ReadCondWrite => {
+        read.push(name.clone());
+        written.push(name);
+      }
+      _ => {}
+    }
+  }
+  let memory = info
+    .used_memory()
+    .iter()
+    .map(|x| format!("{} {:?}", memory_operand(x), x.access()))
+    .collect::<Vec<String>>();
+  let operands = (0..instr.op_count())
+    .map(|i| operand_kind(instr, i, op_code.op_kind(i)))
+    .collect::<Vec<String>>();
+  let cpuid = instr
+    .cpuid_features()
+    .iter()
+    .map(|x| format!("{:?}", x))
+    .collect::<Vec<String>>();
+
+  vec![
+    detail_line("opcode", op_code.op_code_string().to_owned()),
+    detail_line("form", op_code.instruction_string().to_owned()),
+    detail_line(
+      "encoding",
+      format!("{:?}, {} bytes", instr.encoding(), data.size),
+    ),
+    detail_line("cpuid", cpuid.join(" ")),
+    detail_line("operands", operands.join(", ")),
+    detail_line("reads", read.join(" ")),
+    detail_line("writes", written.join(" ")),
+    detail_line("memory", memory.join(", ")),
+    detail_line(
+      "rflags",
+      format!(
+        "tested {}  modified {}  cleared {}  set {}",
+        flag_names(instr.rflags_read()),
+        flag_names(instr.rflags_modified()),
+        flag_names(instr.rflags_cleared()),
+        flag_names(instr.rflags_set()),
+      ),
+    ),
+  ]
+}
+
+fn detail_line(key: &str, value: String) -> Line<'static> {
+  let value = if value.is_empty() {
+    "-".to_owned()
+  } else {
+    value
+  };
+  Line::from(vec![
+    format!("{:<9}", key).yellow(),
+    " ".into(),
+    value.white(),
+  ])
+}
+
+// The decoded kind, what the encoding allows in its place, and the register when there is one
+fn operand_kind(instr: &Instruction, operand: u32, allowed: impl std::fmt::Debug) -> String {
+  match instr.op_kind(operand) {
+    OpKind::Register => format!(
+      "{:?} {:?} ({:?})",
+      OpKind::Register,
+      instr.op_register(operand),
+      allowed
+    ),
+    kind => format!("{:?} ({:?})", kind, allowed),
+  }
+}
+
+fn memory_operand(memory: &UsedMemory) -> String {
+  let mut parts = vec![];
+  if memory.base() != Register::None {
+    parts.push(format!("{:?}", memory.base()));
+  }
+  if memory.index() != Register::None {
+    parts.push(format!("{:?}*{}", memory.index(), memory.scale()));
+  }
+  if memory.displacement() != 0 || parts.is_empty() {
+    parts.push(format!("{:#x}", memory.displacement()));
+  }
+  let segment = match memory.segment() {
+    Register::None => String::new(),
+    segment => format!("{:?}:", segment),
+  };
+  format!(
+    "{:?} {}[{}]",
+    memory.memory_size(),
+    segment,
+    parts.join("+")
+  )
+}
+
+fn flag_names(bits: u32) -> String {
+  let names = FLAG_NAMES
+    .iter()
+    .filter(|(bit, _)| bits & bit != 0)
+    .map(|(_, name)| *name)
+    .collect::<Vec<&str>>();
+  if names.is_empty() {
+    "-".to_owned()
+  } else {
+    names.join(" ")
+  }
+}
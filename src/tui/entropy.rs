@@ -0,0 +1,153 @@
+use crate::analysis::{ENTROPY_WINDOW, HIGH_ENTROPY};
+use crate::tui::App;
+use ratatui::{prelude::*, widgets::*};
+
+const SECTION_CHART_HEIGHT: u16 = 12;
+const FILE_CHART_HEIGHT: u16 = 8;
+// Entropy is drawn in hundredths so the bars keep two decimals
+const SCALE: f64 = 100.0;
+const MAX_ENTROPY: u64 = 8 * SCALE as u64;
+
+// Bars per section, a curve over the whole file, and what looks packed
+pub fn render_entropy(f: &mut Frame, app: &App, area: Rect) {
+  let report = &app.entropy;
+  let split = Layout::default()
+    .direction(Direction::Vertical)
+    .constraints([
+      Constraint::Length(SECTION_CHART_HEIGHT),
+      Constraint::Length(FILE_CHART_HEIGHT),
+      Constraint::Min(0),
+    ])
+    .split(area);
+
+  let bars = report
+    .sections
+    .iter()
+    .map(|x| {
+      Bar::default()
+        .label(Line::from(x.name.clone()))
+        .value((x.entropy * SCALE) as u64)
+        .text_value(format!("{:.2}", x.entropy))
+        .style(Style::default().fg(entropy_color(x.entropy)))
+        .value_style(
+          Style::default()
+            .fg(Color::Black)
+            .bg(entropy_color(x.entropy)),
+        )
+    })
+    .collect::<Vec<Bar>>();
+  // as wide as the sections fit, names are cut to the bar
+  let inner_width = split[0].width.saturating_sub(2) as usize;
+  let bar_width = (inner_width / report.sections.len().max(1)).saturating_sub(2);
+  let chart = BarChart::default()
+    .block(
+      Block::default()
+        .title(" Section entropy (bits per byte) ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::White)),
+    )
+    .data(BarGroup::default().bars(&bars))
+    .bar_width(bar_width.clamp(3, 12) as u16)
+    .bar_gap(2)
+    .max(MAX_ENTROPY)
+    .label_style(Style::default().fg(Color::Yellow));
+  f.render_widget(chart, split[0]);
+
+  // every column is the highest window it covers, so a short encrypted blob still shows
+  let windows = &report.windows;
+  let width = (split[1].width.saturating_sub(2) as usize).min(windows.len());
+  let columns = (0..width)
+    .map(|column| {
+      let start = column * windows.len() / width;
+      let end = ((column + 1) * windows.len() / width).max(start + 1);
+      (windows[start..end].iter().copied().fold(0.0, f64::max) * SCALE) as u64
+    })
+    .collect::<Vec<u64>>();
+  let end = format!(" {:#x} ", app.data.bytes.len());
+  let sparkline = Sparkline::default()
+    .block(
+      Block::default()
+        .title(format!(
+          " File entropy {:.2}, {} byte windows every {} bytes ",
+          report.file, ENTROPY_WINDOW, report.window_step
+        ))
+        .title(block::Title::from(" 0x0 ").position(block::Position::Bottom))
+        .title(
+          block::Title::from(end)
+            .position(block::Position::Bottom)
+            .alignment(Alignment::Right),
+        )
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::White)),
+    )
+    .data(&columns)
+    .max(MAX_ENTROPY)
+    .style(Style::default().fg(Color::Cyan));
+  f.render_widget(sparkline, split[1]);
+
+  let bottom = Layout::default()
+    .direction(Direction::Horizontal)
+    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+    .split(split[2]);
+
+  let mut section_lines = vec![Line::from(
+    format!(
+      "{:<10} {:>10} {:>10} {:>8}",
+      "Name", "Raw", "Virtual", "Entropy"
+    )
+    .dark_gray(),
+  )];
+  section_lines.extend(report.sections.iter().map(|x| {
+    Line::from(vec![
+      format!("{:<10} ", x.name).yellow(),
+      format!(
+        "{:>10} {:>10} ",
+        format!("{:#x}", x.raw_size),
+        format!("{:#x}", x.virtual_size)
+      )
+      .green(),
+      format!("{:>8.2}", x.entropy).fg(entropy_color(x.entropy)),
+      if x.executable {
+        "  x".white()
+      } else {
+        "".into()
+      },
+    ])
+  }));
+  let sections = Paragraph::new(section_lines).block(
+    Block::default()
+      .title(" Sections ")
+      .borders(Borders::ALL)
+      .border_style(Style::default().fg(Color::White))
+      .padding(Padding::new(1, 0, 0, 0)),
+  );
+  f.render_widget(sections, bottom[0]);
+
+  let sign_lines = if report.packer_signs.is_empty() {
+    vec![Line::from("Nothing points at a packer".green())]
+  } else {
+    report
+      .packer_signs
+      .iter()
+      .map(|x| Line::from(format!("! {}", x.description()).light_red()))
+      .collect()
+  };
+  let signs = Paragraph::new(sign_lines).wrap(Wrap { trim: true }).block(
+    Block::default()
+      .title(format!(" Packer signs ({}) ", report.packer_signs.len()))
+      .borders(Borders::ALL)
+      .border_style(Style::default().fg(Color::White))
+      .padding(Padding::new(1, 0, 0, 0)),
+  );
+  f.render_widget(signs, bottom[1]);
+}
+
+fn entropy_color(entropy: f64) -> Color {
+  if entropy >= HIGH_ENTROPY {
+    Color::Red
+  } else if entropy >= 6.0 {
+    Color::Yellow
+  } else {
+    Color::Green
+  }
+}
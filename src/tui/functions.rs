@@ -0,0 +1,58 @@
+use crate::tui::{App, Focus};
+use ratatui::{prelude::*, widgets::*};
+
+const SIZE_WIDTH: usize = 7;
+
+pub fn render_functions(f: &mut Frame, app: &App, area: Rect) {
+  // keep the selected function in view
+  let height = area.height.saturating_sub(2) as usize;
+  // borders, left padding and a space before the size column
+  let name_width = (area.width as usize).saturating_sub(4 + SIZE_WIDTH);
+  let offset = app
+    .function_selected
+    .saturating_sub(height.saturating_sub(1));
+
+  let lines = app
+    .functions
+    .iter()
+    .enumerate()
+    .skip(offset)
+    .take(height)
+    .map(|(i, function)| {
+      let name = app.function_name(function);
+      let size = format!(" {:>width$}", function.size(), width = SIZE_WIDTH);
+      let name = if name.chars().count() > name_width {
+        let name = name
+          .chars()
+          .take(name_width.saturating_sub(2))
+          .collect::<String>();
+        format!("{}..", name)
+      } else {
+        format!("{:<width$}", name, width = name_width)
+      };
+      if i == app.function_selected {
+        Line::from(vec![
+          name.yellow().on_dark_gray(),
+          size.green().on_dark_gray(),
+        ])
+      } else {
+        Line::from(vec![name.yellow(), size.green()])
+      }
+    })
+    .collect::<Vec<Line>>();
+
+  let border_color = match app.focus {
+    Focus::Functions => Color::Yellow,
+    Focus::Listing => Color::White,
+  };
+  let p = Paragraph::new(lines)
+    .block(
+      Block::default()
+        .title(format!(" Functions ({}) ", app.functions.len()))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(border_color))
+        .padding(Padding::new(1, 0, 0, 0)),
+    )
+    .white();
+  f.render_widget(p, area);
+}
@@ -0,0 +1,107 @@
+use crate::analysis;
+use crate::parser::rva_to_offset;
+use crate::tui::App;
+
+// Text typed after g, the error stays until the next key so a typo can be fixed
+#[derive(Debug, Default)]
+pub struct GotoPrompt {
+  pub input: String,
+  pub error: Option<String>,
+}
+
+// Code lands in the listing, anything else that has bytes in the file lands in the hex tab
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GotoTarget {
+  Instruction(usize),
+  FileOffset(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AddressKind {
+  Va,
+  Rva,
+  Offset,
+}
+
+// Resolves the prompt to an instruction of the listing, or a file offset outside the code
+pub fn resolve_goto(app: &App, input: &str) -> Result<GotoTarget, String> {
+  let input = input.trim();
+  if input.is_empty() {
+    return Err("Type an address or a name".to_owned());
+  }
+  let (image_base, image_size) = match &app.data.headers.nt_headers.optional_header {
+    Some(optional_header) => (
+      optional_header.image_base(),
+      optional_header.size_of_image() as u64,
+    ),
+    None => (0, 0),
+  };
+
+  // rva 0x1000, off 0x400, or a bare number that is taken as a VA when it lands in the image
+  let (kind, value) = match input.split_once(' ') {
+    Some(("va", value)) => (Some(AddressKind::Va), value.trim()),
+    Some(("rva", value)) => (Some(AddressKind::Rva), value.trim()),
+    Some(("off" | "offset", value)) => (Some(AddressKind::Offset), value.trim()),
+    _ => (None, input),
+  };
+  let va = match (kind, analysis::parse_number(value)) {
+    (Some(AddressKind::Va), Some(value)) | (None, Some(value))
+      if value >= image_base && value - image_base < image_size =>
+    {
+      value
+    }
+    (Some(AddressKind::Rva), Some(value)) | (None, Some(value)) if value < image_size => {
+      image_base + value
+    }
+    (Some(AddressKind::Offset), Some(value)) => {
+      let offset = usize::try_from(value)
+        .ok()
+        .filter(|x| *x < app.data.bytes.len())
+        .ok_or(format!("{:#x} is past the file", value))?;
+      // headers and the overlay aren't loaded, they only have a file offset
+      let rva = app
+        .data
+        .section_table
+        .iter()
+        .find_map(|x| x.offset_to_rva(offset as u32));
+      match rva {
+        Some(rva) => image_base + rva as u64,
+        None => return Ok(GotoTarget::FileOffset(offset)),
+      }
+    }
+    (Some(_), Some(value)) | (None, Some(value)) => {
+      return Err(format!("{:#x} is outside the image", value))
+    }
+    (Some(_), None) => return Err(format!("{} isn't a number", value)),
+    (None, None) => app
+      .symbols
+      .address(input)
+      .ok_or(format!("No symbol named {}", input))?,
+  };
+
+  let instructions = &app.data.text_section.data;
+  if let Some(index) = analysis::containing_instruction_index(instructions, va) {
+    return Ok(GotoTarget::Instruction(index));
+  }
+
+  // only the code section is disassembled, other sections are shown in the hex tab
+  let rva = u32::try_from(va.wrapping_sub(image_base)).ok();
+  let offset = rva
+    .and_then(|rva| rva_to_offset(&app.data.section_table, rva))
+    .filter(|x| *x < app.data.bytes.len());
+  if let Some(offset) = offset {
+    return Ok(GotoTarget::FileOffset(offset));
+  }
+  let section = app
+    .data
+    .section_table
+    .iter()
+    .find(|x| rva.is_some_and(|rva| x.contains_rva(rva)));
+  match section {
+    Some(section) => Err(format!(
+      "{:#x} is in {}, past its bytes in the file",
+      va, section.name
+    )),
+    None => Err(format!("{:#x} isn't in a section", va)),
+  }
+}
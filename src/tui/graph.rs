@@ -0,0 +1,400 @@
+use crate::analysis::{self, ControlFlowGraph, EdgeKind};
+use crate::parser::InstructionData;
+use iced_x86::Formatter;
+use ratatui::prelude::*;
+use std::collections::HashMap;
+
+const BOX_SPACING: usize = 4; // Columns between boxes in the same layer
+const MAX_LINE_WIDTH: usize = 48;
+const MARGIN: usize = 2;
+
+// How much of each block is drawn, from every instruction down to just the address
+pub const ZOOM_LEVELS: usize = 3;
+const ZOOM_INSTRUCTIONS: [Option<usize>; ZOOM_LEVELS] = [None, Some(3), Some(0)];
+
+// The graph is drawn once into a grid of characters, scrolling only moves the visible window over it
+pub struct GraphCanvas {
+  pub width: usize,
+  pub height: usize,
+  cells: Vec<Vec<(char, Color)>>,
+}
+
+impl GraphCanvas {
+  fn new(width: usize, height: usize) -> Self {
+    GraphCanvas {
+      width,
+      height,
+      cells: vec![vec![(' ', Color::White); width]; height],
+    }
+  }
+
+  fn set(&mut self, x: usize, y: usize, c: char, color: Color) {
+    if let Some(cell) = self.cells.get_mut(y).and_then(|row| row.get_mut(x)) {
+      *cell = (c, color);
+    }
+  }
+
+  fn get(&self, x: usize, y: usize) -> char {
+    match self.cells.get(y).and_then(|row| row.get(x)) {
+      Some((c, _)) => *c,
+      None => ' ',
+    }
+  }
+
+  fn text(&mut self, x: usize, y: usize, text: &str, color: Color) {
+    for (i, c) in text.chars().enumerate() {
+      self.set(x + i, y, c, color);
+    }
+  }
+
+  // lines crossing each other become a junction instead of hiding one another
+  fn line(&mut self, x: usize, y: usize, c: char, color: Color) {
+    let existing = self.get(x, y);
+    let c = match (existing, c) {
+      ('│', '─') | ('─', '│') | ('┼', _) => '┼',
+      _ => c,
+    };
+    self.set(x, y, c, color);
+  }
+
+  fn vertical(&mut self, x: usize, from: usize, to: usize, color: Color) {
+    for y in from.min(to)..=from.max(to) {
+      self.line(x, y, '│', color);
+    }
+  }
+
+  fn horizontal(&mut self, y: usize, from: usize, to: usize, color: Color) {
+    for x in from.min(to)..=from.max(to) {
+      self.line(x, y, '─', color);
+    }
+  }
+
+  // draws the segments between the points, with a corner wherever the path turns
+  fn path(&mut self, points: &[(usize, usize)], color: Color) {
+    let mut points = points.to_vec();
+    points.dedup();
+    for segment in points.windows(2) {
+      let ((x1, y1), (x2, y2)) = (segment[0], segment[1]);
+      if x1 == x2 {
+        self.vertical(x1, y1, y2, color);
+      } else {
+        self.horizontal(y1, x1, x2, color);
+      }
+    }
+    for corner in points.windows(3) {
+      let (previous, point, next) = (corner[0], corner[1], corner[2]);
+      let c = match (direction(point, previous), direction(point, next)) {
+        (Direction::Up, Direction::Right) | (Direction::Right, Direction::Up) => '└',
+        (Direction::Up, Direction::Left) | (Direction::Left, Direction::Up) => '┘',
+        (Direction::Down, Direction::Right) | (Direction::Right, Direction::Down) => '┌',
+        (Direction::Down, Direction::Left) | (Direction::Left, Direction::Down) => '┐',
+        _ => continue,
+      };
+      self.set(point.0, point.1, c, color);
+    }
+  }
+
+  pub fn lines(&self, x: usize, y: usize, width: usize, height: usize) -> Vec<Line<'static>> {
+    self
+      .cells
+      .iter()
+      .skip(y)
+      .take(height)
+      .map(|row| {
+        let spans = row
+          .iter()
+          .skip(x)
+          .take(width)
+          .map(|(c, color)| Span::styled(c.to_string(), Style::default().fg(*color)))
+          .collect::<Vec<Span>>();
+        Line::from(spans)
+      })
+      .collect()
+  }
+}
+
+#[derive(PartialEq)]
+enum Direction {
+  Up,
+  Down,
+  Left,
+  Right,
+}
+
+// which way b lies from a, the points of a path are always in a straight line
+fn direction(a: (usize, usize), b: (usize, usize)) -> Direction {
+  if b.0 > a.0 {
+    Direction::Right
+  } else if b.0 < a.0 {
+    Direction::Left
+  } else if b.1 > a.1 {
+    Direction::Down
+  } else {
+    Direction::Up
+  }
+}
+
+struct BlockBox {
+  layer: usize,
+  x: usize,
+  y: usize,
+  width: usize,
+  lines: Vec<String>,
+}
+
+impl BlockBox {
+  fn height(&self) -> usize {
+    self.lines.len() + 2
+  }
+}
+
+struct GraphEdge {
+  from: usize,
+  to: usize,
+  color: Color,
+}
+
+pub fn layout_graph(
+  cfg: &ControlFlowGraph,
+  instructions: &[InstructionData],
+  zoom: usize,
+  formatter: &mut dyn Formatter,
+) -> GraphCanvas {
+  let block_index = cfg
+    .blocks
+    .iter()
+    .enumerate()
+    .map(|(i, x)| (x.start, i))
+    .collect::<HashMap<u64, usize>>();
+
+  // only edges between blocks of this function are drawn, calls leave the function
+  let mut edges = vec![];
+  for (i, block) in cfg.blocks.iter().enumerate() {
+    let is_conditional = block.edges.iter().any(|x| x.kind == EdgeKind::Conditional);
+    for edge in &block.edges {
+      let color = match edge.kind {
+        EdgeKind::Conditional => Color::Green,
+        EdgeKind::Fallthrough if is_conditional => Color::Red,
+        EdgeKind::Fallthrough | EdgeKind::Unconditional => Color::Blue,
+        EdgeKind::Case => Color::Magenta,
+        _ => continue,
+      };
+      if let Some(to) = edge.target.and_then(|x| block_index.get(&x)) {
+        edges.push(GraphEdge {
+          from: i,
+          to: *to,
+          color,
+        });
+      }
+    }
+  }
+
+  let layers = assign_layers(cfg.blocks.len(), &edges);
+  let mut boxes = cfg
+    .blocks
+    .iter()
+    .enumerate()
+    .map(|(i, block)| {
+      let mut lines = vec![format!("loc_{:x}", block.start)];
+      let block_instructions =
+        &instructions[block.first_instruction..block.first_instruction + block.instruction_count];
+      let shown = match ZOOM_INSTRUCTIONS[zoom.min(ZOOM_LEVELS - 1)] {
+        Some(limit) => limit.min(block_instructions.len()),
+        None => block_instructions.len(),
+      };
+      for data in &block_instructions[..shown] {
+        lines.push(truncate(&analysis::format_instruction(
+          formatter,
+          &data.instr,
+        )));
+      }
+      if shown < block_instructions.len() {
+        lines.push(format!("... {} more", block_instructions.len() - shown));
+      }
+      BlockBox {
+        layer: layers[i],
+        x: 0,
+        y: 0,
+        width: lines.iter().map(|x| x.chars().count()).max().unwrap_or(0) + 2,
+        lines,
+      }
+    })
+    .collect::<Vec<BlockBox>>();
+
+  let layer_count = layers.iter().max().map(|x| x + 1).unwrap_or(0);
+  let mut layer_members: Vec<Vec<usize>> = vec![vec![]; layer_count];
+  for (i, layer) in layers.iter().enumerate() {
+    layer_members[*layer].push(i);
+  }
+
+  // edges that don't go to the next layer are routed around the boxes through a lane on the right
+  let is_long = |edge: &GraphEdge| layers[edge.to] != layers[edge.from] + 1;
+  let mut gap_tracks = vec![0; layer_count];
+  let mut edge_tracks = vec![];
+  for edge in &edges {
+    let out_track = gap_tracks[layers[edge.from]];
+    gap_tracks[layers[edge.from]] += 1;
+    let in_track = if is_long(edge) && layers[edge.to] > 0 {
+      let gap = layers[edge.to] - 1;
+      gap_tracks[gap] += 1;
+      Some((gap, gap_tracks[gap] - 1))
+    } else {
+      None
+    };
+    edge_tracks.push((out_track, in_track));
+  }
+
+  let layer_widths = layer_members
+    .iter()
+    .map(|members| {
+      members.iter().map(|x| boxes[*x].width).sum::<usize>()
+        + members.len().saturating_sub(1) * BOX_SPACING
+    })
+    .collect::<Vec<usize>>();
+  let graph_width = layer_widths.iter().max().copied().unwrap_or(0);
+
+  // loops back to the entry block need a row above it
+  let mut gap_starts = vec![0; layer_count];
+  let mut y = MARGIN + 2;
+  for (layer, members) in layer_members.iter().enumerate() {
+    let x_start = MARGIN + (graph_width - layer_widths[layer]) / 2;
+    let mut x = x_start;
+    for member in members {
+      boxes[*member].x = x;
+      boxes[*member].y = y;
+      x += boxes[*member].width + BOX_SPACING;
+    }
+    let layer_height = members
+      .iter()
+      .map(|x| boxes[*x].height())
+      .max()
+      .unwrap_or(0);
+    // a row for the lines leaving the boxes, one per track and one for the arrows
+    gap_starts[layer] = y + layer_height + 1;
+    y += layer_height + gap_tracks[layer] + 3;
+  }
+
+  let long_edges = edges.iter().filter(|x| is_long(x)).count();
+  let lanes_start = MARGIN + graph_width + BOX_SPACING;
+  let mut canvas = GraphCanvas::new(lanes_start + long_edges * 2 + MARGIN, y + MARGIN);
+
+  for block in &boxes {
+    draw_box(&mut canvas, block);
+  }
+
+  let mut lane = 0;
+  let mut outgoing = vec![0; boxes.len()];
+  let mut incoming = vec![0; boxes.len()];
+  let out_counts = count(&edges, |x| x.from, boxes.len());
+  let in_counts = count(&edges, |x| x.to, boxes.len());
+  for (edge, (out_track, in_track)) in edges.iter().zip(edge_tracks) {
+    let from = &boxes[edge.from];
+    let to = &boxes[edge.to];
+    let from_x = from.x + from.width * (outgoing[edge.from] + 1) / (out_counts[edge.from] + 1);
+    let to_x = to.x + to.width * (incoming[edge.to] + 1) / (in_counts[edge.to] + 1);
+    outgoing[edge.from] += 1;
+    incoming[edge.to] += 1;
+
+    let track_y = gap_starts[from.layer] + out_track;
+    let mut points = vec![(from_x, from.y + from.height()), (from_x, track_y)];
+    if is_long(edge) {
+      let lane_x = lanes_start + lane * 2;
+      lane += 1;
+      let in_y = match in_track {
+        Some((gap, track)) => gap_starts[gap] + track,
+        None => MARGIN,
+      };
+      points.extend_from_slice(&[(lane_x, track_y), (lane_x, in_y), (to_x, in_y)]);
+    } else {
+      points.push((to_x, track_y));
+    }
+    points.push((to_x, to.y - 1));
+    canvas.path(&points, edge.color);
+    canvas.set(to_x, to.y - 1, '▼', edge.color);
+  }
+
+  canvas
+}
+
+fn count(edges: &[GraphEdge], key: impl Fn(&GraphEdge) -> usize, len: usize) -> Vec<usize> {
+  let mut counts = vec![0; len];
+  for edge in edges {
+    counts[key(edge)] += 1;
+  }
+  counts
+}
+
+fn truncate(line: &str) -> String {
+  if line.chars().count() > MAX_LINE_WIDTH {
+    let line = line.chars().take(MAX_LINE_WIDTH - 2).collect::<String>();
+    format!("{}..", line)
+  } else {
+    line.to_owned()
+  }
+}
+
+fn draw_box(canvas: &mut GraphCanvas, block: &BlockBox) {
+  let right = block.x + block.width - 1;
+  let bottom = block.y + block.height() - 1;
+  canvas.set(block.x, block.y, '┌', Color::White);
+  canvas.set(right, block.y, '┐', Color::White);
+  canvas.set(block.x, bottom, '└', Color::White);
+  canvas.set(right, bottom, '┘', Color::White);
+  for x in block.x + 1..right {
+    canvas.set(x, block.y, '─', Color::White);
+    canvas.set(x, bottom, '─', Color::White);
+  }
+  for y in block.y + 1..bottom {
+    canvas.set(block.x, y, '│', Color::White);
+    canvas.set(right, y, '│', Color::White);
+  }
+  for (i, line) in block.lines.iter().enumerate() {
+    let color = if i == 0 { Color::Green } else { Color::Yellow };
+    canvas.text(block.x + 1, block.y + 1 + i, line, color);
+  }
+}
+
+// Longest path from the entry block, ignoring the edges that close loops
+fn assign_layers(block_count: usize, edges: &[GraphEdge]) -> Vec<usize> {
+  let mut successors: Vec<Vec<usize>> = vec![vec![]; block_count];
+  for edge in edges {
+    successors[edge.from].push(edge.to);
+  }
+
+  // depth first order, an edge back to a block still on the stack is a loop
+  let mut order = vec![];
+  let mut state = vec![0u8; block_count]; // 0 unvisited, 1 on stack, 2 done
+  let mut forward: Vec<Vec<usize>> = vec![vec![]; block_count];
+  for root in 0..block_count {
+    if state[root] != 0 {
+      continue;
+    }
+    let mut stack = vec![(root, 0)];
+    state[root] = 1;
+    while let Some((node, next)) = stack.pop() {
+      if let Some(&successor) = successors[node].get(next) {
+        stack.push((node, next + 1));
+        match state[successor] {
+          0 => {
+            forward[node].push(successor);
+            state[successor] = 1;
+            stack.push((successor, 0));
+          }
+          2 => forward[node].push(successor),
+          _ => {}
+        }
+      } else {
+        state[node] = 2;
+        order.push(node);
+      }
+    }
+  }
+
+  let mut layers = vec![0; block_count];
+  for node in order.iter().rev() {
+    for successor in &forward[*node] {
+      layers[*successor] = layers[*successor].max(layers[*node] + 1);
+    }
+  }
+  layers
+}
@@ -0,0 +1,131 @@
+use crate::parser::{FileRegion, FileRegionKind};
+use crate::tui::App;
+use ratatui::{prelude::*, widgets::*};
+
+pub const BYTES_PER_ROW: usize = 16;
+const SECTION_COLORS: [Color; 4] = [
+  Color::Green,
+  Color::Blue,
+  Color::LightGreen,
+  Color::LightBlue,
+];
+
+// The whole file, colored by the structure each byte belongs to
+pub fn render_hex(f: &mut Frame, app: &mut App, area: Rect) {
+  let bytes = &app.data.bytes;
+  let regions = &app.file_regions;
+  let height = area.height.saturating_sub(2) as usize;
+
+  // keep the cursor on screen
+  let cursor_row = app.hex_cursor / BYTES_PER_ROW;
+  if cursor_row < app.hex_scroll {
+    app.hex_scroll = cursor_row;
+  } else if height > 0 && cursor_row >= app.hex_scroll + height {
+    app.hex_scroll = cursor_row + 1 - height;
+  }
+
+  let lines = bytes
+    .chunks(BYTES_PER_ROW)
+    .enumerate()
+    .skip(app.hex_scroll)
+    .take(height)
+    .map(|(row, chunk)| {
+      let row_offset = row * BYTES_PER_ROW;
+      let mut hex = vec![format!("{:08x}  ", row_offset).dark_gray()];
+      let mut ascii = vec![" ".into()];
+      for (i, b) in chunk.iter().enumerate() {
+        let offset = row_offset + i;
+        let mut style = Style::default().fg(region_color(regions, offset));
+        if app.is_patched(offset) {
+          style = style.bold().underlined();
+        }
+        if offset == app.hex_cursor {
+          style = style.reversed();
+        }
+        hex.push(Span::styled(format!("{:02x}", b), style));
+        hex.push(if i == BYTES_PER_ROW / 2 - 1 {
+          "  ".into()
+        } else {
+          " ".into()
+        });
+        let c = if b.is_ascii_graphic() || *b == b' ' {
+          *b as char
+        } else {
+          '.'
+        };
+        ascii.push(Span::styled(c.to_string(), style));
+      }
+      // short last row, pad so the text column lines up
+      let missing = BYTES_PER_ROW - chunk.len();
+      if missing > 0 {
+        let pad = missing * 3 + usize::from(chunk.len() < BYTES_PER_ROW / 2);
+        hex.push(" ".repeat(pad).into());
+      }
+      hex.extend(ascii);
+      Line::from(hex)
+    })
+    .collect::<Vec<Line>>();
+
+  let title = match regions.iter().find(|x| x.range.contains(&app.hex_cursor)) {
+    Some(region) => cursor_title(app, region),
+    None => format!(" {:#x} ", app.hex_cursor),
+  };
+  let mut legend = vec![" ".into()];
+  for (i, region) in regions.iter().enumerate() {
+    legend.push(Span::styled(
+      region.name.clone(),
+      Style::default().fg(region_color_at(regions, i)),
+    ));
+    legend.push(" ".into());
+  }
+
+  let p = Paragraph::new(lines)
+    .block(
+      Block::default()
+        .title(title)
+        .title(block::Title::from(Line::from(legend)).position(block::Position::Bottom))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::White))
+        .padding(Padding::new(1, 0, 0, 0)),
+    )
+    .white();
+  f.render_widget(p, area);
+}
+
+// Offset of the cursor in the structure, and the RVA it is loaded at for sections
+fn cursor_title(app: &App, region: &FileRegion) -> String {
+  let delta = app.hex_cursor - region.range.start;
+  let rva = match region.kind {
+    FileRegionKind::Section => app
+      .data
+      .section_table
+      .iter()
+      .find_map(|x| x.offset_to_rva(u32::try_from(app.hex_cursor).ok()?))
+      .map(|x| format!(", rva {:#x}", x))
+      .unwrap_or_default(),
+    _ => String::new(),
+  };
+  format!(
+    " {:#x}  {}+{:#x}{} ",
+    app.hex_cursor, region.name, delta, rva
+  )
+}
+
+fn region_color(regions: &[FileRegion], offset: usize) -> Color {
+  match regions.iter().position(|x| x.range.contains(&offset)) {
+    Some(i) => region_color_at(regions, i),
+    None => Color::DarkGray,
+  }
+}
+
+fn region_color_at(regions: &[FileRegion], index: usize) -> Color {
+  match regions[index].kind {
+    FileRegionKind::DosHeader => Color::Red,
+    FileRegionKind::DosStub => Color::Magenta,
+    FileRegionKind::NtHeaders => Color::Yellow,
+    FileRegionKind::SectionTable => Color::Cyan,
+    // neighbouring sections get different colors
+    FileRegionKind::Section => SECTION_COLORS[index % SECTION_COLORS.len()],
+    FileRegionKind::Overlay => Color::LightRed,
+  }
+}
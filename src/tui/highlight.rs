@@ -0,0 +1,67 @@
+use iced_x86::{FlowControl, Formatter, FormatterOutput, FormatterTextKind, Instruction};
+use ratatui::prelude::*;
+
+// Collects the formatter's output as spans, styled by what each piece of text is
+struct SpanOutput {
+  flow_control: FlowControl,
+  spans: Vec<Span<'static>>,
+}
+
+impl FormatterOutput for SpanOutput {
+  fn write(&mut self, text: &str, kind: FormatterTextKind) {
+    let style = text_style(kind, self.flow_control);
+    // the formatter writes punctuation one character at a time, keep the span count down
+    match self.spans.last_mut() {
+      Some(last) if last.style == style => last.content.to_mut().push_str(text),
+      _ => self.spans.push(Span::styled(text.to_owned(), style)),
+    }
+  }
+}
+
+pub fn highlight_instruction(
+  formatter: &mut dyn Formatter,
+  instr: &Instruction,
+) -> Vec<Span<'static>> {
+  let mut output = SpanOutput {
+    flow_control: instr.flow_control(),
+    spans: vec![],
+  };
+  formatter.format(instr, &mut output);
+  output.spans
+}
+
+fn text_style(kind: FormatterTextKind, flow_control: FlowControl) -> Style {
+  match kind {
+    FormatterTextKind::Mnemonic => mnemonic_style(flow_control),
+    FormatterTextKind::Prefix => Style::default().fg(Color::Magenta),
+    FormatterTextKind::Keyword => Style::default().fg(Color::Gray),
+    FormatterTextKind::Register => Style::default().fg(Color::LightBlue),
+    FormatterTextKind::Number => Style::default().fg(Color::LightGreen),
+    FormatterTextKind::Function => Style::default().fg(Color::LightMagenta),
+    FormatterTextKind::Label => Style::default().fg(Color::LightCyan),
+    FormatterTextKind::Data => Style::default().fg(Color::LightYellow),
+    FormatterTextKind::Directive => Style::default().fg(Color::DarkGray),
+    FormatterTextKind::Decorator | FormatterTextKind::SelectorValue => {
+      Style::default().fg(Color::Cyan)
+    }
+    _ => Style::default().fg(Color::White),
+  }
+}
+
+// Control flow is what the eye looks for first, so those mnemonics are bold and colored by kind
+fn mnemonic_style(flow_control: FlowControl) -> Style {
+  match flow_control {
+    FlowControl::Next => Style::default().fg(Color::Yellow),
+    FlowControl::UnconditionalBranch | FlowControl::IndirectBranch => {
+      Style::default().fg(Color::LightCyan).bold()
+    }
+    FlowControl::ConditionalBranch => Style::default().fg(Color::Cyan).bold(),
+    FlowControl::Call | FlowControl::IndirectCall => {
+      Style::default().fg(Color::LightMagenta).bold()
+    }
+    FlowControl::Return => Style::default().fg(Color::LightRed).bold(),
+    FlowControl::Interrupt | FlowControl::XbeginXabortXend | FlowControl::Exception => {
+      Style::default().fg(Color::Red).bold()
+    }
+  }
+}
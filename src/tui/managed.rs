@@ -0,0 +1,261 @@
+use crate::parser::{CilMethodBody, ClrData, ClrFlags};
+use crate::tui::{util_hex, HeaderKeyValue};
+use ratatui::prelude::*;
+
+// TypeDef flags, ECMA-335 II.23.1.15
+const TYPE_ATTRIBUTE_INTERFACE: u32 = 0x20;
+const TYPE_ATTRIBUTE_ABSTRACT: u32 = 0x80;
+
+// MethodDef flags, ECMA-335 II.23.1.10
+const METHOD_ATTRIBUTE_STATIC: u16 = 0x10;
+const METHOD_ATTRIBUTE_VIRTUAL: u16 = 0x40;
+const METHOD_ATTRIBUTE_ABSTRACT: u16 = 0x400;
+
+// longer encodings (switch tables) are cut short, the hex pane has the full bytes
+const CIL_BYTES_SHOWN: usize = 6;
+
+pub fn clr_header_values(clr: &ClrData) -> Vec<HeaderKeyValue> {
+  let header = &clr.header;
+  let directory = |rva: u32, size: u32| format!("{} ({} bytes)", util_hex(&rva), size);
+  let mut lines = vec![
+    HeaderKeyValue {
+      key: "cb".to_owned(),
+      value: util_hex(&header.cb),
+    },
+    HeaderKeyValue {
+      key: "runtime_version".to_owned(),
+      value: format!(
+        "{}.{}",
+        header.major_runtime_version, header.minor_runtime_version
+      ),
+    },
+    HeaderKeyValue {
+      key: "metadata".to_owned(),
+      value: directory(header.metadata_rva, header.metadata_size),
+    },
+    HeaderKeyValue {
+      key: "metadata_version".to_owned(),
+      value: clr.metadata_version.clone(),
+    },
+    HeaderKeyValue {
+      key: "flags".to_owned(),
+      value: header
+        .flags
+        .iter()
+        .map(|x| {
+          let str: &str = x.into();
+          str.to_owned()
+        })
+        .collect::<Vec<String>>()
+        .join(", "),
+    },
+  ];
+
+  let entry_point = if header
+    .flags
+    .contains(&ClrFlags::COMIMAGE_FLAGS_NATIVE_ENTRYPOINT)
+  {
+    format!("native {}", util_hex(&header.entry_point_token))
+  } else {
+    match clr
+      .methods
+      .iter()
+      .find(|x| x.token == header.entry_point_token)
+    {
+      Some(method) => format!("{:#010x} {}", method.token, method.name),
+      None => format!("{:#010x}", header.entry_point_token),
+    }
+  };
+  lines.push(HeaderKeyValue {
+    key: "entry_point".to_owned(),
+    value: entry_point,
+  });
+  lines.push(HeaderKeyValue {
+    key: "resources".to_owned(),
+    value: directory(header.resources_rva, header.resources_size),
+  });
+  lines.push(HeaderKeyValue {
+    key: "strong_name_signature".to_owned(),
+    value: directory(
+      header.strong_name_signature_rva,
+      header.strong_name_signature_size,
+    ),
+  });
+  lines.push(HeaderKeyValue {
+    key: "code_manager_table".to_owned(),
+    value: directory(
+      header.code_manager_table_rva,
+      header.code_manager_table_size,
+    ),
+  });
+  lines.push(HeaderKeyValue {
+    key: "vtable_fixups".to_owned(),
+    value: directory(header.vtable_fixups_rva, header.vtable_fixups_size),
+  });
+  lines.push(HeaderKeyValue {
+    key: "export_address_table_jumps".to_owned(),
+    value: directory(
+      header.export_address_table_jumps_rva,
+      header.export_address_table_jumps_size,
+    ),
+  });
+  lines.push(HeaderKeyValue {
+    key: "managed_native_header".to_owned(),
+    value: directory(
+      header.managed_native_header_rva,
+      header.managed_native_header_size,
+    ),
+  });
+  lines.push(HeaderKeyValue {
+    key: "streams".to_owned(),
+    value: clr
+      .streams
+      .iter()
+      .map(|x| format!("{} ({} bytes at {})", x.name, x.size, util_hex(&x.offset)))
+      .collect::<Vec<String>>()
+      .join(", "),
+  });
+
+  lines
+}
+
+pub fn generate_managed_lines(clr: &ClrData) -> Vec<Line<'static>> {
+  let mut lines = vec![Line::from(vec!["Types".yellow()])];
+  for managed_type in &clr.types {
+    let kind = if managed_type.flags & TYPE_ATTRIBUTE_INTERFACE != 0 {
+      "interface"
+    } else if managed_type.flags & TYPE_ATTRIBUTE_ABSTRACT != 0 {
+      "abstract class"
+    } else {
+      "class"
+    };
+
+    let mut line_parts = vec![];
+    line_parts.push(" ".into());
+    line_parts.push(format!("{:#010x}", managed_type.token).green());
+    line_parts.push(format!(" {} ", kind).white());
+    line_parts.push(managed_type.full_name().yellow());
+    if !managed_type.extends.is_empty() {
+      line_parts.push(format!(" : {}", managed_type.extends).white());
+    }
+    lines.push(Line::from(line_parts));
+
+    for method in managed_type
+      .methods
+      .iter()
+      .filter_map(|x| clr.methods.get(*x))
+    {
+      let mut modifiers = vec![];
+      if method.flags & METHOD_ATTRIBUTE_STATIC != 0 {
+        modifiers.push("static");
+      }
+      if method.flags & METHOD_ATTRIBUTE_VIRTUAL != 0 {
+        modifiers.push("virtual");
+      }
+      if method.flags & METHOD_ATTRIBUTE_ABSTRACT != 0 {
+        modifiers.push("abstract");
+      }
+
+      let mut line_parts = vec![];
+      line_parts.push("   ".into());
+      line_parts.push(format!("{:#010x}", method.token).green());
+      line_parts.push(format!(" rva {:#8x} ", method.rva).white());
+      if !modifiers.is_empty() {
+        line_parts.push(format!("{} ", modifiers.join(" ")).white());
+      }
+      line_parts.push(method.name.clone().yellow());
+      lines.push(Line::from(line_parts));
+    }
+  }
+
+  lines.push(Line::from(vec!["  ".into()]));
+  lines.push(Line::from(vec!["Member References".yellow()]));
+  for member_ref in &clr.member_refs {
+    lines.push(Line::from(vec![
+      " ".into(),
+      format!("{:#010x}", member_ref.token).green(),
+      " ".into(),
+      format!("{}::{}", member_ref.parent, member_ref.name).white(),
+    ]));
+  }
+
+  lines.push(Line::from(vec!["  ".into()]));
+  lines.push(Line::from(vec!["Assembly References".yellow()]));
+  for assembly_ref in &clr.assembly_refs {
+    let culture = match assembly_ref.culture.as_str() {
+      "" => "neutral",
+      culture => culture,
+    };
+    lines.push(Line::from(vec![
+      " ".into(),
+      format!("{:#010x}", assembly_ref.token).green(),
+      " ".into(),
+      assembly_ref.name.clone().yellow(),
+      format!(
+        " {}.{}.{}.{} culture={}",
+        assembly_ref.major_version,
+        assembly_ref.minor_version,
+        assembly_ref.build_number,
+        assembly_ref.revision_number,
+        culture
+      )
+      .white(),
+    ]));
+  }
+
+  lines
+}
+
+// A row of the IL listing, offset and size locate the highlighted bytes in the hex pane
+pub struct CilLine {
+  pub offset: usize,
+  pub size: usize,
+  pub line: Line<'static>,
+}
+
+pub fn generate_cil_lines(bodies: &[CilMethodBody]) -> Vec<CilLine> {
+  let mut lines = Vec::new();
+  for body in bodies {
+    let mut header = format!(
+      "; {:#010x} {} maxstack {} codesize {}",
+      body.token, body.name, body.max_stack, body.code_size
+    );
+    if body.local_var_sig_tok != 0 {
+      header.push_str(&format!(" locals {:#010x}", body.local_var_sig_tok));
+    }
+    lines.push(CilLine {
+      offset: body.offset,
+      size: body.header_size,
+      line: Line::from(vec![
+        format!("{:#8x}", body.offset).green(),
+        "  ".into(),
+        header.cyan(),
+      ]),
+    });
+
+    for instr in &body.instructions {
+      let mut bytes = instr
+        .bytes
+        .iter()
+        .take(CIL_BYTES_SHOWN)
+        .map(|x| format!("{:02x}", x))
+        .collect::<Vec<String>>()
+        .join(" ");
+      if instr.bytes.len() > CIL_BYTES_SHOWN {
+        bytes.push_str(" ..");
+      }
+      lines.push(CilLine {
+        offset: instr.offset,
+        size: instr.size,
+        line: Line::from(vec![
+          format!("{:#8x}", instr.offset).green(),
+          "  ".into(),
+          format!("{:<18}", bytes).white(),
+          format!("{} ", instr.mnemonic).yellow(),
+          instr.operand.clone().white(),
+        ]),
+      });
+    }
+  }
+  lines
+}
@@ -1,4 +1,11 @@
-use crate::parser::{CommonOptionalHeaderFields, OptionalHeader, PEFile};
+use crate::analysis::{
+  self, DataString, EntropyReport, ExtractedString, FormatOptions, Function, SymbolTable, XrefIndex,
+};
+use crate::parser::{
+  self, CommonOptionalHeaderFields, DOSHeader, DisassemblyMode, DosStubProgram, FileRegion,
+  OptionalHeader, PEFile, RichHeader, SectionData, StaleBindingReason,
+};
+use crate::project::Project;
 use crossterm::event::EnableMouseCapture;
 use crossterm::{
   event::{self, KeyCode, KeyEventKind},
@@ -6,6 +13,7 @@ use crossterm::{
   terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
   ExecutableCommand,
 };
+use iced_x86::Formatter;
 use ratatui::layout::{Constraint, Direction, Layout};
 use ratatui::{prelude::*, widgets::*};
 use ratatui::{
@@ -13,14 +21,46 @@ use ratatui::{
   widgets::Paragraph,
   Frame,
 };
+use std::collections::BTreeMap;
 use std::fmt::LowerHex;
-use std::io::stdout;
+use std::io::{stdout, Write};
 use strum::EnumIter;
 
+mod annotate;
+mod details;
+mod entropy;
+mod functions;
+mod goto;
+mod graph;
+mod hex;
+mod highlight;
+mod managed;
+mod patch;
+mod search;
+mod strings;
+mod xrefs;
+
+const GRAPH_HORIZONTAL_STEP: usize = 4;
+const COMMENT_WIDTH: usize = 64;
+const HISTORY_LIMIT: usize = 256;
+const HEX_PAGE_ROWS: usize = 32;
+const MAX_STRING_LENGTH_SETTING: usize = 64;
+
+// Which pane of the disassembly tab receives the arrow keys
+#[derive(Debug, Clone, PartialEq)]
+enum Focus {
+  Listing,
+  Functions,
+}
+
 #[derive(Debug, EnumIter, Clone, PartialEq)]
 enum Tab {
   Disassembly,
   Headers,
+  Hex,
+  Strings,
+  Entropy,
+  Managed,
 }
 
 impl Into<String> for &Tab {
@@ -28,6 +68,10 @@ impl Into<String> for &Tab {
     match self {
       Tab::Disassembly => "Disassembly".to_owned(),
       Tab::Headers => "Headers".to_owned(),
+      Tab::Hex => "Hex".to_owned(),
+      Tab::Strings => "Strings".to_owned(),
+      Tab::Entropy => "Entropy".to_owned(),
+      Tab::Managed => "Managed".to_owned(),
     }
   }
 }
@@ -39,6 +83,46 @@ struct App {
   data_scroll: usize,
   header_scroll: usize,
   header_lines: Vec<Line<'static>>,
+  managed_scroll: usize,
+  managed_lines: Vec<Line<'static>>,
+  cil_lines: Vec<managed::CilLine>,
+  functions: Vec<Function>,
+  function_selected: usize,
+  focus: Focus,
+  graph: Option<graph::GraphCanvas>, // Set while the graph view of the current function is shown
+  graph_title: String,
+  graph_zoom: usize,
+  graph_scroll_x: usize,
+  graph_scroll_y: usize,
+  xrefs: XrefIndex,
+  symbols: SymbolTable,
+  strings: BTreeMap<u64, DataString>, // Strings the code points at, shown as comments
+  format_options: FormatOptions,
+  formatter: Box<dyn Formatter>, // Names addresses from the symbol table
+  xref_list: Option<xrefs::XrefList>, // Set while the references popup is open
+  show_details: bool,            // Instruction details pane under the listing
+  goto_prompt: Option<goto::GotoPrompt>, // Set while g is reading an address, takes every key
+  back_history: Vec<usize>, // Listing positions jumped away from, the last one is the most recent
+  forward_history: Vec<usize>, // Positions gone back from, cleared by a new jump
+  search_prompt: Option<search::SearchPrompt>, // Set while / is reading a query, takes every key
+  search: Option<search::Search>, // The last search, highlighted until replaced
+  file_regions: Vec<FileRegion>,
+  hex_cursor: usize,                        // File offset selected in the hex tab
+  hex_scroll: usize,                        // First row shown, follows the cursor
+  file_path: String, // The file that was opened, patched copies are written next to it
+  patch_prompt: Option<patch::PatchPrompt>, // Set while p or w is reading input, takes every key
+  patches: Vec<patch::Patch>, // Undo list, the last one is the most recent
+  status: Option<String>, // Outcome of the last command, cleared by the next key
+  project: Project,  // Comments, labels and bookmarks saved for this binary
+  annotation_prompt: Option<annotate::AnnotationPrompt>, // Set while ; or l is reading text, takes every key
+  extracted_strings: Vec<ExtractedString>, // Every string in the file, for the strings tab
+  string_min_length: usize,
+  string_filter: String,
+  string_filter_open: bool, // Set while / in the strings tab is reading the filter, takes every key
+  string_rows: Vec<usize>,  // Indices into extracted_strings that pass the filter
+  string_selected: usize,   // Row of string_rows
+  string_scroll: usize,
+  entropy: EntropyReport,
 }
 
 fn get_common_values(data: &CommonOptionalHeaderFields) -> Vec<HeaderKeyValue> {
@@ -82,15 +166,116 @@ fn get_common_values(data: &CommonOptionalHeaderFields) -> Vec<HeaderKeyValue> {
 }
 
 impl App {
-  fn new(data: PEFile) -> Self {
+  fn new(
+    data: PEFile,
+    file_path: &str,
+    format_options: FormatOptions,
+    string_min_length: usize,
+  ) -> Self {
+    let mut tabs = vec![
+      Tab::Disassembly,
+      Tab::Headers,
+      Tab::Hex,
+      Tab::Strings,
+      Tab::Entropy,
+    ];
+    let (managed_lines, cil_lines) = match &data.clr {
+      Some(clr) => {
+        tabs.push(Tab::Managed);
+        (
+          managed::generate_managed_lines(clr),
+          managed::generate_cil_lines(&clr.method_bodies),
+        )
+      }
+      None => (vec![], vec![]),
+    };
+    let functions = analysis::discover_functions(&data);
+    let xrefs = analysis::build_xrefs(&data);
+    // the project belongs to the binary as it was opened, patches don't move it. A project file
+    // that can't be read is moved out of the way of the next save
+    let (project, status) = match Project::load(&data.bytes) {
+      Ok(project) => (project, None),
+      Err(e) => {
+        let mut project = Project::empty(&data.bytes);
+        let status = match project.set_aside() {
+          Ok(backup) => format!(
+            "Can't read the project file, moved it to {}: {}",
+            backup.display(),
+            e
+          ),
+          Err(_) => {
+            project.read_only = true;
+            format!("Can't read the project file, changes won't be saved: {}", e)
+          }
+        };
+        (project, Some(status))
+      }
+    };
+    let symbols = analysis::build_symbols(&data, &functions).with_labels(&project.labels);
+    let strings = analysis::build_string_refs(&data, &xrefs);
+    let formatter = analysis::build_formatter(&symbols, &format_options);
+    let entropy = analysis::build_entropy(&data);
+
     let mut temp = App {
-      tabs: vec![Tab::Disassembly, Tab::Headers],
+      tabs,
       active_tab: Tab::Disassembly,
       data,
       data_scroll: 0,
       header_scroll: 0,
       header_lines: vec![],
+      managed_scroll: 0,
+      managed_lines,
+      cil_lines,
+      functions,
+      function_selected: 0,
+      focus: Focus::Listing,
+      graph: None,
+      graph_title: String::new(),
+      graph_zoom: 0,
+      graph_scroll_x: 0,
+      graph_scroll_y: 0,
+      xrefs,
+      symbols,
+      strings,
+      format_options,
+      formatter,
+      xref_list: None,
+      show_details: false,
+      goto_prompt: None,
+      back_history: vec![],
+      forward_history: vec![],
+      search_prompt: None,
+      search: None,
+      file_regions: vec![],
+      hex_cursor: 0,
+      hex_scroll: 0,
+      file_path: file_path.to_owned(),
+      patch_prompt: None,
+      patches: vec![],
+      status,
+      project,
+      annotation_prompt: None,
+      extracted_strings: vec![],
+      string_min_length,
+      string_filter: String::new(),
+      string_filter_open: false,
+      string_rows: vec![],
+      string_selected: 0,
+      string_scroll: 0,
+      entropy,
     };
+    temp.file_regions = temp.data.file_regions();
+    temp.extract_strings();
+    // pick up where the last session left off
+    if let Some(address) = temp.project.last_address {
+      temp.data_scroll =
+        analysis::containing_instruction_index(&temp.data.text_section.data, address)
+          .unwrap_or_default();
+    }
+    temp.hex_cursor = temp
+      .project
+      .last_hex_offset
+      .min(temp.data.bytes.len().saturating_sub(1));
     temp.generate_headers_lines();
     temp
   }
@@ -208,6 +393,21 @@ impl App {
 
     lines.extend_from_slice(&dos_lines);
 
+    // DOS Stub
+    lines.push(Line::from(vec!["  ".into()]));
+    lines.push(Line::from(vec!["DOS Stub".yellow()]));
+    lines.extend_from_slice(&dos_stub_lines(
+      &app.data.headers.dos_header,
+      &app.data.dos_stub_program,
+    ));
+
+    // Rich Header
+    if let Some(rich_header) = &app.data.rich_header {
+      lines.push(Line::from(vec!["  ".into()]));
+      lines.push(Line::from(vec!["Rich Header".yellow()]));
+      lines.extend_from_slice(&rich_header_lines(rich_header));
+    }
+
     // NT Headers
     lines.push(Line::from(vec!["  ".into()]));
     lines.push(Line::from(vec!["NT Headers".yellow()]));
@@ -234,15 +434,9 @@ impl App {
       value: util_hex(&app.data.headers.nt_headers.file_header.number_of_sections),
     });
 
-    let time_date_stamp = app.data.headers.nt_headers.file_header.time_date_stamp;
-    let time_date_stamp = match chrono::NaiveDateTime::from_timestamp_opt(time_date_stamp as i64, 0)
-    {
-      Some(x) => x.format("%Y-%m-%d %H:%M:%S").to_string(),
-      None => "Not a valid timestamp".to_owned(),
-    };
     nt_lines.push(HeaderKeyValue {
       key: "time_date_stamp".to_owned(),
-      value: time_date_stamp,
+      value: util_timestamp(app.data.headers.nt_headers.file_header.time_date_stamp),
     });
     nt_lines.push(HeaderKeyValue {
       key: "pointer_to_symbol_table".to_owned(),
@@ -287,6 +481,13 @@ impl App {
         .collect::<Vec<String>>()
         .join(", "),
     });
+    nt_lines.push(HeaderKeyValue {
+      key: "managed".to_owned(),
+      value: match &app.data.clr {
+        Some(clr) => format!(".NET ({})", clr.metadata_version),
+        None => "no".to_owned(),
+      },
+    });
 
     //  Optional Header
     let nt_optional_header_lines = match &app.data.headers.nt_headers.optional_header {
@@ -550,6 +751,121 @@ impl App {
         .collect::<Vec<Line>>(),
     );
 
+    // Bound Imports
+    let bound_imports = &app.data.bound_imports;
+    if !bound_imports.descriptors.is_empty() {
+      lines.push(Line::from(vec!["  ".into()]));
+      lines.push(Line::from(vec!["Bound Imports".yellow()]));
+      if !bound_imports.stale_reasons.is_empty() {
+        lines.push(Line::from(vec![
+          " ".into(),
+          format!(
+            "All stale: {}",
+            stale_reason_list(&bound_imports.stale_reasons)
+          )
+          .red(),
+        ]));
+      }
+
+      for descriptor in &bound_imports.descriptors {
+        lines.push(bound_import_line(
+          " ",
+          &descriptor.module_name,
+          descriptor.time_date_stamp,
+          &descriptor.stale_reasons,
+        ));
+        for forwarder in &descriptor.forwarder_refs {
+          lines.push(bound_import_line(
+            "   -> ",
+            &forwarder.module_name,
+            forwarder.time_date_stamp,
+            &forwarder.stale_reasons,
+          ));
+        }
+      }
+    }
+
+    // Exports
+    if !app.data.exports.is_empty() {
+      lines.push(Line::from(vec!["  ".into()]));
+      lines.push(Line::from(vec!["Exports".yellow()]));
+      for export in &app.data.exports {
+        let mut line_parts = vec![
+          " ".into(),
+          format!("{:<5} ", export.ordinal).white(),
+          format!("{:#010x} ", export.rva).green(),
+          export.name.clone().unwrap_or_default().yellow(),
+        ];
+        if let Some(forwarder) = &export.forwarder {
+          line_parts.push(format!(" -> {}", forwarder).white());
+        }
+        lines.push(Line::from(line_parts));
+      }
+    }
+
+    // Imports
+    if !app.data.imports.is_empty() {
+      lines.push(Line::from(vec!["  ".into()]));
+      lines.push(Line::from(vec!["Imports".yellow()]));
+      for import in &app.data.imports {
+        lines.push(Line::from(vec![
+          " ".into(),
+          format!("{:#010x} ", import.iat_rva).green(),
+          import.display_name().yellow(),
+        ]));
+      }
+    }
+
+    // CPUID features the code needs, the rarer ones at the bottom are usually what matters
+    let cpu_features = analysis::build_cpu_features(&app.data.text_section);
+    if !cpu_features.is_empty() {
+      lines.push(Line::from(vec!["  ".into()]));
+      lines.push(Line::from(vec!["CPU Features".yellow()]));
+      for feature in &cpu_features {
+        let addresses = feature
+          .addresses
+          .iter()
+          .map(util_hex)
+          .collect::<Vec<String>>()
+          .join(", ");
+        lines.push(Line::from(vec![
+          " ".into(),
+          format!("{:<16}", feature.feature).yellow(),
+          format!("{:>8} ", feature.count).green(),
+          addresses.white(),
+        ]));
+      }
+    }
+
+    // Code entry points other than address_of_entry_point
+    if !app.data.tls_callbacks.is_empty() || !app.data.runtime_functions.is_empty() {
+      lines.push(Line::from(vec!["  ".into()]));
+      lines.push(Line::from(vec!["Code Entry Points".yellow()]));
+      lines.extend_from_slice(&header_key_value_lines(&[
+        HeaderKeyValue {
+          key: "tls_callbacks".to_owned(),
+          value: app
+            .data
+            .tls_callbacks
+            .iter()
+            .map(util_hex)
+            .collect::<Vec<String>>()
+            .join(", "),
+        },
+        HeaderKeyValue {
+          key: "runtime_functions".to_owned(),
+          value: app.data.runtime_functions.len().to_string(),
+        },
+      ]));
+    }
+
+    // CLR Header
+    if let Some(clr) = &app.data.clr {
+      lines.push(Line::from(vec!["  ".into()]));
+      lines.push(Line::from(vec!["CLR Header".yellow()]));
+      lines.extend_from_slice(&header_key_value_lines(&managed::clr_header_values(clr)));
+    }
+
     self.header_lines = lines;
   }
 
@@ -564,21 +880,865 @@ impl App {
       .clone();
   }
 
+  // managed binaries show their IL instead of the x86 stub
+  fn disassembly_len(&self) -> usize {
+    if self.cil_lines.is_empty() {
+      self.data.text_section.data.len()
+    } else {
+      self.cil_lines.len()
+    }
+  }
+
+  // the function list only applies to the x86 listing
+  fn has_function_list(&self) -> bool {
+    self.cil_lines.is_empty() && !self.functions.is_empty()
+  }
+
+  fn toggle_focus(&mut self) {
+    if self.active_tab != Tab::Disassembly || !self.has_function_list() {
+      return;
+    }
+    self.focus = match self.focus {
+      Focus::Listing => Focus::Functions,
+      Focus::Functions => Focus::Listing,
+    };
+  }
+
+  fn jump_to_selected_function(&mut self) {
+    if self.active_tab != Tab::Disassembly || self.focus != Focus::Functions {
+      return;
+    }
+    if let Some(function) = self.functions.get(self.function_selected) {
+      if let Some(index) = analysis::instruction_index(&self.data.text_section.data, function.start)
+      {
+        self.focus = Focus::Listing;
+        self.navigate_to(index);
+      }
+    }
+  }
+
+  // the function containing the instruction at the top of the listing
+  fn current_function(&self) -> Option<&Function> {
+    let ip = self
+      .data
+      .text_section
+      .data
+      .get(self.data_scroll)?
+      .instr
+      .ip();
+    self.function_at(ip)
+  }
+
+  // a label the user gave the function wins over its own name
+  fn function_name(&self, function: &Function) -> String {
+    match self.project.labels.get(&function.start) {
+      Some(label) => label.clone(),
+      None => function.display_name(),
+    }
+  }
+
+  fn function_at(&self, address: u64) -> Option<&Function> {
+    let index = self.functions.partition_point(|x| x.start <= address);
+    let function = self.functions.get(index.checked_sub(1)?)?;
+    if address < function.end {
+      Some(function)
+    } else {
+      None
+    }
+  }
+
+  // lists the references to what the top instruction points at, or to the instruction itself
+  fn toggle_xrefs(&mut self) {
+    if self.active_tab != Tab::Disassembly
+      || !self.cil_lines.is_empty()
+      || self.focus != Focus::Listing
+      || self.graph.is_some()
+    {
+      return;
+    }
+    if self.xref_list.is_some() {
+      self.xref_list = None;
+      return;
+    }
+    let ip = match self.data.text_section.data.get(self.data_scroll) {
+      Some(data) => data.instr.ip(),
+      None => return,
+    };
+    let target = match self.xrefs.references_from(ip).first() {
+      Some(xref) => xref.to,
+      None => ip,
+    };
+    self.xref_list = Some(xrefs::xref_list(self, target));
+  }
+
+  fn next_syntax(&mut self) {
+    if self.active_tab != Tab::Disassembly || !self.cil_lines.is_empty() {
+      return;
+    }
+    self.format_options.syntax = self.format_options.syntax.next();
+    self.refresh_formatter();
+  }
+
+  fn refresh_formatter(&mut self) {
+    self.formatter = analysis::build_formatter(&self.symbols, &self.format_options);
+    // everything that holds formatted text is rebuilt
+    if self.graph.is_some() {
+      self.show_graph();
+    }
+    if let Some(list) = &self.xref_list {
+      let selected = list.selected;
+      let mut list = xrefs::xref_list(self, list.target);
+      list.selected = selected;
+      self.xref_list = Some(list);
+    }
+  }
+
+  fn open_goto(&mut self) {
+    if !self.cil_lines.is_empty() || self.data.text_section.data.is_empty() {
+      return;
+    }
+    self.goto_prompt = Some(goto::GotoPrompt::default());
+  }
+
+  fn goto_key(&mut self, code: KeyCode) {
+    let prompt = match &mut self.goto_prompt {
+      Some(prompt) => prompt,
+      None => return,
+    };
+    prompt.error = None;
+    match code {
+      KeyCode::Char(c) => prompt.input.push(c),
+      KeyCode::Backspace => {
+        prompt.input.pop();
+      }
+      KeyCode::Esc => self.goto_prompt = None,
+      KeyCode::Enter => {
+        let input = prompt.input.clone();
+        match goto::resolve_goto(self, &input) {
+          Ok(goto::GotoTarget::Instruction(index)) => {
+            self.goto_prompt = None;
+            self.active_tab = Tab::Disassembly;
+            self.focus = Focus::Listing;
+            self.xref_list = None;
+            self.navigate_to(index);
+          }
+          Ok(goto::GotoTarget::FileOffset(offset)) => {
+            self.goto_prompt = None;
+            self.active_tab = Tab::Hex;
+            self.xref_list = None;
+            self.hex_cursor = offset;
+          }
+          Err(error) => {
+            if let Some(prompt) = &mut self.goto_prompt {
+              prompt.error = Some(error);
+            }
+          }
+        }
+      }
+      _ => {}
+    }
+  }
+
+  fn open_search(&mut self) {
+    if !self.cil_lines.is_empty() || self.data.text_section.data.is_empty() {
+      return;
+    }
+    // the last query is kept so n/N and editing it are one key away
+    self.search_prompt = Some(match &self.search {
+      Some(search) => search::SearchPrompt {
+        input: search.query.clone(),
+        mode: search.mode,
+        error: None,
+      },
+      None => search::SearchPrompt::default(),
+    });
+  }
+
+  fn search_key(&mut self, code: KeyCode) {
+    let prompt = match &mut self.search_prompt {
+      Some(prompt) => prompt,
+      None => return,
+    };
+    prompt.error = None;
+    match code {
+      KeyCode::Char(c) => prompt.input.push(c),
+      KeyCode::Backspace => {
+        prompt.input.pop();
+      }
+      KeyCode::Tab => prompt.mode = prompt.mode.next(),
+      KeyCode::Esc => self.search_prompt = None,
+      // an empty query clears the highlighting
+      KeyCode::Enter if prompt.input.is_empty() => {
+        self.search_prompt = None;
+        self.search = None;
+      }
+      KeyCode::Enter => {
+        let (mode, input) = (prompt.mode, prompt.input.clone());
+        match search::run_search(self, mode, &input) {
+          Ok(search) if search.matches.is_empty() => {
+            if let Some(prompt) = &mut self.search_prompt {
+              prompt.error = Some("No matches".to_owned());
+            }
+          }
+          Ok(search) => {
+            let index = search.current_match().map(|x| x.index);
+            self.search_prompt = None;
+            self.search = Some(search);
+            self.active_tab = Tab::Disassembly;
+            self.focus = Focus::Listing;
+            self.xref_list = None;
+            if let Some(index) = index {
+              self.navigate_to(index);
+            }
+          }
+          Err(error) => {
+            if let Some(prompt) = &mut self.search_prompt {
+              prompt.error = Some(error);
+            }
+          }
+        }
+      }
+      _ => {}
+    }
+  }
+
+  fn next_match(&mut self, forward: bool) {
+    if self.active_tab != Tab::Disassembly {
+      return;
+    }
+    let search = match &mut self.search {
+      Some(search) if !search.matches.is_empty() => search,
+      _ => return,
+    };
+    let count = search.matches.len();
+    search.current = if forward {
+      (search.current + 1) % count
+    } else {
+      (search.current + count - 1) % count
+    };
+    let index = search.matches[search.current].index;
+    self.navigate_to(index);
+  }
+
+  fn toggle_details(&mut self) {
+    if self.active_tab != Tab::Disassembly || !self.cil_lines.is_empty() {
+      return;
+    }
+    self.show_details = !self.show_details;
+  }
+
+  fn close_xrefs(&mut self) {
+    self.xref_list = None;
+  }
+
+  fn jump_to_selected_xref(&mut self) {
+    let xref = match &self.xref_list {
+      Some(list) => match list.rows.get(list.selected) {
+        Some(row) => row.xref,
+        None => return,
+      },
+      None => return,
+    };
+    if let Some(index) = analysis::instruction_index(&self.data.text_section.data, xref.from) {
+      self.xref_list = None;
+      self.navigate_to(index);
+    }
+  }
+
+  // Enter on a call or jump goes to its target, lea and immediates that point at code work too
+  fn follow_reference(&mut self) {
+    if self.active_tab != Tab::Disassembly
+      || !self.cil_lines.is_empty()
+      || self.focus != Focus::Listing
+    {
+      return;
+    }
+    let ip = match self.data.text_section.data.get(self.data_scroll) {
+      Some(data) => data.instr.ip(),
+      None => return,
+    };
+    let index = self
+      .xrefs
+      .references_from(ip)
+      .iter()
+      .find_map(|x| analysis::instruction_index(&self.data.text_section.data, x.to));
+    if let Some(index) = index {
+      self.navigate_to(index);
+    }
+  }
+
+  // Every jump in the listing goes through here so it can be undone
+  fn navigate_to(&mut self, index: usize) {
+    if index != self.data_scroll {
+      self.back_history.push(self.data_scroll);
+      if self.back_history.len() > HISTORY_LIMIT {
+        self.back_history.remove(0);
+      }
+      self.forward_history.clear();
+      self.data_scroll = index;
+    }
+    if self.graph.is_some() {
+      self.show_graph();
+    }
+  }
+
+  fn navigate_back(&mut self) {
+    if let Some(index) = self.back_history.pop() {
+      self.forward_history.push(self.data_scroll);
+      self.data_scroll = index;
+      if self.graph.is_some() {
+        self.show_graph();
+      }
+    }
+  }
+
+  fn navigate_forward(&mut self) {
+    if let Some(index) = self.forward_history.pop() {
+      self.back_history.push(self.data_scroll);
+      self.data_scroll = index;
+      if self.graph.is_some() {
+        self.show_graph();
+      }
+    }
+  }
+
+  fn show_graph(&mut self) {
+    let function = match self.current_function() {
+      Some(function) => function,
+      None => return,
+    };
+    let cfg = analysis::build_cfg(&self.data.text_section, function);
+    self.graph_title = format!(
+      " {} ({} blocks, zoom {}/{}) ",
+      self.function_name(function),
+      cfg.blocks.len(),
+      self.graph_zoom + 1,
+      graph::ZOOM_LEVELS
+    );
+    self.graph = Some(graph::layout_graph(
+      &cfg,
+      &self.data.text_section.data,
+      self.graph_zoom,
+      self.formatter.as_mut(),
+    ));
+    self.graph_scroll_x = 0;
+    self.graph_scroll_y = 0;
+  }
+
+  fn toggle_graph(&mut self) {
+    if self.active_tab != Tab::Disassembly || !self.has_function_list() {
+      return;
+    }
+    if self.graph.is_some() {
+      self.graph = None;
+    } else {
+      self.show_graph();
+    }
+  }
+
+  fn zoom_graph(&mut self, zoom_in: bool) {
+    if self.active_tab != Tab::Disassembly || self.graph.is_none() {
+      return;
+    }
+    self.graph_zoom = match zoom_in {
+      true => self.graph_zoom.saturating_sub(1),
+      false => (self.graph_zoom + 1).min(graph::ZOOM_LEVELS - 1),
+    };
+    self.show_graph();
+  }
+
+  fn scroll_graph_horizontal(&mut self, right: bool) {
+    if self.active_tab != Tab::Disassembly {
+      return;
+    }
+    if let Some(graph) = &self.graph {
+      self.graph_scroll_x = match right {
+        true => (self.graph_scroll_x + GRAPH_HORIZONTAL_STEP).min(graph.width.saturating_sub(1)),
+        false => self.graph_scroll_x.saturating_sub(GRAPH_HORIZONTAL_STEP),
+      };
+    }
+  }
+
+  fn move_hex_cursor(&mut self, delta: isize) {
+    if self.active_tab != Tab::Hex || self.data.bytes.is_empty() {
+      return;
+    }
+    self.hex_cursor = self
+      .hex_cursor
+      .saturating_add_signed(delta)
+      .min(self.data.bytes.len() - 1);
+  }
+
+  // p writes bytes at the hex cursor, or assembles over the top instruction of the listing
+  fn open_patch(&mut self) {
+    let kind = match self.active_tab {
+      Tab::Hex if !self.data.bytes.is_empty() => patch::PatchKind::Bytes {
+        offset: self.hex_cursor,
+      },
+      Tab::Disassembly
+        if self.cil_lines.is_empty()
+          && self.focus == Focus::Listing
+          && self.graph.is_none()
+          && self.xref_list.is_none()
+          && !self.data.text_section.data.is_empty() =>
+      {
+        patch::PatchKind::Assemble {
+          index: self.data_scroll,
+        }
+      }
+      _ => return,
+    };
+    self.patch_prompt = Some(patch::PatchPrompt {
+      kind,
+      input: String::new(),
+      error: None,
+    });
+  }
+
+  fn open_write(&mut self) {
+    if self.patches.is_empty() {
+      self.status = Some("Nothing is patched".to_owned());
+      return;
+    }
+    self.patch_prompt = Some(patch::PatchPrompt {
+      kind: patch::PatchKind::Write,
+      input: format!("{}.patched", self.file_path),
+      error: None,
+    });
+  }
+
+  fn patch_key(&mut self, code: KeyCode) {
+    let prompt = match &mut self.patch_prompt {
+      Some(prompt) => prompt,
+      None => return,
+    };
+    prompt.error = None;
+    match code {
+      KeyCode::Char(c) => prompt.input.push(c),
+      KeyCode::Backspace => {
+        prompt.input.pop();
+      }
+      KeyCode::Esc => self.patch_prompt = None,
+      KeyCode::Enter => {
+        let (kind, input) = (prompt.kind.clone(), prompt.input.clone());
+        let result = match kind {
+          patch::PatchKind::Bytes { offset } => {
+            patch::parse_bytes(&input).and_then(|bytes| self.apply_patch(offset, bytes))
+          }
+          patch::PatchKind::Assemble { index } => patch::assemble_patch(self, index, &input)
+            .and_then(|(offset, bytes)| self.apply_patch(offset, bytes)),
+          patch::PatchKind::Write => self.write_patched(input.trim()),
+        };
+        match result {
+          Ok(()) => self.patch_prompt = None,
+          Err(error) => {
+            if let Some(prompt) = &mut self.patch_prompt {
+              prompt.error = Some(error);
+            }
+          }
+        }
+      }
+      _ => {}
+    }
+  }
+
+  fn apply_patch(&mut self, offset: usize, bytes: Vec<u8>) -> Result<(), String> {
+    let original = self.data.patch(offset, &bytes).ok_or(format!(
+      "{} bytes at {:#x} run past the end of the file",
+      bytes.len(),
+      offset
+    ))?;
+    self.status = Some(format!("Patched {} bytes at {:#x}", bytes.len(), offset));
+    self.patches.push(patch::Patch {
+      offset,
+      original,
+      patched: bytes,
+    });
+    self.redecode();
+    Ok(())
+  }
+
+  fn undo_patch(&mut self) {
+    if let Some(patch) = self.patches.pop() {
+      self.data.patch(patch.offset, &patch.original);
+      self.status = Some(format!(
+        "Undid {} bytes at {:#x}",
+        patch.original.len(),
+        patch.offset
+      ));
+      self.redecode();
+    }
+  }
+
+  // the original stays as it was, patches only ever go to a new file. Paths are compared once
+  // resolved so ./file or a symlink to the original can't get past the check
+  fn write_patched(&mut self, path: &str) -> Result<(), String> {
+    if path.is_empty() {
+      return Err("Type a path".to_owned());
+    }
+    let original = std::fs::canonicalize(&self.file_path);
+    match std::fs::canonicalize(path) {
+      Ok(target) if original.is_ok_and(|x| x == target) => {
+        return Err("Write to a new file, the original is kept".to_owned())
+      }
+      Ok(_) => return Err(format!("{} already exists, pick another name", path)),
+      Err(_) => {}
+    }
+    // create_new also refuses a file that shows up in between, or a dangling symlink
+    let mut file = std::fs::OpenOptions::new()
+      .write(true)
+      .create_new(true)
+      .open(path)
+      .map_err(|e| format!("Can't write {}: {}", path, e))?;
+    file
+      .write_all(&self.data.bytes)
+      .map_err(|e| format!("Can't write {}: {}", path, e))?;
+    self.status = Some(format!(
+      "Wrote {} with {} patches",
+      path,
+      self.patches.len()
+    ));
+    Ok(())
+  }
+
+  fn is_patched(&self, offset: usize) -> bool {
+    self.patches.iter().any(|x| x.contains(offset))
+  }
+
+  // Parses the file again so a patch reads as the instructions and header fields it makes, all that
+  // is built from the file follows, positions are carried over by address since indices can shift
+  fn redecode(&mut self) {
+    let old = &self.data.text_section.data;
+    let ip_of = |index: &usize| old.get(*index).map(|x| x.instr.ip()).unwrap_or_default();
+    let scroll = ip_of(&self.data_scroll);
+    let back = self.back_history.iter().map(ip_of).collect::<Vec<u64>>();
+    let forward = self.forward_history.iter().map(ip_of).collect::<Vec<u64>>();
+
+    // a patch can break the headers, the code is then decoded again with the ones read before
+    match parser::parse_pe(self.data.bytes.clone(), self.data.text_section.mode) {
+      Ok(data) => self.data = data,
+      Err(_) => {
+        if let Err(e) = parser::redecode_text_section(&mut self.data) {
+          self.status = Some(format!(
+            "Can't decode {}: {}",
+            self.data.text_section.name, e
+          ));
+          return;
+        }
+        self.status = Some("Can't parse the patched headers, kept the old ones".to_owned());
+      }
+    }
+    self.functions = analysis::discover_functions(&self.data);
+    self.xrefs = analysis::build_xrefs(&self.data);
+    self.symbols =
+      analysis::build_symbols(&self.data, &self.functions).with_labels(&self.project.labels);
+    self.strings = analysis::build_string_refs(&self.data, &self.xrefs);
+    self.formatter = analysis::build_formatter(&self.symbols, &self.format_options);
+
+    let instructions = &self.data.text_section.data;
+    let index_of =
+      |ip: &u64| analysis::containing_instruction_index(instructions, *ip).unwrap_or_default();
+    self.back_history = back.iter().map(index_of).collect();
+    self.forward_history = forward.iter().map(index_of).collect();
+    if let Some(clr) = &self.data.clr {
+      self.managed_lines = managed::generate_managed_lines(clr);
+      self.cil_lines = managed::generate_cil_lines(&clr.method_bodies);
+    }
+    // the IL listing of a managed binary isn't indexed by instruction, its line is kept
+    self.data_scroll = match self.cil_lines.is_empty() {
+      true => analysis::containing_instruction_index(instructions, scroll).unwrap_or_default(),
+      false => self.data_scroll.min(self.cil_lines.len().saturating_sub(1)),
+    };
+    self.function_selected = self
+      .function_selected
+      .min(self.functions.len().saturating_sub(1));
+    self.xref_list = None;
+    self.file_regions = self.data.file_regions();
+    self.extract_strings();
+    self.entropy = analysis::build_entropy(&self.data);
+    self.generate_headers_lines();
+    if let Some(search) = self.search.take() {
+      self.search = search::run_search(self, search.mode, &search.query).ok();
+    }
+    if self.graph.is_some() {
+      self.show_graph();
+    }
+  }
+
+  // ; comments the top instruction of the listing, l names it
+  fn open_annotation(&mut self, kind: annotate::AnnotationKind) {
+    if self.active_tab != Tab::Disassembly
+      || !self.cil_lines.is_empty()
+      || self.focus != Focus::Listing
+      || self.graph.is_some()
+      || self.xref_list.is_some()
+    {
+      return;
+    }
+    let address = match self.data.text_section.data.get(self.data_scroll) {
+      Some(data) => data.instr.ip(),
+      None => return,
+    };
+    let existing = match kind {
+      annotate::AnnotationKind::Comment => self.project.comments.get(&address),
+      annotate::AnnotationKind::Label => self.project.labels.get(&address),
+    };
+    self.annotation_prompt = Some(annotate::AnnotationPrompt {
+      kind,
+      address,
+      input: existing.cloned().unwrap_or_default(),
+      error: None,
+    });
+  }
+
+  fn annotation_key(&mut self, code: KeyCode) {
+    let prompt = match &mut self.annotation_prompt {
+      Some(prompt) => prompt,
+      None => return,
+    };
+    prompt.error = None;
+    match code {
+      KeyCode::Char(c) => prompt.input.push(c),
+      KeyCode::Backspace => {
+        prompt.input.pop();
+      }
+      KeyCode::Esc => self.annotation_prompt = None,
+      KeyCode::Enter => {
+        let (kind, address) = (prompt.kind, prompt.address);
+        let input = prompt.input.trim().to_owned();
+        match kind {
+          annotate::AnnotationKind::Comment if input.is_empty() => {
+            self.project.comments.remove(&address);
+          }
+          annotate::AnnotationKind::Comment => {
+            self.project.comments.insert(address, input);
+          }
+          annotate::AnnotationKind::Label => {
+            if input.is_empty() {
+              self.project.labels.remove(&address);
+            } else if let Err(error) = annotate::check_label(self, address, &input) {
+              if let Some(prompt) = &mut self.annotation_prompt {
+                prompt.error = Some(error);
+              }
+              return;
+            } else {
+              self.project.labels.insert(address, input);
+            }
+            self.symbols = analysis::build_symbols(&self.data, &self.functions)
+              .with_labels(&self.project.labels);
+            self.refresh_formatter();
+          }
+        }
+        self.annotation_prompt = None;
+        self.save_project();
+      }
+      _ => {}
+    }
+  }
+
+  fn toggle_bookmark(&mut self) {
+    if self.active_tab != Tab::Disassembly || !self.cil_lines.is_empty() {
+      return;
+    }
+    let address = match self.data.text_section.data.get(self.data_scroll) {
+      Some(data) => data.instr.ip(),
+      None => return,
+    };
+    if !self.project.bookmarks.remove(&address) {
+      self.project.bookmarks.insert(address);
+    }
+    self.save_project();
+  }
+
+  // the first bookmark below the top of the listing, wrapping around to the first one
+  fn next_bookmark(&mut self) {
+    if self.active_tab != Tab::Disassembly || !self.cil_lines.is_empty() {
+      return;
+    }
+    let ip = match self.data.text_section.data.get(self.data_scroll) {
+      Some(data) => data.instr.ip(),
+      None => return,
+    };
+    let bookmarks = &self.project.bookmarks;
+    let address = match bookmarks.range(ip + 1..).next().or(bookmarks.first()) {
+      Some(address) => *address,
+      None => {
+        self.status = Some("No bookmarks, b adds one".to_owned());
+        return;
+      }
+    };
+    if let Some(index) =
+      analysis::containing_instruction_index(&self.data.text_section.data, address)
+    {
+      self.navigate_to(index);
+    }
+  }
+
+  fn save_project(&mut self) {
+    self.project.last_address = self
+      .data
+      .text_section
+      .data
+      .get(self.data_scroll)
+      .map(|x| x.instr.ip());
+    self.project.last_hex_offset = self.hex_cursor;
+    if let Err(e) = self.project.save() {
+      self.status = Some(format!("Can't save the project: {}", e));
+    }
+  }
+
+  fn extract_strings(&mut self) {
+    self.extracted_strings =
+      analysis::extract_strings(&self.data, &self.xrefs, self.string_min_length);
+    self.filter_strings();
+  }
+
+  // case insensitive, on the text or the section name
+  fn filter_strings(&mut self) {
+    let filter = self.string_filter.to_lowercase();
+    self.string_rows = self
+      .extracted_strings
+      .iter()
+      .enumerate()
+      .filter(|(_, x)| {
+        filter.is_empty()
+          || x.text.to_lowercase().contains(&filter)
+          || x.region.to_lowercase() == filter
+      })
+      .map(|(i, _)| i)
+      .collect();
+    self.string_selected = self
+      .string_selected
+      .min(self.string_rows.len().saturating_sub(1));
+  }
+
+  fn open_string_filter(&mut self) {
+    self.string_filter_open = true;
+  }
+
+  // the list follows every key, enter keeps the filter and esc drops it
+  fn string_filter_key(&mut self, code: KeyCode) {
+    match code {
+      KeyCode::Char(c) => self.string_filter.push(c),
+      KeyCode::Backspace => {
+        self.string_filter.pop();
+      }
+      KeyCode::Enter => self.string_filter_open = false,
+      KeyCode::Esc => {
+        self.string_filter.clear();
+        self.string_filter_open = false;
+      }
+      _ => return,
+    }
+    self.string_selected = 0;
+    self.filter_strings();
+  }
+
+  fn change_min_string_length(&mut self, longer: bool) {
+    if self.active_tab != Tab::Strings {
+      return;
+    }
+    self.string_min_length = match longer {
+      true => (self.string_min_length + 1).min(MAX_STRING_LENGTH_SETTING),
+      false => self.string_min_length.saturating_sub(1).max(1),
+    };
+    self.extract_strings();
+  }
+
+  fn move_string_selection(&mut self, delta: isize) {
+    if self.active_tab != Tab::Strings {
+      return;
+    }
+    self.string_selected = self
+      .string_selected
+      .saturating_add_signed(delta)
+      .min(self.string_rows.len().saturating_sub(1));
+  }
+
+  // enter on a string goes to the code that loads it, a list when several places do
+  fn jump_to_selected_string(&mut self) {
+    let string = match self
+      .string_rows
+      .get(self.string_selected)
+      .and_then(|x| self.extracted_strings.get(*x))
+    {
+      Some(string) => string,
+      None => return,
+    };
+    let (address, references) = match (string.address, string.references.clone()) {
+      (Some(address), references) if !references.is_empty() => (address, references),
+      _ => {
+        self.status = Some("No code references this string".to_owned());
+        return;
+      }
+    };
+    self.active_tab = Tab::Disassembly;
+    self.focus = Focus::Listing;
+    if let [from] = references.as_slice() {
+      if let Some(index) = analysis::instruction_index(&self.data.text_section.data, *from) {
+        self.xref_list = None;
+        self.navigate_to(index);
+      }
+      return;
+    }
+    self.graph = None;
+    self.xref_list = Some(xrefs::xref_list(self, address));
+  }
+
   fn scroll_down(&mut self) {
     match self.active_tab {
+      Tab::Disassembly if self.xref_list.is_some() => {
+        if let Some(list) = &mut self.xref_list {
+          if list.selected + 1 < list.rows.len() {
+            list.selected += 1;
+          }
+        }
+      }
+      Tab::Disassembly if self.focus == Focus::Functions => {
+        if self.function_selected + 1 < self.functions.len() {
+          self.function_selected += 1;
+        }
+      }
+      Tab::Disassembly if self.graph.is_some() => {
+        let height = self.graph.as_ref().map(|x| x.height).unwrap_or(0);
+        if self.graph_scroll_y + 1 < height {
+          self.graph_scroll_y += 1;
+        }
+      }
       Tab::Disassembly => {
-        if self.data_scroll < self.data.text_section.data.len() {
+        if self.data_scroll + 1 < self.disassembly_len() {
           self.data_scroll += 1;
         }
       }
       Tab::Headers => {
         self.header_scroll += 1;
       }
+      Tab::Hex => self.move_hex_cursor(hex::BYTES_PER_ROW as isize),
+      Tab::Strings => self.move_string_selection(1),
+      Tab::Entropy => {}
+      Tab::Managed => {
+        if self.managed_scroll < self.managed_lines.len() {
+          self.managed_scroll += 1;
+        }
+      }
     }
   }
 
   fn scroll_up(&mut self) {
     match self.active_tab {
+      Tab::Disassembly if self.xref_list.is_some() => {
+        if let Some(list) = &mut self.xref_list {
+          list.selected = list.selected.saturating_sub(1);
+        }
+      }
+      Tab::Disassembly if self.focus == Focus::Functions => {
+        if self.function_selected > 0 {
+          self.function_selected -= 1;
+        }
+      }
+      Tab::Disassembly if self.graph.is_some() => {
+        self.graph_scroll_y = self.graph_scroll_y.saturating_sub(1);
+      }
       Tab::Disassembly => {
         if self.data_scroll > 0 {
           self.data_scroll -= 1;
@@ -589,12 +1749,25 @@ impl App {
           self.header_scroll -= 1;
         }
       }
+      Tab::Hex => self.move_hex_cursor(-(hex::BYTES_PER_ROW as isize)),
+      Tab::Strings => self.move_string_selection(-1),
+      Tab::Entropy => {}
+      Tab::Managed => {
+        if self.managed_scroll > 0 {
+          self.managed_scroll -= 1;
+        }
+      }
     }
   }
 }
 
-pub fn draw(file_data: PEFile) -> anyhow::Result<()> {
-  let mut app = App::new(file_data);
+pub fn draw(
+  file_data: PEFile,
+  file_path: &str,
+  format_options: FormatOptions,
+  string_min_length: usize,
+) -> anyhow::Result<()> {
+  let mut app = App::new(file_data, file_path, format_options, string_min_length);
   execute!(stdout(), EnterAlternateScreen, EnableMouseCapture)?;
   enable_raw_mode()?;
   let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
@@ -618,11 +1791,47 @@ pub fn draw(file_data: PEFile) -> anyhow::Result<()> {
       }
 
       if let event::Event::Key(key) = event {
-        // break on ctrl+c or q
+        if key.kind == KeyEventKind::Press {
+          app.status = None;
+        }
+        // the go to, search and patch prompts are typed into, none of the other keys apply
+        if app.goto_prompt.is_some() {
+          if key.kind == KeyEventKind::Press {
+            app.goto_key(key.code);
+          }
+          continue;
+        }
+        if app.search_prompt.is_some() {
+          if key.kind == KeyEventKind::Press {
+            app.search_key(key.code);
+          }
+          continue;
+        }
+        if app.patch_prompt.is_some() {
+          if key.kind == KeyEventKind::Press {
+            app.patch_key(key.code);
+          }
+          continue;
+        }
+        if app.annotation_prompt.is_some() {
+          if key.kind == KeyEventKind::Press {
+            app.annotation_key(key.code);
+          }
+          continue;
+        }
+        if app.string_filter_open {
+          if key.kind == KeyEventKind::Press {
+            app.string_filter_key(key.code);
+          }
+          continue;
+        }
+
+        // break on ctrl+c or q, the position is saved for next time
         if key.kind == KeyEventKind::Press
           && (key.modifiers == event::KeyModifiers::CONTROL && key.code == KeyCode::Char('c'))
           || key.code == KeyCode::Char('q')
         {
+          app.save_project();
           break;
         }
 
@@ -639,6 +1848,134 @@ pub fn draw(file_data: PEFile) -> anyhow::Result<()> {
           app.scroll_down();
         }
 
+        // f switches between the function list and the listing
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('f') {
+          app.toggle_focus();
+        }
+        // enter acts on whatever has focus, the references popup, the function list or the listing
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Enter {
+          if app.active_tab == Tab::Strings {
+            app.jump_to_selected_string();
+          } else if app.xref_list.is_some() {
+            app.jump_to_selected_xref();
+          } else if app.focus == Focus::Functions {
+            app.jump_to_selected_function();
+          } else {
+            app.follow_reference();
+          }
+        }
+
+        // x lists the references to the current instruction's target
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('x') {
+          app.toggle_xrefs();
+        }
+        // esc closes the popup first, then walks back through the jumps like backspace
+        // the history is the listing's, the other tabs would move it out of sight
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Esc {
+          if app.xref_list.is_some() {
+            app.close_xrefs();
+          } else if app.active_tab == Tab::Disassembly {
+            app.navigate_back();
+          }
+        }
+        if key.kind == KeyEventKind::Press
+          && key.code == KeyCode::Backspace
+          && app.active_tab == Tab::Disassembly
+        {
+          app.navigate_back();
+        }
+        if key.kind == KeyEventKind::Press
+          && key.code == KeyCode::Char(']')
+          && app.active_tab == Tab::Disassembly
+        {
+          app.navigate_forward();
+        }
+
+        // g asks for an address or a name to go to
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('g') {
+          app.open_goto();
+        }
+
+        // / searches the listing, n and N move between the matches
+        // in the strings tab / filters the list instead
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('/') {
+          if app.active_tab == Tab::Strings {
+            app.open_string_filter();
+          } else {
+            app.open_search();
+          }
+        }
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('n') {
+          app.next_match(true);
+        }
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('N') {
+          app.next_match(false);
+        }
+
+        // p patches bytes or an instruction, u takes the last patch back, w writes a patched copy
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('p') {
+          app.open_patch();
+        }
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('u') {
+          app.undo_patch();
+        }
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('w') {
+          app.open_write();
+        }
+
+        // ; comments, l labels and b bookmarks the current instruction, ' goes to the next bookmark
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Char(';') {
+          app.open_annotation(annotate::AnnotationKind::Comment);
+        }
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('l') {
+          app.open_annotation(annotate::AnnotationKind::Label);
+        }
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('b') {
+          app.toggle_bookmark();
+        }
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('\'') {
+          app.next_bookmark();
+        }
+
+        // i shows what the current instruction needs and touches
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('i') {
+          app.toggle_details();
+        }
+
+        // s cycles through the assembly syntaxes
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('s') {
+          app.next_syntax();
+        }
+
+        // space switches between the listing and the graph of the current function
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Char(' ') {
+          app.toggle_graph();
+        }
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('+') {
+          app.zoom_graph(true);
+          app.change_min_string_length(true);
+        }
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('-') {
+          app.zoom_graph(false);
+          app.change_min_string_length(false);
+        }
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Left {
+          app.scroll_graph_horizontal(false);
+          app.move_hex_cursor(-1);
+        }
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::Right {
+          app.scroll_graph_horizontal(true);
+          app.move_hex_cursor(1);
+        }
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::PageUp {
+          app.move_hex_cursor(-((hex::BYTES_PER_ROW * HEX_PAGE_ROWS) as isize));
+          app.move_string_selection(-(HEX_PAGE_ROWS as isize));
+        }
+        if key.kind == KeyEventKind::Press && key.code == KeyCode::PageDown {
+          app.move_hex_cursor((hex::BYTES_PER_ROW * HEX_PAGE_ROWS) as isize);
+          app.move_string_selection(HEX_PAGE_ROWS as isize);
+        }
+
         // move on scroll wheel
       }
     }
@@ -681,6 +2018,10 @@ fn ui(f: &mut Frame, app: &mut App) {
   match app.active_tab {
     Tab::Disassembly => render_disassembly(f, app, chunks[1]),
     Tab::Headers => render_headers(f, app, chunks[1]),
+    Tab::Hex => hex::render_hex(f, app, chunks[1]),
+    Tab::Strings => strings::render_strings(f, app, chunks[1]),
+    Tab::Entropy => entropy::render_entropy(f, app, chunks[1]),
+    Tab::Managed => render_managed(f, app, chunks[1]),
   };
 
   let mut default_help = vec![];
@@ -689,11 +2030,158 @@ fn ui(f: &mut Frame, app: &mut App) {
   default_help.extend_from_slice(&helper_text("tab".to_owned(), "Switch tabs".to_owned()));
   default_help.push(" | ".yellow());
   default_help.extend_from_slice(&helper_text("up/down".to_owned(), "Scroll".to_owned()));
+  if app.active_tab == Tab::Disassembly && app.has_function_list() {
+    default_help.push(" | ".yellow());
+    default_help.extend_from_slice(&helper_text("f".to_owned(), "Functions".to_owned()));
+    if app.focus == Focus::Functions {
+      default_help.push(" | ".yellow());
+      default_help.extend_from_slice(&helper_text(
+        "enter".to_owned(),
+        "Go to function".to_owned(),
+      ));
+    }
+    default_help.push(" | ".yellow());
+    default_help.extend_from_slice(&helper_text("space".to_owned(), "Graph".to_owned()));
+    if app.graph.is_none() {
+      default_help.push(" | ".yellow());
+      default_help.extend_from_slice(&helper_text("x".to_owned(), "Xrefs".to_owned()));
+    }
+    if app.xref_list.is_some() {
+      default_help.push(" | ".yellow());
+      default_help.extend_from_slice(&helper_text("enter".to_owned(), "Go to xref".to_owned()));
+    } else if app.focus == Focus::Listing && app.cil_lines.is_empty() {
+      default_help.push(" | ".yellow());
+      default_help.extend_from_slice(&helper_text("enter".to_owned(), "Follow".to_owned()));
+    }
+    if app.graph.is_some() {
+      default_help.push(" | ".yellow());
+      default_help.extend_from_slice(&helper_text("+/-".to_owned(), "Zoom".to_owned()));
+      default_help.push(" | ".yellow());
+      default_help.extend_from_slice(&helper_text("left/right".to_owned(), "Pan".to_owned()));
+    }
+  }
+  if app.active_tab == Tab::Hex {
+    default_help.push(" | ".yellow());
+    default_help.extend_from_slice(&helper_text("left/right".to_owned(), "Move".to_owned()));
+    default_help.push(" | ".yellow());
+    default_help.extend_from_slice(&helper_text("pgup/pgdn".to_owned(), "Page".to_owned()));
+  }
+  if app.active_tab == Tab::Disassembly && app.cil_lines.is_empty() {
+    default_help.push(" | ".yellow());
+    default_help.extend_from_slice(&helper_text("s".to_owned(), "Syntax".to_owned()));
+    if app.graph.is_none() {
+      default_help.push(" | ".yellow());
+      default_help.extend_from_slice(&helper_text("i".to_owned(), "Details".to_owned()));
+      default_help.push(" | ".yellow());
+      default_help.extend_from_slice(&helper_text(";/l".to_owned(), "Comment/Label".to_owned()));
+      default_help.push(" | ".yellow());
+      default_help.extend_from_slice(&helper_text("b/'".to_owned(), "Bookmark/Next".to_owned()));
+    }
+  }
+
+  if app.active_tab == Tab::Strings {
+    default_help.push(" | ".yellow());
+    default_help.extend_from_slice(&helper_text("/".to_owned(), "Filter".to_owned()));
+    default_help.push(" | ".yellow());
+    default_help.extend_from_slice(&helper_text("enter".to_owned(), "Go to code".to_owned()));
+    default_help.push(" | ".yellow());
+    default_help.extend_from_slice(&helper_text("+/-".to_owned(), "Min length".to_owned()));
+  } else if app.cil_lines.is_empty() {
+    default_help.push(" | ".yellow());
+    default_help.extend_from_slice(&helper_text("g".to_owned(), "Go to".to_owned()));
+    default_help.push(" | ".yellow());
+    default_help.extend_from_slice(&helper_text("/".to_owned(), "Search".to_owned()));
+    if app.search.is_some() {
+      default_help.push(" | ".yellow());
+      default_help.extend_from_slice(&helper_text("n/N".to_owned(), "Next/previous".to_owned()));
+    }
+  }
+  if app.active_tab == Tab::Hex
+    || app.active_tab == Tab::Disassembly && app.cil_lines.is_empty() && app.graph.is_none()
+  {
+    default_help.push(" | ".yellow());
+    default_help.extend_from_slice(&helper_text("p".to_owned(), "Patch".to_owned()));
+  }
+  if !app.patches.is_empty() {
+    default_help.push(" | ".yellow());
+    default_help.extend_from_slice(&helper_text(
+      "u".to_owned(),
+      format!("Undo ({})", app.patches.len()),
+    ));
+    default_help.push(" | ".yellow());
+    default_help.extend_from_slice(&helper_text("w".to_owned(), "Write copy".to_owned()));
+  }
+  // shown until the next key, in place of the keys so it isn't cut off
+  if let Some(status) = &app.status {
+    default_help = vec![status.clone().green()];
+  }
+  if let Some(prompt) = &app.goto_prompt {
+    default_help = vec![
+      "Go to (VA, rva/off <n>, name): ".yellow(),
+      format!("{}_", prompt.input).white(),
+    ];
+    if let Some(error) = &prompt.error {
+      default_help.push(format!("  {}", error).red());
+    }
+  }
+  if let Some(prompt) = &app.search_prompt {
+    let mode: &'static str = prompt.mode.into();
+    default_help = vec![
+      format!("Search {} (tab to change): ", mode).yellow(),
+      format!("{}_", prompt.input).white(),
+    ];
+    if let Some(error) = &prompt.error {
+      default_help.push(format!("  {}", error).red());
+    }
+  }
 
+  if let Some(prompt) = &app.patch_prompt {
+    let label = match prompt.kind {
+      patch::PatchKind::Bytes { offset } => format!("Bytes at offset {:#x}: ", offset),
+      patch::PatchKind::Assemble { index } => match app.data.text_section.data.get(index) {
+        Some(data) => format!("Assemble at {:#x}: ", data.instr.ip()),
+        None => "Assemble: ".to_owned(),
+      },
+      patch::PatchKind::Write => "Write patched copy to: ".to_owned(),
+    };
+    default_help = vec![label.yellow(), format!("{}_", prompt.input).white()];
+    if let Some(error) = &prompt.error {
+      default_help.push(format!("  {}", error).red());
+    }
+  }
+
+  if app.string_filter_open {
+    default_help = vec![
+      "Filter strings (enter to keep, esc to clear): ".yellow(),
+      format!("{}_", app.string_filter).white(),
+    ];
+  }
+  if let Some(prompt) = &app.annotation_prompt {
+    let label = match prompt.kind {
+      annotate::AnnotationKind::Comment => format!("Comment at {:#x}: ", prompt.address),
+      annotate::AnnotationKind::Label => format!("Label at {:#x}: ", prompt.address),
+    };
+    default_help = vec![label.yellow(), format!("{}_", prompt.input).white()];
+    if let Some(error) = &prompt.error {
+      default_help.push(format!("  {}", error).red());
+    }
+  }
+
+  // how far back and forward the jump history goes, on the right of the border
+  let history = Line::from(vec![
+    " <esc> Back ".white(),
+    app.back_history.len().to_string().yellow(),
+    " | <]> Forward ".white(),
+    app.forward_history.len().to_string().yellow(),
+    " ".into(),
+  ]);
+  let mut help_block = Block::default().title(" Help ");
+  if app.active_tab == Tab::Disassembly && app.cil_lines.is_empty() {
+    help_block = help_block.title(block::Title::from(history).alignment(Alignment::Right));
+  }
   let help = Paragraph::new(Line::from(default_help))
     .block(
-      Block::default()
-        .title(" Help ")
+      help_block
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::White))
         .padding(Padding::new(1, 0, 0, 0)),
@@ -708,47 +2196,132 @@ fn helper_text(key: String, text: String) -> Vec<Span<'static>> {
 }
 
 fn render_disassembly(f: &mut Frame, app: &mut App, section_size: Rect) {
-  let top = app.data.text_section.data.get(app.data_scroll).unwrap();
+  let section_size = if app.has_function_list() {
+    let split = Layout::default()
+      .direction(Direction::Horizontal)
+      .constraints([Constraint::Length(36), Constraint::Min(0)])
+      .split(section_size);
+    functions::render_functions(f, app, split[0]);
+    split[1]
+  } else {
+    section_size
+  };
+
+  if let Some(graph) = &app.graph {
+    // borders take a row/column on each side
+    let width = section_size.width.saturating_sub(2) as usize;
+    let height = section_size.height.saturating_sub(2) as usize;
+    let p = Paragraph::new(graph.lines(app.graph_scroll_x, app.graph_scroll_y, width, height))
+      .block(
+        Block::default()
+          .title(app.graph_title.clone())
+          .borders(Borders::ALL)
+          .border_style(Style::default().fg(Color::White)),
+      );
+    f.render_widget(p, section_size);
+    return;
+  }
+
   let split = Layout::default()
     .direction(Direction::Horizontal)
     .constraints([Constraint::Min(0), Constraint::Length(27)])
     .split(section_size);
 
-  // TODO add search bar
-  // let left_split = Layout::default()
-  //   .direction(Direction::Vertical)
-  //   .constraints([Constraint::Length(3), Constraint::Min(0)])
-  //   .split(split[0]);
+  let (listing_area, details_area) = if app.show_details && app.cil_lines.is_empty() {
+    let left_split = Layout::default()
+      .direction(Direction::Vertical)
+      .constraints([
+        Constraint::Min(0),
+        Constraint::Length(details::DETAILS_HEIGHT),
+      ])
+      .split(split[0]);
+    (left_split[0], Some(left_split[1]))
+  } else {
+    (split[0], None)
+  };
 
-  let left_height = split[0].height;
-  let left_lines = app
-    .data
-    .text_section
-    .data
-    .iter()
-    .enumerate()
-    .filter_map(|(i, l)| {
-      if i < app.data_scroll {
-        return None;
-      }
-      if i > app.data_scroll + left_height as usize {
-        return None;
-      }
-      let real_index = i - app.data_scroll;
-      let mut line_parts = vec![];
-      if real_index == 0 {
-        line_parts.push(format!("{:#8x}", l.offset).green().on_gray());
-      } else {
-        line_parts.push(format!("{:#8x}", l.offset).green());
-      }
-      line_parts.push("  ".to_owned().into());
-      line_parts.push(l.instr.to_string().yellow());
-      Some(Line::from(line_parts))
-    })
-    .collect::<Vec<Line>>();
+  let left_height = listing_area.height;
+  let (top_offset, top_size, left_lines) = if app.cil_lines.is_empty() {
+    let top = app.data.text_section.data.get(app.data_scroll).unwrap();
+    let left_lines = app
+      .data
+      .text_section
+      .data
+      .iter()
+      .enumerate()
+      .skip(app.data_scroll)
+      // labels push the last ones past the bottom, where they are cut off
+      .take(left_height as usize + 1)
+      .flat_map(|(i, l)| {
+        let real_index = i - app.data_scroll;
+        let mut lines = vec![];
+        // labels get a line of their own above the instruction, like a label in source
+        if let Some(label) = app.project.labels.get(&l.instr.ip()) {
+          let address_width = format!("{:#10x}", l.instr.ip()).len();
+          lines.push(Line::from(vec![
+            " ".repeat(address_width + 2).into(),
+            format!("{}:", label).yellow().bold(),
+          ]));
+        }
+        let mut line_parts = vec![];
+        if real_index == 0 {
+          line_parts.push(format!("{:#10x}", l.instr.ip()).green().on_gray());
+        } else {
+          line_parts.push(format!("{:#10x}", l.instr.ip()).green());
+        }
+        if app.project.bookmarks.contains(&l.instr.ip()) {
+          line_parts.push("* ".light_magenta().bold());
+        } else {
+          line_parts.push("  ".to_owned().into());
+        }
+        let mut instruction = highlight::highlight_instruction(app.formatter.as_mut(), &l.instr);
+        if app.search.as_ref().is_some_and(|x| x.is_match(i)) {
+          instruction = instruction.into_iter().map(|x| x.on_blue()).collect();
+        }
+        line_parts.extend(instruction);
+        if let Some(comment) = instruction_comment(
+          &app.data.text_section,
+          &app.xrefs,
+          &app.strings,
+          l.instr.ip(),
+        ) {
+          line_parts.push(format!("  ; {}", comment).dark_gray());
+        }
+        if let Some(comment) = app.project.comments.get(&l.instr.ip()) {
+          line_parts.push(format!("  ; {}", comment).light_cyan());
+        }
+        lines.push(Line::from(line_parts));
+        lines
+      })
+      .collect::<Vec<Line>>();
+    (top.offset, top.size, left_lines)
+  } else {
+    let top = app.cil_lines.get(app.data_scroll).unwrap();
+    let left_lines = app
+      .cil_lines
+      .iter()
+      .skip(app.data_scroll)
+      .take(left_height as usize + 1)
+      .enumerate()
+      .map(|(i, l)| {
+        let mut line = l.line.clone();
+        if i == 0 {
+          line.spans[0] = line.spans[0].clone().on_gray();
+        }
+        line
+      })
+      .collect::<Vec<Line>>();
+    (top.offset, top.size, left_lines)
+  };
 
   // Hex
   let right_height = split[1].height;
+  let section_start = app
+    .data
+    .section_table
+    .iter()
+    .find(|x| x.name == app.data.text_section.name)
+    .map(|x| x.pointer_to_raw_data as usize);
   let right_lines = app
     .data
     .text_section
@@ -756,15 +2329,21 @@ fn render_disassembly(f: &mut Frame, app: &mut App, section_size: Rect) {
     .iter()
     .enumerate()
     .filter_map(|(i, b)| {
-      if i < top.offset {
+      if i < top_offset {
         return None;
       }
-      if i > top.offset + (right_height * 8) as usize {
+      if i > top_offset + (right_height * 8) as usize {
         return None;
       }
-      if i >= top.offset && i < top.offset + top.size {
+      if i >= top_offset && i < top_offset + top_size {
         return Some(format!("{:02x}", b).blue().on_gray());
       }
+      if section_start.is_some_and(|start| app.is_patched(start + i)) {
+        return Some(format!("{:02x}", b).light_red().bold());
+      }
+      if app.cil_lines.is_empty() && app.search.as_ref().is_some_and(|x| x.is_match_byte(i)) {
+        return Some(format!("{:02x}", b).black().on_yellow());
+      }
       Some(format!("{:02x}", b).green())
     })
     .collect::<Vec<Span>>()
@@ -796,18 +2375,66 @@ fn render_disassembly(f: &mut Frame, app: &mut App, section_size: Rect) {
     )
     .white();
 
+  let mode = match app.data.text_section.mode {
+    DisassemblyMode::RecursiveDescent => "recursive descent",
+    DisassemblyMode::LinearSweep => "linear sweep",
+  };
+  let syntax: &'static str = app.format_options.syntax.into();
+  let matches = match &app.search {
+    Some(search) => format!("match {}/{} ", search.current + 1, search.matches.len()),
+    None => String::new(),
+  };
   let left = Paragraph::new(left_lines)
     .block(
       Block::default()
-        .title(" .text ")
+        .title(format!(
+          " {} ({}, {}) {}",
+          app.data.text_section.name, mode, syntax, matches
+        ))
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::White))
         .padding(Padding::new(1, 0, 0, 0)),
     )
     .white();
 
-  f.render_widget(left, split[0]);
+  f.render_widget(left, listing_area);
   f.render_widget(right, split[1]);
+  if let Some(area) = details_area {
+    details::render_details(f, app, area);
+  }
+
+  if let Some(list) = &app.xref_list {
+    xrefs::render_xrefs(f, app, list, section_size);
+  }
+}
+
+// Strings the instruction points at and the cases of a switch, cut to fit next to the instruction
+fn instruction_comment(
+  section: &SectionData,
+  xrefs: &XrefIndex,
+  strings: &BTreeMap<u64, DataString>,
+  ip: u64,
+) -> Option<String> {
+  let comment = if let Some(table) = section.jump_table(ip) {
+    let cases = table
+      .targets
+      .iter()
+      .enumerate()
+      .filter_map(|(i, x)| Some(format!("{}:{:#x}", i, (*x)?)))
+      .collect::<Vec<String>>()
+      .join(" ");
+    format!("switch, {} cases {}", table.targets.len(), cases)
+  } else {
+    analysis::referenced_string(xrefs, strings, ip)?.quoted()
+  };
+  if comment.chars().count() > COMMENT_WIDTH {
+    Some(format!(
+      "{}...",
+      comment.chars().take(COMMENT_WIDTH).collect::<String>()
+    ))
+  } else {
+    Some(comment)
+  }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -820,6 +2447,160 @@ fn util_hex<T: LowerHex>(value: &T) -> String {
   format!("{:#x}", value)
 }
 
+fn header_key_value_lines(values: &[HeaderKeyValue]) -> Vec<Line<'static>> {
+  values
+    .iter()
+    .map(|x| {
+      Line::from(vec![
+        " ".into(),
+        x.key.clone().yellow(),
+        " ".into(),
+        x.value.clone().white(),
+      ])
+    })
+    .collect::<Vec<Line>>()
+}
+
+fn util_timestamp(value: u32) -> String {
+  match chrono::NaiveDateTime::from_timestamp_opt(value as i64, 0) {
+    Some(x) => x.format("%Y-%m-%d %H:%M:%S").to_string(),
+    None => "Not a valid timestamp".to_owned(),
+  }
+}
+
+fn dos_stub_lines(dos_header: &DOSHeader, program: &DosStubProgram) -> Vec<Line<'static>> {
+  let status = if program.anomalies.is_empty() {
+    "standard".to_owned().green()
+  } else {
+    let anomalies = program
+      .anomalies
+      .iter()
+      .map(|x| {
+        let str: &str = x.into();
+        str
+      })
+      .collect::<Vec<&str>>()
+      .join(", ");
+    format!("non-standard: {}", anomalies).red()
+  };
+  let mut lines = vec![
+    Line::from(vec![
+      " ".into(),
+      "entry_point".yellow(),
+      " ".into(),
+      format!(
+        "{:04x}:{:04x} (file offset {})",
+        dos_header.e_cs,
+        dos_header.e_ip,
+        util_hex(&program.entry_offset)
+      )
+      .white(),
+    ]),
+    Line::from(vec![
+      " ".into(),
+      "message".yellow(),
+      " ".into(),
+      match &program.message {
+        Some(message) => format!("{:?}", message).white(),
+        None => "none".to_owned().white(),
+      },
+    ]),
+    Line::from(vec![" ".into(), "stub".yellow(), " ".into(), status]),
+  ];
+
+  for instruction in &program.instructions {
+    let bytes = instruction
+      .bytes
+      .iter()
+      .map(|x| format!("{:02x}", x))
+      .collect::<Vec<String>>()
+      .join(" ");
+    lines.push(Line::from(vec![
+      "   ".into(),
+      format!("{:#6x}", instruction.offset).green(),
+      "  ".into(),
+      format!("{:<20}", bytes).white(),
+      instruction.instr.to_string().yellow(),
+    ]));
+  }
+  lines
+}
+
+fn rich_header_lines(rich_header: &RichHeader) -> Vec<Line<'static>> {
+  let checksum = if rich_header.checksum == rich_header.key {
+    "valid".to_owned().green()
+  } else {
+    format!("invalid, computed {:#010x}", rich_header.checksum).red()
+  };
+  let mut lines = vec![
+    Line::from(vec![
+      " ".into(),
+      "offset".yellow(),
+      " ".into(),
+      util_hex(&rich_header.offset).white(),
+    ]),
+    Line::from(vec![
+      " ".into(),
+      "key".yellow(),
+      " ".into(),
+      format!("{:#010x} ", rich_header.key).white(),
+      checksum,
+    ]),
+    Line::from(vec![
+      " ".into(),
+      "hash".yellow(),
+      " ".into(),
+      rich_header.hash.clone().white(),
+    ]),
+  ];
+
+  for entry in &rich_header.entries {
+    let product = match entry.product_name {
+      Some(name) => format!("{:<18}", name),
+      None => format!("{:<18}", format!("prodid {:#06x}", entry.product_id)),
+    };
+    let mut line_parts = vec![
+      "   ".into(),
+      product.yellow(),
+      format!(" build {:<6} count {:<6}", entry.build_number, entry.count).white(),
+    ];
+    if let Some(version) = entry.visual_studio_version {
+      line_parts.push(format!(" {}", version).green());
+    }
+    lines.push(Line::from(line_parts));
+  }
+  lines
+}
+
+fn bound_import_line(
+  indent: &'static str,
+  module_name: &str,
+  time_date_stamp: u32,
+  stale_reasons: &[StaleBindingReason],
+) -> Line<'static> {
+  let mut line_parts = vec![
+    indent.into(),
+    module_name.to_owned().yellow(),
+    " ".into(),
+    util_timestamp(time_date_stamp).white(),
+  ];
+  if !stale_reasons.is_empty() {
+    line_parts.push(format!(" stale: {}", stale_reason_list(stale_reasons)).red());
+  }
+  Line::from(line_parts)
+}
+
+fn stale_reason_list(stale_reasons: &[StaleBindingReason]) -> String {
+  stale_reasons
+    .iter()
+    .map(|x| {
+      let str: &str = x.into();
+      str
+    })
+    .collect::<Vec<&str>>()
+    .join(", ")
+}
+
 fn render_headers(f: &mut Frame, app: &mut App, size: Rect) {
   let p = Paragraph::new(app.header_lines.clone())
     .scroll((app.header_scroll as u16, 0))
@@ -834,3 +2615,18 @@ fn render_headers(f: &mut Frame, app: &mut App, size: Rect) {
 
   f.render_widget(p, size);
 }
+
+fn render_managed(f: &mut Frame, app: &mut App, size: Rect) {
+  let p = Paragraph::new(app.managed_lines.clone())
+    .scroll((app.managed_scroll as u16, 0))
+    .block(
+      Block::default()
+        .title(" Managed ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::White))
+        .padding(Padding::new(1, 0, 0, 0)),
+    )
+    .white();
+
+  f.render_widget(p, size);
+}
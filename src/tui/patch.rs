@@ -0,0 +1,162 @@
+use crate::analysis;
+use crate::parser::InstructionData;
+use crate::tui::{search, App};
+
+const NOP: u8 = 0x90;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchKind {
+  Bytes { offset: usize }, // Hex bytes written at a file offset from the hex tab
+  Assemble { index: usize }, // An instruction replacing the one at this index of the listing
+  Write,                   // Where the patched copy goes
+}
+
+// Text typed after p or w, the error stays until the next key so a typo can be fixed
+#[derive(Debug)]
+pub struct PatchPrompt {
+  pub kind: PatchKind,
+  pub input: String,
+  pub error: Option<String>,
+}
+
+// One entry of the undo list
+#[derive(Debug, Clone)]
+pub struct Patch {
+  pub offset: usize, // File offset
+  pub original: Vec<u8>,
+  pub patched: Vec<u8>,
+}
+
+impl Patch {
+  pub fn contains(&self, offset: usize) -> bool {
+    offset >= self.offset && offset < self.offset + self.patched.len()
+  }
+}
+
+// "90 90" or "9090", every byte has to be given
+pub fn parse_bytes(input: &str) -> Result<Vec<u8>, String> {
+  search::parse_pattern(input)?
+    .into_iter()
+    .map(|x| x.ok_or("?? can't be written, give every byte".to_owned()))
+    .collect()
+}
+
+// The file offset and bytes that replace the instruction at the index, when the new one is longer
+// it runs into the next instructions and NOPs fill whatever is left of the last one it touches
+pub fn assemble_patch(app: &App, index: usize, text: &str) -> Result<(usize, Vec<u8>), String> {
+  let section = &app.data.text_section;
+  let data = section
+    .data
+    .get(index)
+    .ok_or("Nothing to replace".to_owned())?;
+  let bitness = app.data.headers.nt_headers.file_header.machine.bitness();
+  let bytes = analysis::assemble(text, data.instr.ip(), bitness, &app.symbols)?;
+  let bytes = pad_with_nops(&section.data, index, bytes).ok_or(format!(
+    "{} runs past the end of {}",
+    text.trim(),
+    section.name
+  ))?;
+
+  let entry = app
+    .data
+    .section_table
+    .iter()
+    .find(|x| x.name == section.name)
+    .ok_or(format!("{} isn't in the section table", section.name))?;
+  Ok((entry.pointer_to_raw_data as usize + data.offset, bytes))
+}
+
+// Fills up to the end of the last instruction the new one overlaps, so what follows decodes as before
+fn pad_with_nops(
+  instructions: &[InstructionData],
+  index: usize,
+  mut bytes: Vec<u8>,
+) -> Option<Vec<u8>> {
+  let first = instructions.get(index)?;
+  let start = first.offset;
+  let mut end = start + first.size;
+  let mut next = index + 1;
+  while end < start + bytes.len() {
+    let following = instructions.get(next)?;
+    end = following.offset + following.size;
+    next += 1;
+  }
+  bytes.resize(end - start, NOP);
+  Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::analysis::SymbolTable;
+  use iced_x86::{Decoder, DecoderOptions};
+
+  // push rbp / mov rbp, rsp / sub rsp, 0x20 / ret
+  const CODE: [u8; 9] = [0x55, 0x48, 0x89, 0xe5, 0x48, 0x83, 0xec, 0x20, 0xc3];
+
+  fn decode(bytes: &[u8]) -> Vec<InstructionData> {
+    let mut decoder = Decoder::with_ip(64, bytes, 0x1000, DecoderOptions::NONE);
+    let mut data = vec![];
+    while decoder.can_decode() {
+      let offset = decoder.position();
+      let instr = decoder.decode();
+      data.push(InstructionData {
+        instr,
+        offset,
+        size: instr.len(),
+        bytes: bytes[offset..offset + instr.len()].to_vec(),
+      });
+    }
+    data
+  }
+
+  fn assemble_over(index: usize, text: &str) -> Option<Vec<u8>> {
+    let instructions = decode(&CODE);
+    let ip = instructions[index].instr.ip();
+    let bytes = analysis::assemble(text, ip, 64, &SymbolTable::default()).ok()?;
+    pad_with_nops(&instructions, index, bytes)
+  }
+
+  #[test]
+  fn shorter_instruction_is_padded() {
+    // xor eax, eax over the 3 byte mov
+    assert_eq!(
+      assemble_over(1, "xor eax, eax"),
+      Some(vec![0x31, 0xc0, 0x90])
+    );
+  }
+
+  #[test]
+  fn longer_instruction_pads_the_one_it_cuts_into() {
+    // 4 bytes cover push and mov exactly, the sub after them is left alone
+    assert_eq!(
+      assemble_over(0, "add rsp, 8"),
+      Some(vec![0x48, 0x83, 0xc4, 0x08])
+    );
+    // 6 bytes end inside the sub, so all of it becomes nops
+    assert_eq!(
+      assemble_over(1, "mov ecx, 0x12345678"),
+      Some(vec![0xb9, 0x78, 0x56, 0x34, 0x12, 0x90, 0x90])
+    );
+  }
+
+  #[test]
+  fn same_size_needs_no_padding() {
+    assert_eq!(assemble_over(3, "int3"), Some(vec![0xcc]));
+  }
+
+  #[test]
+  fn running_past_the_section_fails() {
+    assert_eq!(assemble_over(3, "mov eax, 1"), None);
+  }
+
+  #[test]
+  fn decodes_back_to_the_same_boundaries() {
+    let instructions = decode(&CODE);
+    let mut patched = CODE.to_vec();
+    let bytes = assemble_over(1, "xor eax, eax").unwrap();
+    patched[1..1 + bytes.len()].copy_from_slice(&bytes);
+    let offsets = |x: &[InstructionData]| x.iter().map(|x| x.offset).collect::<Vec<usize>>();
+    assert!(offsets(&decode(&patched)).ends_with(&offsets(&instructions)[2..]));
+  }
+}
@@ -0,0 +1,217 @@
+use crate::analysis::{self, ExtractedString};
+use crate::tui::App;
+use regex::Regex;
+use std::ops::Range;
+use strum::{EnumIter, IntoEnumIterator, IntoStaticStr};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, EnumIter, IntoStaticStr)]
+#[strum(serialize_all = "lowercase")]
+pub enum SearchMode {
+  #[default]
+  Text, // Substring of the formatted instruction, ignoring case
+  Regex,
+  Bytes,  // Hex bytes, ?? matches any byte
+  String, // Strings of the whole file, found at the code that uses them
+}
+
+impl SearchMode {
+  pub fn next(self) -> Self {
+    SearchMode::iter()
+      .cycle()
+      .skip_while(|x| *x != self)
+      .nth(1)
+      .unwrap_or_default()
+  }
+}
+
+// Text typed after /, tab switches the mode
+#[derive(Debug, Default)]
+pub struct SearchPrompt {
+  pub input: String,
+  pub mode: SearchMode,
+  pub error: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Search {
+  pub query: String,
+  pub mode: SearchMode,
+  pub matches: Vec<SearchMatch>, // Sorted by instruction index
+  pub current: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchMatch {
+  pub index: usize, // Instruction the match is in, or starts in for byte patterns
+  pub bytes: Range<usize>, // Matched bytes of the section, the whole instruction unless searching bytes
+}
+
+impl Search {
+  pub fn is_match(&self, index: usize) -> bool {
+    self
+      .matches
+      .binary_search_by_key(&index, |x| x.index)
+      .is_ok()
+  }
+
+  pub fn is_match_byte(&self, offset: usize) -> bool {
+    // matches can overlap when searching bytes, so every match that starts at or before the offset is a candidate
+    let end = self.matches.partition_point(|x| x.bytes.start <= offset);
+    self.matches[..end]
+      .iter()
+      .rev()
+      .take_while(|x| x.bytes.start + MAX_MATCH_BYTES > offset)
+      .any(|x| x.bytes.contains(&offset))
+  }
+
+  pub fn current_match(&self) -> Option<&SearchMatch> {
+    self.matches.get(self.current)
+  }
+}
+
+// A byte pattern or instruction can't be longer than this, which bounds the overlap scan
+const MAX_MATCH_BYTES: usize = 256;
+
+pub fn run_search(app: &mut App, mode: SearchMode, query: &str) -> Result<Search, String> {
+  let matches = match mode {
+    SearchMode::Text => {
+      let query = query.to_lowercase();
+      instruction_matches(app, |text| text.to_lowercase().contains(&query))
+    }
+    SearchMode::Regex => {
+      // the error points at the problem over several lines, the prompt only has one
+      let regex = Regex::new(query).map_err(|e| {
+        e.to_string()
+          .split_whitespace()
+          .collect::<Vec<&str>>()
+          .join(" ")
+      })?;
+      instruction_matches(app, |text| regex.is_match(text))
+    }
+    SearchMode::Bytes => byte_matches(app, &parse_pattern(query)?),
+    SearchMode::String => string_matches(app, &query.to_lowercase())?,
+  };
+
+  // start from the first match at or below the current position
+  let current = matches
+    .iter()
+    .position(|x| x.index >= app.data_scroll)
+    .unwrap_or(0);
+  Ok(Search {
+    query: query.to_owned(),
+    mode,
+    matches,
+    current,
+  })
+}
+
+fn instruction_matches(app: &mut App, matches: impl Fn(&str) -> bool) -> Vec<SearchMatch> {
+  let section = &app.data.text_section;
+  section
+    .data
+    .iter()
+    .enumerate()
+    .filter(|(_, data)| {
+      matches(&analysis::format_instruction(
+        app.formatter.as_mut(),
+        &data.instr,
+      ))
+    })
+    .map(|(index, data)| SearchMatch {
+      index,
+      bytes: data.offset..data.offset + data.size,
+    })
+    .collect()
+}
+
+// Every extracted string containing the query, at the instructions that load it, or at the line it
+// is in when it sits in the code section itself
+fn string_matches(app: &App, query: &str) -> Result<Vec<SearchMatch>, String> {
+  let instructions = &app.data.text_section.data;
+  let strings = app
+    .extracted_strings
+    .iter()
+    .filter(|x| x.text.to_lowercase().contains(query))
+    .collect::<Vec<&ExtractedString>>();
+  let mut matches = strings
+    .iter()
+    .flat_map(|string| {
+      let inside = string
+        .address
+        .and_then(|address| analysis::containing_instruction_index(instructions, address));
+      string
+        .references
+        .iter()
+        .filter_map(|from| analysis::instruction_index(instructions, *from))
+        .chain(inside)
+    })
+    .map(|index| {
+      let data = &instructions[index];
+      SearchMatch {
+        index,
+        bytes: data.offset..data.offset + data.size,
+      }
+    })
+    .collect::<Vec<SearchMatch>>();
+  matches.sort_by_key(|x| x.index);
+  matches.dedup_by_key(|x| x.index);
+
+  // the strings tab lists them all, the listing can only show what the code reaches
+  if matches.is_empty() && !strings.is_empty() {
+    return Err(format!(
+      "{} matching strings, none is used by the code, filter the strings tab instead",
+      strings.len()
+    ));
+  }
+  Ok(matches)
+}
+
+fn byte_matches(app: &App, pattern: &[Option<u8>]) -> Vec<SearchMatch> {
+  let section = &app.data.text_section;
+  section
+    .bytes
+    .windows(pattern.len())
+    .enumerate()
+    .filter(|(_, window)| {
+      window
+        .iter()
+        .zip(pattern)
+        .all(|(b, p)| p.map_or(true, |p| p == *b))
+    })
+    .filter_map(|(offset, _)| {
+      // the instruction the match starts in
+      let index = section
+        .data
+        .partition_point(|x| x.offset <= offset)
+        .checked_sub(1)?;
+      Some(SearchMatch {
+        index,
+        bytes: offset..offset + pattern.len(),
+      })
+    })
+    .collect()
+}
+
+// "48 8B ?? 24" or "488B??24"
+pub fn parse_pattern(query: &str) -> Result<Vec<Option<u8>>, String> {
+  let digits = query.split_whitespace().collect::<String>();
+  if digits.is_empty() || !digits.is_ascii() || digits.len() % 2 != 0 {
+    return Err("Bytes are two hex digits each".to_owned());
+  }
+  let pattern = (0..digits.len())
+    .step_by(2)
+    .map(|i| match &digits[i..i + 2] {
+      "??" => Ok(None),
+      byte => u8::from_str_radix(byte, 16)
+        .map(Some)
+        .map_err(|_| format!("{} isn't a hex byte", byte)),
+    })
+    .collect::<Result<Vec<Option<u8>>, String>>()?;
+  if pattern.len() > MAX_MATCH_BYTES {
+    return Err(format!("Patterns are at most {} bytes", MAX_MATCH_BYTES));
+  }
+  if pattern.iter().all(|x| x.is_none()) {
+    return Err("The pattern needs at least one byte".to_owned());
+  }
+  Ok(pattern)
+}
@@ -0,0 +1,95 @@
+use crate::analysis::StringEncoding;
+use crate::tui::App;
+use ratatui::{prelude::*, widgets::*};
+
+const ADDRESS_WIDTH: usize = 18;
+
+// Strings of the whole file that pass the filter, one per row with where they are and who uses them
+pub fn render_strings(f: &mut Frame, app: &mut App, area: Rect) {
+  let height = area.height.saturating_sub(3) as usize; // borders and the column names
+  if app.string_selected < app.string_scroll {
+    app.string_scroll = app.string_selected;
+  } else if height > 0 && app.string_selected >= app.string_scroll + height {
+    app.string_scroll = app.string_selected + 1 - height;
+  }
+
+  let region_width = app
+    .extracted_strings
+    .iter()
+    .map(|x| x.region.len())
+    .max()
+    .unwrap_or(0)
+    .max("Region".len());
+  let mut lines = vec![Line::from(
+    format!(
+      "{:<8}  {:<region_width$}  {:>ADDRESS_WIDTH$}  {}  {:>4}  Text",
+      "Offset", "Region", "Address", "Enc", "Refs"
+    )
+    .dark_gray(),
+  )];
+  lines.extend(
+    app
+      .string_rows
+      .iter()
+      .enumerate()
+      .skip(app.string_scroll)
+      .take(height)
+      .map(|(row, index)| {
+        let string = &app.extracted_strings[*index];
+        let address = match string.address {
+          Some(address) => format!("{:#x}", address),
+          None => "-".to_owned(),
+        };
+        let encoding = match string.encoding {
+          StringEncoding::Ascii => "ascii",
+          StringEncoding::Utf16Le => "utf16",
+        };
+        let references = match string.references.len() {
+          0 => String::new(),
+          count => count.to_string(),
+        };
+        let mut parts = vec![
+          format!("{:08x}  ", string.offset).green(),
+          format!("{:<region_width$}  ", string.region).blue(),
+          format!("{:>ADDRESS_WIDTH$}  ", address).green(),
+          format!("{:<5}  ", encoding).dark_gray(),
+          format!("{:>4}  ", references).light_magenta(),
+          escape_controls(&string.text).yellow(),
+        ];
+        if row == app.string_selected {
+          parts = parts.into_iter().map(|x| x.on_dark_gray()).collect();
+        }
+        Line::from(parts)
+      }),
+  );
+
+  let filter = if app.string_filter.is_empty() {
+    String::new()
+  } else {
+    format!(", matching \"{}\"", app.string_filter)
+  };
+  let p = Paragraph::new(lines)
+    .block(
+      Block::default()
+        .title(format!(
+          " Strings ({} of {}, at least {} characters{}) ",
+          app.string_rows.len(),
+          app.extracted_strings.len(),
+          app.string_min_length,
+          filter
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::White))
+        .padding(Padding::new(1, 0, 0, 0)),
+    )
+    .white();
+  f.render_widget(p, area);
+}
+
+// one row per string, so line breaks and tabs are written out
+fn escape_controls(text: &str) -> String {
+  text
+    .replace('\r', "\\r")
+    .replace('\n', "\\n")
+    .replace('\t', "\\t")
+}
@@ -0,0 +1,108 @@
+use crate::analysis::{self, Xref};
+use crate::tui::App;
+use ratatui::{prelude::*, widgets::*};
+
+// The references listed by the popup, opened with x from the top instruction of the listing
+pub struct XrefList {
+  pub target: u64,
+  pub rows: Vec<XrefRow>,
+  pub selected: usize,
+}
+
+pub struct XrefRow {
+  pub xref: Xref,
+  pub text: String, // The referencing instruction, formatted when the popup opens
+}
+
+pub fn xref_list(app: &mut App, target: u64) -> XrefList {
+  let instructions = &app.data.text_section.data;
+  let rows = app
+    .xrefs
+    .references_to(target)
+    .iter()
+    .map(|xref| XrefRow {
+      xref: *xref,
+      text: match analysis::instruction_index(instructions, xref.from) {
+        Some(index) => {
+          analysis::format_instruction(app.formatter.as_mut(), &instructions[index].instr)
+        }
+        None => String::new(),
+      },
+    })
+    .collect();
+  XrefList {
+    target,
+    rows,
+    selected: 0,
+  }
+}
+
+pub fn render_xrefs(f: &mut Frame, app: &App, list: &XrefList, area: Rect) {
+  let lines = if list.rows.is_empty() {
+    vec![Line::from(vec!["No references".white()])]
+  } else {
+    list
+      .rows
+      .iter()
+      .enumerate()
+      .map(|(i, row)| {
+        let xref = &row.xref;
+        let kind: &'static str = xref.kind.into();
+        let location = match app.function_at(xref.from) {
+          Some(function) => format!(
+            "{}+{:#x}",
+            app.function_name(function),
+            xref.from - function.start
+          ),
+          None => String::new(),
+        };
+        let parts = vec![
+          format!("{:#10x}", xref.from).green(),
+          format!("  {:<10} ", kind).blue(),
+          format!("{:<32} ", location).white(),
+          row.text.clone().yellow(),
+        ];
+        if i == list.selected {
+          Line::from(
+            parts
+              .into_iter()
+              .map(|x| x.on_dark_gray())
+              .collect::<Vec<Span>>(),
+          )
+        } else {
+          Line::from(parts)
+        }
+      })
+      .collect::<Vec<Line>>()
+  };
+
+  // keep the popup no larger than its contents, borders take a row on each side
+  let height = (lines.len() as u16 + 2).min(area.height);
+  let width = area.width * 4 / 5;
+  let popup = Rect {
+    x: area.x + (area.width - width) / 2,
+    y: area.y + (area.height - height) / 2,
+    width,
+    height,
+  };
+
+  let offset = list
+    .selected
+    .saturating_sub((height as usize).saturating_sub(3));
+  let p = Paragraph::new(lines)
+    .scroll((offset as u16, 0))
+    .block(
+      Block::default()
+        .title(match app.symbols.name(list.target) {
+          Some(name) => format!(" References to {} ({}) ", name, list.rows.len()),
+          None => format!(" References to {:#x} ({}) ", list.target, list.rows.len()),
+        })
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow))
+        .padding(Padding::new(1, 0, 0, 0)),
+    )
+    .white()
+    .on_black();
+  f.render_widget(Clear, popup);
+  f.render_widget(p, popup);
+}